@@ -38,12 +38,13 @@ pub fn groth16_aggregation() {
 
     let mut prover_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
     prover_transcript.append(b"public-inputs", &all_inputs);
-    let aggregate_proof = snarkpack::aggregate_proofs(&prover_srs, &mut prover_transcript, &proofs)
-        .expect("error in aggregation");
+    let aggregate_proof =
+        snarkpack::aggregate_proofs_unchecked(&prover_srs, &mut prover_transcript, &proofs)
+            .expect("error in aggregation");
 
     let mut ver_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
     ver_transcript.append(b"public-inputs", &all_inputs);
-    snarkpack::verify_aggregate_proof(
+    snarkpack::verify_aggregate_proof_unchecked(
         &ver_srs,
         &pvk,
         &all_inputs,
@@ -0,0 +1,218 @@
+use ark_ec::pairing::Pairing;
+use ark_groth16::{Proof, VerifyingKey};
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{
+    inputs::PublicInputs,
+    pairing_check::PairingCheck,
+    policy::VerificationPolicy,
+    prepared::PreparedAggregateVerifier,
+    proof::AggregateProof,
+    prover::aggregate_proofs,
+    srs::{ProverSRS, VerifierSRS},
+    transcript::{new_merlin_transcript, Transcript},
+    verifier::{pending_check, VerifierKeys},
+};
+use crate::Error;
+
+/// Label of the transcript used by `Aggregator` and `AggregateVerifier` when
+/// none is given to their builder.
+pub const DEFAULT_TRANSCRIPT_LABEL: &[u8] = b"snarkpack-groth16-aggregation";
+
+/// TranscriptPolicy describes how the transcript of an aggregation is created
+/// before the statement is bound in it: its label and an optional context of
+/// the application appended first.
+#[derive(Clone, Debug)]
+struct TranscriptPolicy {
+    label: &'static [u8],
+    context: Option<Vec<u8>>,
+}
+
+impl Default for TranscriptPolicy {
+    fn default() -> Self {
+        Self {
+            label: DEFAULT_TRANSCRIPT_LABEL,
+            context: None,
+        }
+    }
+}
+
+impl TranscriptPolicy {
+    fn transcript(&self) -> impl Transcript + Send {
+        let mut transcript = new_merlin_transcript(self.label);
+        if let Some(context) = &self.context {
+            transcript.append(b"application-context", context);
+        }
+        transcript
+    }
+}
+
+/// Aggregator aggregates Groth16 proofs of a single circuit with the SRS
+/// specialized to the number of proofs. It creates the transcript itself so
+/// that it always matches the one of the `AggregateVerifier` built from the
+/// same options, for example with `Aggregator::verifier`.
+#[derive(Clone, Debug)]
+pub struct Aggregator<E: Pairing> {
+    srs: ProverSRS<E>,
+    vk: VerifyingKey<E>,
+    policy: TranscriptPolicy,
+}
+
+impl<E: Pairing + std::fmt::Debug> Aggregator<E> {
+    pub fn builder(srs: ProverSRS<E>, vk: VerifyingKey<E>) -> AggregatorBuilder<E> {
+        AggregatorBuilder {
+            srs,
+            vk,
+            policy: TranscriptPolicy::default(),
+        }
+    }
+
+    /// Aggregates the proofs, whose number must be the one the SRS is
+    /// specialized to. `inputs` contains the public inputs of each proof.
+    pub fn aggregate(
+        &self,
+        proofs: &[Proof<E>],
+        inputs: &[Vec<E::ScalarField>],
+    ) -> Result<AggregateProof<E>, Error> {
+        let mut transcript = self.policy.transcript();
+        aggregate_proofs(&self.srs, &self.vk, inputs, proofs, &mut transcript)
+    }
+
+    /// Returns the verifier of the proofs created by this aggregator.
+    pub fn verifier(&self) -> AggregateVerifier<E> {
+        AggregateVerifier {
+            prepared: PreparedAggregateVerifier::new(self.srs.to_verifier_srs(), &self.vk),
+            policy: self.policy.clone(),
+            verification: None,
+        }
+    }
+}
+
+/// Builder of an `Aggregator`.
+pub struct AggregatorBuilder<E: Pairing> {
+    srs: ProverSRS<E>,
+    vk: VerifyingKey<E>,
+    policy: TranscriptPolicy,
+}
+
+impl<E: Pairing> AggregatorBuilder<E> {
+    /// Sets the label of the transcript. It must be specific to the
+    /// application.
+    pub fn transcript_label(mut self, label: &'static [u8]) -> Self {
+        self.policy.label = label;
+        self
+    }
+
+    /// Sets the context of the application appended to the transcript before
+    /// the statement, e.g. a block hash.
+    pub fn context(mut self, context: &[u8]) -> Self {
+        self.policy.context = Some(context.to_vec());
+        self
+    }
+
+    pub fn build(self) -> Aggregator<E> {
+        Aggregator {
+            srs: self.srs,
+            vk: self.vk,
+            policy: self.policy,
+        }
+    }
+}
+
+/// AggregateVerifier verifies the aggregated proofs created by an
+/// `Aggregator` with the same options. It relies on a
+/// `PreparedAggregateVerifier` so it is meant to be reused.
+#[derive(Clone, Debug)]
+pub struct AggregateVerifier<E: Pairing> {
+    prepared: PreparedAggregateVerifier<E>,
+    policy: TranscriptPolicy,
+    verification: Option<VerificationPolicy>,
+}
+
+impl<E: Pairing + std::fmt::Debug> AggregateVerifier<E> {
+    pub fn builder(srs: VerifierSRS<E>, vk: &VerifyingKey<E>) -> AggregateVerifierBuilder<E> {
+        AggregateVerifierBuilder {
+            srs,
+            vk: vk.clone(),
+            policy: TranscriptPolicy::default(),
+            verification: None,
+        }
+    }
+
+    /// Verifies the aggregated proof against the public inputs of each proof.
+    /// The randomness of the verifier is drawn from the operating system.
+    pub fn verify<I: PublicInputs<E::ScalarField> + ?Sized>(
+        &self,
+        proof: &AggregateProof<E>,
+        inputs: &I,
+    ) -> Result<(), Error> {
+        let mut transcript = self.policy.transcript();
+        let rng = StdRng::from_entropy();
+        match &self.verification {
+            Some(policy) => {
+                self.prepared
+                    .verify_with_policy(policy, inputs, proof, rng, &mut transcript)
+            }
+            None => self.prepared.verify(inputs, proof, rng, &mut transcript),
+        }
+    }
+
+    /// Returns the pending pairing check of the aggregated proof, checked
+    /// against the verification policy if any, to be merged with other
+    /// checks of the application before a single final exponentiation. See
+    /// `aggregate_proof_pairing_check`.
+    pub fn pairing_check<I: PublicInputs<E::ScalarField> + ?Sized>(
+        &self,
+        proof: &AggregateProof<E>,
+        inputs: &I,
+    ) -> Result<PairingCheck<E>, Error> {
+        let mut transcript = self.policy.transcript();
+        pending_check(
+            &VerifierKeys::prepared(&self.prepared),
+            self.verification.as_ref(),
+            inputs,
+            proof,
+            StdRng::from_entropy(),
+            &mut transcript,
+        )
+    }
+}
+
+/// Builder of an `AggregateVerifier`.
+pub struct AggregateVerifierBuilder<E: Pairing> {
+    srs: VerifierSRS<E>,
+    vk: VerifyingKey<E>,
+    policy: TranscriptPolicy,
+    verification: Option<VerificationPolicy>,
+}
+
+impl<E: Pairing + std::fmt::Debug> AggregateVerifierBuilder<E> {
+    /// Sets the label of the transcript, which must be the one of the
+    /// aggregator.
+    pub fn transcript_label(mut self, label: &'static [u8]) -> Self {
+        self.policy.label = label;
+        self
+    }
+
+    /// Sets the context of the application appended to the transcript before
+    /// the statement, which must be the one of the aggregator.
+    pub fn context(mut self, context: &[u8]) -> Self {
+        self.policy.context = Some(context.to_vec());
+        self
+    }
+
+    /// Sets the policy the proofs are checked against before being verified,
+    /// failing fast on untrusted proofs.
+    pub fn verification_policy(mut self, policy: VerificationPolicy) -> Self {
+        self.verification = Some(policy);
+        self
+    }
+
+    pub fn build(self) -> AggregateVerifier<E> {
+        AggregateVerifier {
+            prepared: PreparedAggregateVerifier::new(self.srs, &self.vk),
+            policy: self.policy,
+            verification: self.verification,
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Compatibility mode with the aggregation of
+//! [bellperson](https://github.com/filecoin-project/bellperson), the
+//! implementation of SnarkPack used by Filecoin, for the BLS12 curves.
+//!
+//! Both implementations run the same protocol, but bellperson derives its
+//! challenges from its own transcript and encodes the elements of the target
+//! group compressed on the torus, in the layout of `AggregateProof::write_torus`
+//! but without encoding of the identity. The functions of this module aggregate
+//! and verify proofs with the transcript of bellperson, and read and write
+//! aggregate proofs, SRS and verifying keys in the formats of bellperson, such
+//! that proofs produced by one implementation are byte for byte the proofs
+//! produced by the other one and can be verified by it. The specialization of
+//! the SRS is the same in both implementations. Groth16 proofs are encoded
+//! the same way in both, as the compressed points A, B and C.
+//!
+//! Both versions of the protocol of bellperson are supported, see
+//! `AggregateVersion`, and checked against fixtures generated by bellperson
+//! 0.21. In both, the public inputs are bound in the transcript through
+//! `transcript_include` only: callers who need the public inputs in the
+//! transcript can include their encoding in `transcript_include`.
+use ark_ec::{
+    bls12::{Bls12, Bls12Config},
+    pairing::Pairing,
+    AffineRepr,
+};
+use ark_ff::{Field, PrimeField};
+use ark_groth16::{PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::io::{Read, Write};
+use ark_std::{rand::Rng, string::ToString, vec::Vec};
+use sha2::{Digest, Sha256};
+
+use super::{
+    inputs::PublicInputs,
+    proof::{check_target_subgroup, AggregateProof},
+    prover::aggregate_proofs_unchecked,
+    srs::{GenericSRS, ProverSRS, VerifierSRS, MAX_SRS_SIZE},
+    torus::TorusCompressible,
+    transcript::Transcript,
+    verifier::verify_aggregate_proof_unchecked,
+};
+use crate::Error;
+
+/// Prefix of all the transcripts of bellperson.
+const PREFIX: &[u8] = b"snarkpack-v1";
+
+/// Version of the aggregation protocol of bellperson. The proofs of both
+/// versions have the same format but not the same challenges, so an aggregate
+/// proof only verifies with the version it was produced with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregateVersion {
+    /// The first round of GIPA is folded with the challenge derived from the
+    /// commitments, and the KZG challenge binds the last challenge of GIPA
+    /// and the final commitment keys.
+    V1,
+    /// The challenges also bind the elements of the first round of GIPA and
+    /// its final values, see `Transcript::bind_gipa_rounds`.
+    V2,
+}
+
+/// Aggregates the proofs exactly like bellperson, with `transcript_include`
+/// bound in the transcript. The resulting proof is the one bellperson produces
+/// for the same SRS, proofs, `transcript_include` and version.
+pub fn aggregate_proofs<P: Bls12Config>(
+    srs: &ProverSRS<Bls12<P>>,
+    transcript_include: &[u8],
+    proofs: &[Proof<Bls12<P>>],
+    version: AggregateVersion,
+) -> Result<AggregateProof<Bls12<P>>, Error> {
+    let mut transcript = BellpersonTranscript::new(transcript_include, version);
+    aggregate_proofs_unchecked(srs, &mut transcript, proofs)
+}
+
+/// Verifies an aggregate proof produced by bellperson, or by
+/// `aggregate_proofs`, with the same `transcript_include` and version as the
+/// prover.
+pub fn verify_aggregate_proof<
+    P: Bls12Config,
+    R: Rng + Send,
+    I: PublicInputs<<Bls12<P> as Pairing>::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<Bls12<P>>,
+    pvk: &PreparedVerifyingKey<Bls12<P>>,
+    public_inputs: &I,
+    proof: &AggregateProof<Bls12<P>>,
+    transcript_include: &[u8],
+    version: AggregateVersion,
+    rng: R,
+) -> Result<(), Error> {
+    let mut transcript = BellpersonTranscript::new(transcript_include, version);
+    verify_aggregate_proof_unchecked(
+        ip_verifier_srs,
+        pvk,
+        public_inputs,
+        proof,
+        rng,
+        &mut transcript,
+    )
+}
+
+/// Transcript of bellperson. Each challenge is derived from a fresh SHA-256
+/// hash of the prefix, a tag and the elements written since the previous
+/// challenge, the tag being given by the label of the challenge:
+/// * `hcom` on the commitments to A, B and C, then `random-r` on this hash and
+///   `transcript_include` for $r$
+/// * `gipa-0` on the hash of the commitments, the aggregated values and $r$
+///   for the challenge of the first round of GIPA, and in the version 2
+///   `gipa-0` again on this challenge and the elements of the first round
+/// * `gipa-i` on the elements of the round for the next rounds
+/// * in the version 2, `gipa-extra-link` on the last challenge, the final
+///   values and the elements of the last round
+/// * `random-z` on the last challenge, or the one of `gipa-extra-link` in the
+///   version 2, and the final commitment keys, followed in the version 2 by
+///   the final values, for the KZG challenge
+/// * the label itself on the elements written since the previous challenge
+///   for any other challenge, which bellperson never derives
+///
+/// The elements are written with their compressed encoding, which is also the
+/// encoding of bellperson in its transcript.
+struct BellpersonTranscript {
+    /// `transcript_include` as written by bellperson, prefixed by its length
+    include: Vec<u8>,
+    /// elements written since the last challenge
+    buffer: Vec<u8>,
+    /// hash of the commitments to A, B and C and the randomness $r$
+    hcom: Vec<u8>,
+    r: Vec<u8>,
+    /// index of the next round of GIPA
+    round: usize,
+    version: AggregateVersion,
+}
+
+impl BellpersonTranscript {
+    fn new(transcript_include: &[u8], version: AggregateVersion) -> Self {
+        let mut include = Vec::with_capacity(8 + transcript_include.len());
+        include.extend_from_slice(&(transcript_include.len() as u64).to_le_bytes());
+        include.extend_from_slice(transcript_include);
+        Self {
+            include,
+            buffer: Vec::new(),
+            hcom: Vec::new(),
+            r: Vec::new(),
+            round: 0,
+            version,
+        }
+    }
+
+    /// Derives a challenge from the hash of the prefix, the tag and the data.
+    /// As in bellperson, a counter is appended to the hash until its digest,
+    /// read in little endian and reduced to the bit size of the field, is a
+    /// field element different from zero and one.
+    fn derive<F: Field>(tag: &[u8], data: &[&[u8]]) -> F {
+        let mut hasher = Sha256::new();
+        hasher.update(PREFIX);
+        hasher.update(tag);
+        data.iter().for_each(|d| hasher.update(d));
+
+        let bits = F::BasePrimeField::MODULUS_BIT_SIZE as usize;
+        let mut repr = vec![0u8; bits.div_ceil(8)];
+        let mut counter = 0u64;
+        loop {
+            counter += 1;
+            hasher.update(counter.to_be_bytes());
+            let digest = hasher.clone().finalize();
+            let len = repr.len().min(digest.len());
+            repr[..len].copy_from_slice(&digest[..len]);
+            *repr.last_mut().unwrap() &= 0xff >> (repr.len() * 8 - bits);
+
+            let challenge = F::BasePrimeField::deserialize_compressed(&repr[..])
+                .ok()
+                .and_then(|c| F::from_base_prime_field_elems(&[c]));
+            match challenge {
+                Some(c) if !c.is_zero() && !c.is_one() => return c,
+                _ => continue,
+            }
+        }
+    }
+
+    fn to_bytes<F: Field>(challenge: &F) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        challenge.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+}
+
+impl Transcript for BellpersonTranscript {
+    /// The transcripts of bellperson are not domain separated.
+    fn domain_sep(&mut self) {}
+
+    fn append<S: CanonicalSerialize>(&mut self, _label: &'static [u8], element: &S) {
+        element.serialize_compressed(&mut self.buffer).unwrap();
+    }
+
+    fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
+        let buffer = ark_std::mem::take(&mut self.buffer);
+        match label {
+            b"r-random-fiatshamir" => {
+                let hcom = Self::derive::<F>(b"hcom", &[&buffer]);
+                self.hcom = Self::to_bytes(&hcom);
+                let r = Self::derive::<F>(b"random-r", &[&self.hcom, &self.include]);
+                self.r = Self::to_bytes(&r);
+                r
+            }
+            b"first-challenge" => {
+                // the version 2 derives the challenge of the first round again
+                self.round = match self.version {
+                    AggregateVersion::V1 => 1,
+                    AggregateVersion::V2 => 0,
+                };
+                Self::derive(b"gipa-0", &[&self.hcom, &buffer, &self.r])
+            }
+            b"challenge_i" => {
+                let tag = format!("gipa-{}", self.round);
+                self.round += 1;
+                Self::derive(tag.as_bytes(), &[&buffer])
+            }
+            b"link-challenge" => Self::derive(b"gipa-extra-link", &[&buffer]),
+            b"z-challenge" => Self::derive(b"random-z", &[&buffer]),
+            // bellperson derives no other challenge, the label serves as tag
+            _ => Self::derive(label, &[&buffer]),
+        }
+    }
+
+    fn bind_gipa_rounds(&self) -> bool {
+        self.version == AggregateVersion::V2
+    }
+}
+
+type TargetField<P> = <Bls12<P> as Pairing>::TargetField;
+type CompressedTarget<P> = <TargetField<P> as TorusCompressible>::Compressed;
+
+/// Writes an element $x = c_0 + c_1 w$ of the target group compressed on the
+/// torus as $(c_0 + 1) / c_1$, like bellperson, which cannot compress the
+/// identity.
+fn write_target<P: Bls12Config, W: Write>(x: &TargetField<P>, out: W) -> Result<(), Error> {
+    x.torus_compress()
+        .ok_or_else(|| Error::InvalidProof("target group element not compressible".to_string()))?
+        .serialize_compressed(out)?;
+    Ok(())
+}
+
+/// Reads an element $b$ of the torus and decompresses it to $(b + w) / (b - w)$,
+/// checking that it is in the prime order subgroup of the target group.
+fn read_target<P: Bls12Config, R: Read>(source: R) -> Result<TargetField<P>, Error> {
+    let b = CompressedTarget::<P>::deserialize_compressed(source)?;
+    let x = TargetField::<P>::torus_decompress(&b);
+    check_target_subgroup::<Bls12<P>>([&x])?;
+    Ok(x)
+}
+
+/// Writes the aggregate proof in the format of bellperson, where the elements
+/// of the target group are compressed on the torus.
+pub fn write_aggregate_proof<P: Bls12Config, W: Write>(
+    proof: &AggregateProof<Bls12<P>>,
+    out: W,
+) -> Result<(), Error> {
+    proof.write_with_target(out, |x, out| write_target::<P, _>(x, out))
+}
+
+/// Reads an aggregate proof written by bellperson or by
+/// `write_aggregate_proof`, checking that all elements are in the prime order
+/// subgroups.
+pub fn read_aggregate_proof<P: Bls12Config, R: Read>(
+    source: R,
+) -> Result<AggregateProof<Bls12<P>>, Error> {
+    AggregateProof::read_with_target(source, |source| read_target::<P, _>(source))
+}
+
+/// Writes the generic SRS in the format of bellperson: the powers of
+/// $g^\alpha$, $g^\beta$, $h^\alpha$ and $h^\beta$, each prefixed by their big
+/// endian length.
+pub fn write_srs<P: Bls12Config, W: Write>(
+    srs: &GenericSRS<Bls12<P>>,
+    mut out: W,
+) -> Result<(), Error> {
+    write_points(&srs.g_alpha_powers, &mut out)?;
+    write_points(&srs.g_beta_powers, &mut out)?;
+    write_points(&srs.h_alpha_powers, &mut out)?;
+    write_points(&srs.h_beta_powers, &mut out)
+}
+
+/// Reads a generic SRS written by bellperson or by `write_srs`.
+pub fn read_srs<P: Bls12Config, R: Read>(mut source: R) -> Result<GenericSRS<Bls12<P>>, Error> {
+    let g_alpha_powers = read_points(&mut source)?;
+    let g_beta_powers = read_points(&mut source)?;
+    let h_alpha_powers = read_points(&mut source)?;
+    let h_beta_powers = read_points(&mut source)?;
+    Ok(GenericSRS {
+        g_alpha_powers,
+        g_beta_powers,
+        h_alpha_powers,
+        h_beta_powers,
+    })
+}
+
+fn write_points<G: AffineRepr, W: Write>(points: &[G], mut out: W) -> Result<(), Error> {
+    let len = u32::try_from(points.len())
+        .map_err(|_| Error::InvalidSRS("SRS len > maximum".to_string()))?;
+    out.write_all(&len.to_be_bytes())
+        .map_err(|e| Error::Serialization(e.into()))?;
+    points
+        .iter()
+        .try_for_each(|p| p.serialize_compressed(&mut out))?;
+    Ok(())
+}
+
+fn read_points<G: AffineRepr, R: Read>(mut source: R) -> Result<Vec<G>, Error> {
+    let len = read_len(&mut source)?;
+    if len > MAX_SRS_SIZE {
+        return Err(Error::InvalidSRS("SRS len > maximum".to_string()));
+    }
+    (0..len)
+        .map(|_| G::deserialize_compressed(&mut source).map_err(Error::from))
+        .collect()
+}
+
+fn read_len<R: Read>(mut source: R) -> Result<usize, Error> {
+    let mut len = [0u8; 4];
+    source
+        .read_exact(&mut len)
+        .map_err(|e| Error::Serialization(e.into()))?;
+    Ok(u32::from_be_bytes(len) as usize)
+}
+
+/// Reads a Groth16 verifying key written by bellperson, whose points are not
+/// compressed. The points $g^\beta$ and $g^\delta$ of bellperson are checked
+/// but discarded, as only the verifier of bellperson uses them.
+pub fn read_verifying_key<P: Bls12Config, R: Read>(
+    mut source: R,
+) -> Result<VerifyingKey<Bls12<P>>, Error> {
+    let (alpha_g1, _beta_g1, beta_g2): (_, <Bls12<P> as Pairing>::G1Affine, _) =
+        CanonicalDeserialize::deserialize_uncompressed(&mut source)?;
+    let (gamma_g2, _delta_g1, delta_g2): (_, <Bls12<P> as Pairing>::G1Affine, _) =
+        CanonicalDeserialize::deserialize_uncompressed(&mut source)?;
+    let len = read_len(&mut source)?;
+    let gamma_abc_g1 = (0..len)
+        .map(|_| CanonicalDeserialize::deserialize_uncompressed(&mut source))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(VerifyingKey {
+        alpha_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g2,
+        gamma_abc_g1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Config, G1Projective, G2Projective};
+    use ark_ff::{One, Zero};
+    use ark_std::UniformRand;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_torus_compression() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let x = Bls12_381::pairing(G1Projective::rand(&mut rng), G2Projective::rand(&mut rng)).0;
+        let mut bytes = Vec::new();
+        write_target::<Config, _>(&x, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 288);
+        assert_eq!(read_target::<Config, _>(&bytes[..]).unwrap(), x);
+
+        // the identity has no torus representation
+        assert!(write_target::<Config, _>(&TargetField::<Config>::one(), &mut bytes).is_err());
+
+        // decompressing the zero element of the torus gives -1, outside of
+        // the prime order subgroup
+        let mut bytes = Vec::new();
+        CompressedTarget::<Config>::zero()
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+        assert!(read_target::<Config, _>(&bytes[..]).is_err());
+    }
+}
@@ -4,10 +4,12 @@ use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
 use ark_ff::CyclotomicMultSubgroup;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{
+    cfg_iter,
     fmt::Debug,
     ops::{AddAssign, MulAssign},
     vec::Vec,
 };
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 /// This module implements two binding commitment schemes used in the Groth16
 /// aggregation.
@@ -74,11 +76,9 @@ where
         if self.a.len() != s_vec.len() {
             return Err(Error::InvalidKeyLength);
         }
-        let (a, b) = self
-            .a
-            .par_iter()
-            .zip(self.b.par_iter())
-            .zip(s_vec.par_iter())
+        let (a, b) = cfg_iter!(self.a)
+            .zip(cfg_iter!(self.b))
+            .zip(cfg_iter!(s_vec))
             .map(|((ap, bp), si)| {
                 let v1s = ap.mul(si).into_affine();
                 let v2s = bp.mul(si).into_affine();
@@ -113,12 +113,10 @@ where
         if left.a.len() != right.a.len() {
             return Err(Error::InvalidKeyLength);
         }
-        let (a, b): (Vec<G>, Vec<G>) = left
-            .a
-            .par_iter()
-            .zip(left.b.par_iter())
-            .zip(right.a.par_iter())
-            .zip(right.b.par_iter())
+        let (a, b): (Vec<G>, Vec<G>) = cfg_iter!(left.a)
+            .zip(cfg_iter!(left.b))
+            .zip(cfg_iter!(right.a))
+            .zip(cfg_iter!(right.b))
             .map(|(((left_a, left_b), right_a), right_b)| {
                 let mut ra = right_a.mul(scale);
                 let mut rb = right_b.mul(scale);
@@ -0,0 +1,481 @@
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_groth16::{PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{rand::Rng, string::ToString, vec::Vec};
+use sha2::{Digest, Sha256};
+
+use super::{
+    proof::CommittedAggregateProof,
+    prover::aggregate_proofs_with_extensions,
+    srs::{ProverSRS, VerifierSRS},
+    statement::bind_statement,
+    transcript::Transcript,
+    verifier::{
+        verify_aggregate_proof_with_extensions, EquationPoint, Extension, Extensions, VerifierKeys,
+    },
+};
+use crate::Error;
+
+/// CommittedProof is a Groth16 proof extended with a Pedersen commitment $D$
+/// to some of the witness and a proof of knowledge of its opening, as output
+/// by gnark or LegoGroth16 style provers. Such a proof is valid when
+/// $e(A,B) = e(\alpha,\beta) e(IC(x, H(D)) + D, \gamma) e(C, \delta)$, where the
+/// last public input $H(D)$ is given by `commitment_input`, and the proof of
+/// knowledge is valid under the `CommitmentKey`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommittedProof<E: Pairing> {
+    pub proof: Proof<E>,
+    pub d: E::G1Affine,
+    pub pok: E::G1Affine,
+}
+
+/// CommitmentKey is the part of the verifying key checking the proof of
+/// knowledge of the commitment, i.e. $e(D, g_{-\sigma}) e(PoK, g) = 1$.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CommitmentKey<E: Pairing> {
+    pub g: E::G2Affine,
+    pub g_sigma_neg: E::G2Affine,
+}
+
+/// Domain separation tag of the hash of the commitments, the one of gnark.
+const COMMITMENT_DST: &[u8] = b"bsb22-commitment";
+
+/// Returns the public input derived from the commitment $D$ of a proof, which
+/// the Groth16 verifying key expects after all the other public inputs. As
+/// gnark does for commitments to private values only, it is the hash to the
+/// scalar field of the uncompressed big endian encoding of $D$, i.e. $x$ then
+/// $y$, following `hash_to_field` of RFC 9380 with `expand_message_xmd` and
+/// SHA-256.
+pub fn commitment_input<E: Pairing>(d: &E::G1Affine) -> E::ScalarField {
+    type BaseField<E> = <<E as Pairing>::G1Affine as AffineRepr>::BaseField;
+    let elem_size =
+        (<BaseField<E> as Field>::BasePrimeField::MODULUS_BIT_SIZE as usize).div_ceil(8);
+    let coordinate_size = BaseField::<E>::extension_degree() as usize * elem_size;
+    let mut msg = Vec::with_capacity(2 * coordinate_size);
+    match d.xy() {
+        Some((x, y)) => {
+            for c in x
+                .to_base_prime_field_elements()
+                .chain(y.to_base_prime_field_elements())
+            {
+                let bytes = c.into_bigint().to_bytes_be();
+                msg.extend_from_slice(&bytes[bytes.len() - elem_size..]);
+            }
+        }
+        // the flag of the uncompressed point at infinity
+        None => {
+            msg.resize(2 * coordinate_size, 0);
+            msg[0] = 0x40;
+        }
+    }
+    let len = (E::ScalarField::MODULUS_BIT_SIZE as usize + 128).div_ceil(8);
+    E::ScalarField::from_be_bytes_mod_order(&expand_message_xmd(&msg, COMMITMENT_DST, len))
+}
+
+/// `expand_message_xmd` of RFC 9380 with SHA-256, for outputs of at most 255
+/// blocks.
+fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let ell = len.div_ceil(32);
+    assert!(ell <= 255 && dst.len() <= 255);
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+    let b_0 = Sha256::new()
+        .chain_update([0u8; 64])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Sha256::new()
+        .chain_update(b_0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = b_i.to_vec();
+    for i in 2..=ell {
+        let xored = b_0.iter().zip(&b_i).map(|(a, b)| a ^ b).collect::<Vec<_>>();
+        b_i = Sha256::new()
+            .chain_update(xored)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len);
+    uniform_bytes
+}
+
+/// Returns the public inputs of each proof followed by the input derived from
+/// its commitment.
+fn committed_inputs<E: Pairing>(
+    public_inputs: &[Vec<E::ScalarField>],
+    commitments: &[E::G1Affine],
+) -> Result<Vec<Vec<E::ScalarField>>, Error> {
+    if public_inputs.len() != commitments.len() {
+        return Err(Error::InvalidProof(
+            "public inputs len != number of proofs".to_string(),
+        ));
+    }
+    Ok(public_inputs
+        .iter()
+        .zip(commitments)
+        .map(|(inputs, d)| {
+            let mut inputs = inputs.clone();
+            inputs.push(commitment_input::<E>(d));
+            inputs
+        })
+        .collect())
+}
+
+/// Aggregate `n` Groth16 proofs extended with a Pedersen commitment, where `n`
+/// must be a power of two. The commitments are sent with the aggregate proof
+/// and their proofs of knowledge are proven with an extra MIPP alongside C.
+/// The public inputs must not contain the inputs derived from the
+/// commitments, which are appended to the inputs of each proof as the
+/// verifying key expects. The statement is bound in the transcript as in
+/// `aggregate_proofs`, followed by the commitment key and the commitments.
+pub fn aggregate_committed_proofs<E: Pairing, T: Transcript>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    ck: &CommitmentKey<E>,
+    public_inputs: &[Vec<E::ScalarField>],
+    proofs: &[CommittedProof<E>],
+    transcript: &mut T,
+) -> Result<CommittedAggregateProof<E>, Error> {
+    let commitments = proofs.iter().map(|p| p.d).collect::<Vec<_>>();
+    let inputs = committed_inputs::<E>(public_inputs, &commitments)?;
+    bind_statement(transcript, vk, &srs.to_verifier_srs(), &inputs)?;
+    transcript.append_point(b"commitment-key-g", &ck.g);
+    transcript.append_point(b"commitment-key-g-sigma-neg", &ck.g_sigma_neg);
+    transcript.append(b"commitments", &commitments);
+
+    let groth_proofs = proofs.iter().map(|p| p.proof.clone()).collect::<Vec<_>>();
+    let extras = vec![proofs.iter().map(|p| p.pok).collect::<Vec<_>>()];
+    let (proof, mut extensions, _) =
+        aggregate_proofs_with_extensions(srs, transcript, &groth_proofs, &extras, None)?;
+    let (com_pok, agg_pok, mipp_pok) = extensions.pop().unwrap();
+    Ok(CommittedAggregateProof {
+        proof,
+        commitments,
+        com_pok,
+        agg_pok,
+        mipp_pok,
+    })
+}
+
+/// Verifies an aggregation of Groth16 proofs extended with a Pedersen
+/// commitment. On top of the checks of `verify_aggregate_proof`, it appends
+/// the input derived from each commitment to the public inputs of its proof,
+/// checks the MIPP proof of the proofs of knowledge, adds $D^r$, computed from
+/// the commitments, to the public inputs term of the Groth16 equation and
+/// checks the aggregated proof of knowledge $e(D^r, g_{-\sigma}) e(PoK^r, g) =
+/// 1$.
+pub fn verify_committed_aggregate_proof<E: Pairing, R: Rng + Send, T: Transcript + Send>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    ck: &CommitmentKey<E>,
+    public_inputs: &[Vec<E::ScalarField>],
+    proof: &CommittedAggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    proof.parsing_check()?;
+    let inputs = committed_inputs::<E>(public_inputs, &proof.commitments)?;
+    bind_statement(transcript, &pvk.vk, ip_verifier_srs, &inputs)?;
+    transcript.append_point(b"commitment-key-g", &ck.g);
+    transcript.append_point(b"commitment-key-g-sigma-neg", &ck.g_sigma_neg);
+    transcript.append(b"commitments", &proof.commitments);
+
+    let ext = Extensions {
+        mipps: vec![Extension {
+            com: &proof.com_pok,
+            agg: &proof.agg_pok,
+            mipp: &proof.mipp_pok,
+        }],
+        ic: Some(&proof.commitments),
+        equations: vec![vec![
+            (EquationPoint::Ic, ck.g_sigma_neg),
+            (EquationPoint::Point(proof.agg_pok), ck.g),
+        ]],
+        inputs: None,
+    };
+    verify_aggregate_proof_with_extensions(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        &inputs,
+        &proof.proof,
+        &ext,
+        rng,
+        transcript,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        srs,
+        transcript::{new_merlin_transcript, MerlinTranscript},
+    };
+    use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Projective};
+    use ark_ec::{AffineRepr, CurveGroup, Group};
+    use ark_ff::{Field, One};
+    use ark_groth16::prepare_verifying_key;
+    use ark_std::UniformRand;
+    use rand_core::SeedableRng;
+
+    /// Creates a verifying key and proofs extended with a commitment from
+    /// known trapdoors, without any circuit.
+    fn fake_committed_proofs<R: Rng>(
+        rng: &mut R,
+        n: usize,
+        inputs: &[Fr],
+    ) -> (
+        VerifyingKey<Bn254>,
+        CommitmentKey<Bn254>,
+        Vec<CommittedProof<Bn254>>,
+    ) {
+        let g = G1Projective::generator();
+        let h = G2Projective::generator();
+        let (alpha, beta, gamma, delta) =
+            (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+        // the last input is the one derived from the commitment
+        let ic = (0..inputs.len() + 2)
+            .map(|_| Fr::rand(rng))
+            .collect::<Vec<_>>();
+        let ic_x = ic[0] + ic[1..].iter().zip(inputs).map(|(i, x)| *i * x).sum::<Fr>();
+        let vk = VerifyingKey::<Bn254> {
+            alpha_g1: (g * alpha).into_affine(),
+            beta_g2: (h * beta).into_affine(),
+            gamma_g2: (h * gamma).into_affine(),
+            delta_g2: (h * delta).into_affine(),
+            gamma_abc_g1: ic.iter().map(|i| (g * i).into_affine()).collect(),
+        };
+        let (t, sigma) = (Fr::rand(rng), Fr::rand(rng));
+        let ck = CommitmentKey::<Bn254> {
+            g: (h * t).into_affine(),
+            g_sigma_neg: (h * (-sigma * t)).into_affine(),
+        };
+        let proofs = (0..n)
+            .map(|_| {
+                let (a, b, d) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+                let d_g = (g * d).into_affine();
+                let ic_x = ic_x + ic[inputs.len() + 1] * commitment_input::<Bn254>(&d_g);
+                // ab = alpha beta + (IC(x, H(D)) + d) gamma + c delta
+                let c = (a * b - alpha * beta - (ic_x + d) * gamma) * delta.inverse().unwrap();
+                CommittedProof {
+                    proof: Proof {
+                        a: (g * a).into_affine(),
+                        b: (h * b).into_affine(),
+                        c: (g * c).into_affine(),
+                    },
+                    d: d_g,
+                    pok: (g * (d * sigma)).into_affine(),
+                }
+            })
+            .collect();
+        (vk, ck, proofs)
+    }
+
+    /// Merlin transcript binding every round of GIPA.
+    struct BoundTranscript(MerlinTranscript);
+
+    impl Transcript for BoundTranscript {
+        fn domain_sep(&mut self) {
+            self.0.domain_sep();
+        }
+
+        fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+            self.0.append(label, element);
+        }
+
+        fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
+            self.0.challenge_scalar(label)
+        }
+
+        fn bind_gipa_rounds(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        let unhex = |s: &str| {
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>()
+        };
+        // test vectors of RFC 9380
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        assert_eq!(
+            expand_message_xmd(b"", dst, 0x20),
+            unhex("68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235")
+        );
+        assert_eq!(
+            expand_message_xmd(b"abc", dst, 0x20),
+            unhex("d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615")
+        );
+        assert_eq!(
+            expand_message_xmd(b"", dst, 0x80),
+            unhex(concat!(
+                "af84c27ccfd45d41914fdff5df25293e221afc53d8ad2ac06d5e3e29485dadbe",
+                "e0d121587713a3e0dd4d5e69e93eb7cd4f5df4cd103e188cf60cb02edc3edf18",
+                "eda8576c412b18ffb658e3dd6ec849469b979d444cf7b26911a08e63cf31f9dc",
+                "c541708d3491184472c2c29bb749d4286b004ceb5ee6b9a7fa5b646c993f0ced"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_committed_aggregation() {
+        let n = 8;
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let inputs = vec![Fr::one(), Fr::from(2u64)];
+        let all_inputs = vec![inputs.clone(); n];
+        let (vk, ck, proofs) = fake_committed_proofs(&mut rng, n, &inputs);
+        let pvk = prepare_verifying_key(&vk);
+        let generic = srs::setup_fake_srs::<Bn254, _>(&mut rng, n);
+        let (prover_srs, ver_srs) = generic.specialize(n);
+
+        let mut transcript = new_merlin_transcript(b"test");
+        let proof = aggregate_committed_proofs(
+            &prover_srs,
+            &vk,
+            &ck,
+            &all_inputs,
+            &proofs,
+            &mut transcript,
+        )
+        .unwrap();
+        let mut transcript = new_merlin_transcript(b"test");
+        verify_committed_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            &ck,
+            &all_inputs,
+            &proof,
+            &mut rng,
+            &mut transcript,
+        )
+        .expect("valid proof");
+
+        let mut buffer = Vec::new();
+        proof.write(&mut buffer).unwrap();
+        let read = CommittedAggregateProof::<Bn254>::read(&buffer[..]).unwrap();
+        assert_eq!(proof, read);
+
+        // the commitments are bound to the proofs by their derived inputs
+        let mut swapped = proof.clone();
+        swapped.commitments.swap(0, 1);
+        let mut transcript = new_merlin_transcript(b"test");
+        verify_committed_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            &ck,
+            &all_inputs,
+            &swapped,
+            &mut rng,
+            &mut transcript,
+        )
+        .expect_err("swapped commitments");
+
+        // an invalid proof of knowledge must be rejected
+        let mut invalid = proofs.clone();
+        invalid[3].pok = (invalid[3].pok + G1Affine::generator()).into_affine();
+        let mut transcript = new_merlin_transcript(b"test");
+        let proof = aggregate_committed_proofs(
+            &prover_srs,
+            &vk,
+            &ck,
+            &all_inputs,
+            &invalid,
+            &mut transcript,
+        )
+        .unwrap();
+        let mut transcript = new_merlin_transcript(b"test");
+        verify_committed_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            &ck,
+            &all_inputs,
+            &proof,
+            &mut rng,
+            &mut transcript,
+        )
+        .expect_err("invalid proof of knowledge");
+
+        // so must a commitment that doesn't satisfy the Groth16 equation, even
+        // with a valid proof of knowledge
+        let mut invalid = proofs;
+        invalid[5].d = (invalid[5].d * Fr::from(2u64)).into_affine();
+        invalid[5].pok = (invalid[5].pok * Fr::from(2u64)).into_affine();
+        let mut transcript = new_merlin_transcript(b"test");
+        let proof = aggregate_committed_proofs(
+            &prover_srs,
+            &vk,
+            &ck,
+            &all_inputs,
+            &invalid,
+            &mut transcript,
+        )
+        .unwrap();
+        let mut transcript = new_merlin_transcript(b"test");
+        verify_committed_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            &ck,
+            &all_inputs,
+            &proof,
+            &mut rng,
+            &mut transcript,
+        )
+        .expect_err("invalid commitment");
+    }
+
+    #[test]
+    fn test_committed_aggregation_bound_rounds() {
+        let n = 4;
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(1u64);
+        let inputs = vec![Fr::one(), Fr::from(2u64)];
+        let all_inputs = vec![inputs.clone(); n];
+        let (vk, ck, proofs) = fake_committed_proofs(&mut rng, n, &inputs);
+        let pvk = prepare_verifying_key(&vk);
+        let generic = srs::setup_fake_srs::<Bn254, _>(&mut rng, n);
+        let (prover_srs, ver_srs) = generic.specialize(n);
+
+        let mut transcript = BoundTranscript(MerlinTranscript::new(b"test"));
+        let proof = aggregate_committed_proofs(
+            &prover_srs,
+            &vk,
+            &ck,
+            &all_inputs,
+            &proofs,
+            &mut transcript,
+        )
+        .unwrap();
+        let mut transcript = BoundTranscript(MerlinTranscript::new(b"test"));
+        verify_committed_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            &ck,
+            &all_inputs,
+            &proof,
+            &mut rng,
+            &mut transcript,
+        )
+        .expect("valid proof");
+
+        // the challenges differ from the ones of the unbound transcript
+        let mut transcript = MerlinTranscript::new(b"test");
+        verify_committed_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            &ck,
+            &all_inputs,
+            &proof,
+            &mut rng,
+            &mut transcript,
+        )
+        .expect_err("unbound transcript");
+    }
+}
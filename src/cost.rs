@@ -0,0 +1,332 @@
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_serialize::{CanonicalSerialize, Compress};
+use ark_std::{marker::PhantomData, mem::size_of, string::ToString, time::Duration, vec::Vec, One};
+#[cfg(feature = "std")]
+use ark_std::{rand::Rng, UniformRand};
+
+use super::{
+    srs::MAX_SRS_SIZE,
+    torus::{torus_size, TorusCompressible},
+};
+use crate::Error;
+
+/// Number of elements of the multi-scalar multiplications timed by
+/// `Calibration::measure`.
+pub const CALIBRATION_MSM_SIZE: usize = 256;
+
+/// CostEstimator predicts the size of an aggregate proof and the work of the
+/// prover and of the verifier for a curve, a number of proofs and a number of
+/// public inputs per proof, without running any of them.
+#[derive(Clone, Copy, Debug)]
+pub struct CostEstimator<E: Pairing> {
+    nproofs: usize,
+    ninputs: usize,
+    _e: PhantomData<E>,
+}
+
+impl<E: Pairing> CostEstimator<E> {
+    /// Returns the estimator of the aggregation of `nproofs` proofs with
+    /// `ninputs` public inputs each. The number of proofs must be a power of
+    /// two supported by the SRS.
+    pub fn new(nproofs: usize, ninputs: usize) -> Result<Self, Error> {
+        if !(2..=MAX_SRS_SIZE).contains(&nproofs) || !nproofs.is_power_of_two() {
+            return Err(Error::InvalidProof(
+                "number of proofs must be a power of two within the SRS bounds".to_string(),
+            ));
+        }
+        Ok(Self {
+            nproofs,
+            ninputs,
+            _e: PhantomData,
+        })
+    }
+
+    fn log_proofs(&self) -> usize {
+        ark_std::log2(self.nproofs) as usize
+    }
+
+    /// Returns the exact size in bytes of the serialized `AggregateProof`.
+    pub fn proof_size(&self, compress: Compress) -> usize {
+        let gt = E::TargetField::one().serialized_size(compress);
+        self.size_with_target(compress, gt)
+    }
+
+    fn size_with_target(&self, compress: Compress, gt: usize) -> usize {
+        let g1 = E::G1Affine::generator().serialized_size(compress);
+        let g2 = E::G2Affine::generator().serialized_size(compress);
+        let l = self.log_proofs();
+        // com_ab, com_c, ip_ab and agg_c, then the GIPA proof: the number of
+        // proofs, comms_ab, comms_c, z_ab and z_c of log n rounds and the
+        // final values of the loop, and the KZG openings of v and w
+        (5 * gt + g1)
+            + (0u32.serialized_size(compress) + l * (10 * gt + 2 * g1) + 4 * g1 + 3 * g2)
+            + (2 * g2 + 2 * g1)
+    }
+
+    /// Returns the length of each vector of powers of the `GenericSRS` needed
+    /// to specialize it to the number of proofs, i.e. twice the number of
+    /// proofs. `setup_fake_srs` must be called with the number of proofs.
+    pub fn generic_srs_len(&self) -> usize {
+        2 * self.nproofs
+    }
+
+    /// Returns the work of `aggregate_proofs`.
+    pub fn prover(&self) -> ProverCost {
+        let n = self.nproofs;
+        let l = self.log_proofs();
+        // sizes of the vectors at each round of GIPA
+        let rounds = (0..l).map(|i| n >> i);
+        let g1_msms = ark_std::iter::once(n)
+            .chain(rounds.clone().flat_map(|m| [m / 2, m / 2]))
+            .chain([2 * n, 2 * n])
+            .collect();
+        let (g1, g2, fr) = (
+            size_of::<E::G1Affine>(),
+            size_of::<E::G2Affine>(),
+            size_of::<E::ScalarField>(),
+        );
+        ProverCost {
+            // commitments to A, B and C, A * B^r, then each round of GIPA
+            // commits to both halves of A, B and C and pairs them
+            miller_loops: 7 * n + rounds.clone().map(|m| 7 * m).sum::<usize>(),
+            final_exponentiations: 7 + 14 * l,
+            g1_msms,
+            g2_msms: vec![n, n],
+            // w^{r^{-1}}, then the compression of A, C and w at each round
+            g1_muls: 2 * n + rounds.clone().map(|m| 2 * m).sum::<usize>(),
+            // B^r, then the compression of B and v at each round
+            g2_muls: n + rounds.map(|m| 3 * m / 2).sum::<usize>(),
+            // A, B, C, B^r, r, r^{-1} and w^{r^{-1}} along with the vectors and
+            // keys of the first round of GIPA and their compressed halves
+            peak_memory: 9 * n * g1 + 6 * n * g2 + 3 * n * fr,
+            srs_memory: 6 * n * g1 + 4 * n * g2,
+        }
+    }
+
+    /// Returns the work of `verify_aggregate_proof`, or of a
+    /// `PreparedAggregateVerifier` if `prepared` is true.
+    pub fn verifier(&self, prepared: bool) -> VerifierCost {
+        let l = self.log_proofs();
+        let prepared = prepared as usize;
+        VerifierCost {
+            // 7 for TIPP and MIPP, 8 for the KZG openings of v and w and 3 for
+            // the Groth16 equation, whose e(alpha, beta) is prepared
+            miller_loops: 18 - prepared,
+            final_exponentiations: 1,
+            // rescaling of the 10 commitments and products of each round,
+            // randomization of the 5 checks of TIPP and MIPP not equal to one
+            gt_exponentiations: 10 * l + 5 + prepared,
+            // rescaling of z_c and final C^r, randomization of the checks,
+            // KZG openings and alpha^{r_sum}
+            g1_muls: 2 * l + 21 - prepared,
+            g2_muls: 4,
+            g1_msm: self.ninputs + 1,
+        }
+    }
+}
+
+impl<E: Pairing> CostEstimator<E>
+where
+    E::TargetField: TorusCompressible,
+{
+    /// Returns the exact size in bytes of the `AggregateProof` written by
+    /// `AggregateProof::write_torus`.
+    pub fn torus_proof_size(&self) -> usize {
+        self.size_with_target(Compress::Yes, torus_size::<E>())
+    }
+}
+
+/// Work of the prover, counted as the operations of the aggregation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProverCost {
+    /// pairs of points going through a Miller loop in the pairing products
+    pub miller_loops: usize,
+    pub final_exponentiations: usize,
+    /// sizes of the multi-scalar multiplications in G1
+    pub g1_msms: Vec<usize>,
+    /// sizes of the multi-scalar multiplications in G2
+    pub g2_msms: Vec<usize>,
+    /// scalar multiplications in G1 outside of multi-scalar multiplications
+    pub g1_muls: usize,
+    /// scalar multiplications in G2 outside of multi-scalar multiplications
+    pub g2_muls: usize,
+    /// approximate peak of the memory allocated by the aggregation in bytes,
+    /// on top of the proofs and the SRS
+    pub peak_memory: usize,
+    /// memory of the prover SRS in bytes
+    pub srs_memory: usize,
+}
+
+impl ProverCost {
+    /// Returns the time of the aggregation on the calibrated machine, as the
+    /// sum of the times of its operations.
+    pub fn time(&self, calibration: &Calibration) -> Duration {
+        let c = calibration;
+        let msms = |unit: Duration, sizes: &[usize]| {
+            sizes
+                .iter()
+                .map(|s| Calibration::msm_time(unit, *s))
+                .sum::<Duration>()
+        };
+        c.miller_loop.mul_f64(self.miller_loops as f64)
+            + c.final_exponentiation
+                .mul_f64(self.final_exponentiations as f64)
+            + msms(c.g1_msm, &self.g1_msms)
+            + msms(c.g2_msm, &self.g2_msms)
+            + c.g1_mul.mul_f64(self.g1_muls as f64)
+            + c.g2_mul.mul_f64(self.g2_muls as f64)
+    }
+}
+
+/// Work of the verifier, counted as the operations of the verification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifierCost {
+    /// pairs of points going through a Miller loop
+    pub miller_loops: usize,
+    pub final_exponentiations: usize,
+    pub gt_exponentiations: usize,
+    pub g1_muls: usize,
+    pub g2_muls: usize,
+    /// size of the multi-scalar multiplication of the public inputs with the
+    /// verifying key
+    pub g1_msm: usize,
+}
+
+impl VerifierCost {
+    /// Returns the time of the verification on the calibrated machine, as the
+    /// sum of the times of its operations.
+    pub fn time(&self, calibration: &Calibration) -> Duration {
+        let c = calibration;
+        c.miller_loop.mul_f64(self.miller_loops as f64)
+            + c.final_exponentiation
+                .mul_f64(self.final_exponentiations as f64)
+            + c.gt_exponentiation.mul_f64(self.gt_exponentiations as f64)
+            + c.g1_mul.mul_f64(self.g1_muls as f64)
+            + c.g2_mul.mul_f64(self.g2_muls as f64)
+            + Calibration::msm_time(c.g1_msm, self.g1_msm)
+    }
+}
+
+/// Calibration contains the time of the primitives of a curve on the local
+/// machine, turning the counts of `ProverCost` and `VerifierCost` into time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Calibration {
+    /// time of a Miller loop of a single pair
+    pub miller_loop: Duration,
+    pub final_exponentiation: Duration,
+    pub gt_exponentiation: Duration,
+    pub g1_mul: Duration,
+    pub g2_mul: Duration,
+    /// time of a multi-scalar multiplication of `CALIBRATION_MSM_SIZE`
+    /// elements in G1
+    pub g1_msm: Duration,
+    /// time of a multi-scalar multiplication of `CALIBRATION_MSM_SIZE`
+    /// elements in G2
+    pub g2_msm: Duration,
+}
+
+impl Calibration {
+    /// Benchmarks the primitives of the curve, taking the average time of
+    /// `samples` runs of each.
+    #[cfg(feature = "std")]
+    pub fn measure<E: Pairing, R: Rng>(rng: &mut R, samples: usize) -> Self {
+        use ark_ec::{CurveGroup, VariableBaseMSM};
+        use ark_ff::{Field, PrimeField};
+        use std::time::Instant;
+
+        let samples = samples.max(1);
+        let time = |mut f: Box<dyn FnMut() + '_>| {
+            let start = Instant::now();
+            for _ in 0..samples {
+                f();
+            }
+            start.elapsed() / samples as u32
+        };
+        let g1 = E::G1::rand(rng);
+        let g2 = E::G2::rand(rng);
+        let x = E::ScalarField::rand(rng);
+        let ml = E::miller_loop(g1, g2);
+        let gt = E::final_exponentiation(ml).unwrap().0;
+        let g1s = (0..CALIBRATION_MSM_SIZE)
+            .map(|_| E::G1::rand(rng).into_affine())
+            .collect::<Vec<_>>();
+        let g2s = (0..CALIBRATION_MSM_SIZE)
+            .map(|_| E::G2::rand(rng).into_affine())
+            .collect::<Vec<_>>();
+        let scalars = (0..CALIBRATION_MSM_SIZE)
+            .map(|_| E::ScalarField::rand(rng))
+            .collect::<Vec<_>>();
+        Calibration {
+            miller_loop: time(Box::new(|| {
+                let _ = E::miller_loop(g1, g2);
+            })),
+            final_exponentiation: time(Box::new(|| {
+                let _ = E::final_exponentiation(ml);
+            })),
+            gt_exponentiation: time(Box::new(|| {
+                let _ = gt.pow(x.into_bigint());
+            })),
+            g1_mul: time(Box::new(|| {
+                let _ = g1 * x;
+            })),
+            g2_mul: time(Box::new(|| {
+                let _ = g2 * x;
+            })),
+            g1_msm: time(Box::new(|| {
+                let _ = E::G1::msm(&g1s, &scalars);
+            })),
+            g2_msm: time(Box::new(|| {
+                let _ = E::G2::msm(&g2s, &scalars);
+            })),
+        }
+    }
+
+    /// Returns the time of a multi-scalar multiplication of the given size
+    /// from the time of one of `CALIBRATION_MSM_SIZE` elements, assuming a
+    /// cost in $n / \log n$ as for Pippenger's algorithm.
+    fn msm_time(unit: Duration, size: usize) -> Duration {
+        let cost = |n: usize| n as f64 / ark_std::log2(n).max(1) as f64;
+        unit.mul_f64(cost(size) / cost(CALIBRATION_MSM_SIZE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Bn254;
+
+    #[test]
+    fn test_cost_estimator() {
+        assert!(CostEstimator::<Bn254>::new(6, 2).is_err());
+        assert!(CostEstimator::<Bn254>::new(1, 2).is_err());
+
+        let estimator = CostEstimator::<Bn254>::new(8, 2).unwrap();
+        assert_eq!(estimator.generic_srs_len(), 16);
+        let prover = estimator.prover();
+        assert_eq!(prover.miller_loops, 21 * 8 - 14);
+        assert_eq!(prover.final_exponentiations, 7 + 14 * 3);
+        assert_eq!(prover.g1_msms, vec![8, 4, 4, 2, 2, 1, 1, 16, 16]);
+        assert_eq!(prover.g1_muls, 6 * 8 - 4);
+        assert_eq!(prover.g2_muls, 4 * 8 - 3);
+
+        let verifier = estimator.verifier(false);
+        assert_eq!(verifier.miller_loops, 18);
+        assert_eq!(verifier.gt_exponentiations, 35);
+        assert_eq!(verifier.g1_msm, 3);
+        assert_eq!(estimator.verifier(true).miller_loops, 17);
+
+        // a calibration with the same time for all operations
+        let unit = Duration::from_micros(1);
+        let calibration = Calibration {
+            miller_loop: unit,
+            final_exponentiation: unit,
+            gt_exponentiation: unit,
+            g1_mul: unit,
+            g2_mul: unit,
+            g1_msm: unit,
+            g2_msm: unit,
+        };
+        assert!(verifier.time(&calibration) >= unit * (18 + 1 + 35));
+        assert!(prover.time(&calibration) > verifier.time(&calibration));
+    }
+}
@@ -0,0 +1,814 @@
+//! Distributed aggregation of Groth16 proofs.
+//!
+//! The distributed aggregation splits the heavy parts of `aggregate_proofs`
+//! between workers. Each worker is assigned a range $[s,e)$ of the left half
+//! of the proofs and the same range shifted by $n/2$ on the right half, so it
+//! can compute its share of the commitments and of the first GIPA round
+//! locally. The coordinator multiplies the partial results together, derives
+//! the challenges and finishes the remaining rounds and the KZG openings on
+//! the vectors of size $n/2$ returned by the workers.
+//!
+//! Only the first round of GIPA is distributed: it is the most expensive one,
+//! as each round halves the vectors. The coordinator then holds the vectors
+//! A, B, C, $r$ and the commitment keys of size $n/2$, i.e. memory in
+//! $O(n/2)$, about half of the memory of `aggregate_proofs`, and runs the
+//! $\log n - 1$ other rounds alone.
+//!
+//! The requests sent to a worker are, in order:
+//! * commit: the worker receives its slices of A, B, C and of the commitment
+//!   keys and returns its share of the commitments to AB and C
+//! * scale: the worker receives $r$ and returns its share of $Z_{AB}$ and
+//!   $C^r$
+//! * fold: the worker receives the first challenge and returns its share of
+//!   the cross terms of the first round along with its compressed slices.
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{Field, One};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{cfg_iter, cfg_iter_mut, Zero};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::io::{ErrorKind, Read, Write};
+use std::ops::{AddAssign, MulAssign, Range};
+
+use super::{
+    commitment,
+    commitment::{Output, VKey, WKey},
+    compress,
+    errors::Error,
+    ip,
+    proof::AggregateProof,
+    prover::{gipa_rounds, prove_commitment_keys, GipaState},
+    srs::{ProverSRS, MAX_SRS_SIZE},
+    statement::bind_statement,
+    transcript::Transcript,
+};
+
+const REQUEST_COMMIT: u8 = 0;
+const REQUEST_SCALE: u8 = 1;
+const REQUEST_FOLD: u8 = 2;
+
+/// Status byte prefixed to every response sent over a stream.
+const STATUS_OK: u8 = 0;
+const STATUS_ERR: u8 = 1;
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct CommitRequest<E: Pairing> {
+    /// start of the range of the worker in the left half
+    offset: u64,
+    /// half of the number of proofs aggregated
+    half: u64,
+    /// slices of the vectors and keys: the left range followed by the right
+    /// range
+    a: Vec<E::G1Affine>,
+    b: Vec<E::G2Affine>,
+    c: Vec<E::G1Affine>,
+    vkey: VKey<E>,
+    wkey: WKey<E>,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct CommitResponse<E: Pairing> {
+    com_ab: Output<<E as Pairing>::TargetField>,
+    com_c: Output<<E as Pairing>::TargetField>,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct ScaleResponse<E: Pairing> {
+    ip_ab: <E as Pairing>::TargetField,
+    agg_c: E::G1Affine,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct FoldRequest<E: Pairing> {
+    c: E::ScalarField,
+    c_inv: E::ScalarField,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct FoldResponse<E: Pairing> {
+    tab_l: Output<<E as Pairing>::TargetField>,
+    tab_r: Output<<E as Pairing>::TargetField>,
+    tuc_l: Output<<E as Pairing>::TargetField>,
+    tuc_r: Output<<E as Pairing>::TargetField>,
+    zab_l: <E as Pairing>::TargetField,
+    zab_r: <E as Pairing>::TargetField,
+    zc_l: E::G1Affine,
+    zc_r: E::G1Affine,
+    /// compressed slices of the range of the worker
+    a: Vec<E::G1Affine>,
+    b: Vec<E::G2Affine>,
+    c: Vec<E::G1Affine>,
+    r: Vec<E::ScalarField>,
+    vkey: VKey<E>,
+    wkey: WKey<E>,
+}
+
+/// Worker is the coordinator's handle to a worker of the distributed
+/// aggregation. It sends a serialized request and returns the serialized
+/// response of the worker.
+pub trait Worker {
+    fn call(&mut self, request: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+enum Phase<E: Pairing> {
+    Idle,
+    Committed {
+        offset: u64,
+        half: u64,
+        a: Vec<E::G1Affine>,
+        b: Vec<E::G2Affine>,
+        c: Vec<E::G1Affine>,
+        vkey: VKey<E>,
+        wkey: WKey<E>,
+    },
+    Scaled {
+        a: Vec<E::G1Affine>,
+        b_r: Vec<E::G2Affine>,
+        c: Vec<E::G1Affine>,
+        r_vec: Vec<E::ScalarField>,
+        vkey: VKey<E>,
+        wkey_r_inv: WKey<E>,
+    },
+}
+
+/// WorkerState performs the computations of a worker of the distributed
+/// aggregation. It keeps the slices received between the requests of an
+/// aggregation and is ready for a new aggregation once the fold request is
+/// answered.
+pub struct WorkerState<E: Pairing> {
+    phase: Phase<E>,
+}
+
+impl<E: Pairing> Default for WorkerState<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Pairing> WorkerState<E> {
+    pub fn new() -> Self {
+        Self { phase: Phase::Idle }
+    }
+
+    /// Handles a serialized request from the coordinator and returns the
+    /// serialized response.
+    pub fn handle(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        let (tag, mut payload) = request
+            .split_first()
+            .ok_or_else(|| Error::Worker("empty request".to_string()))?;
+        let phase = std::mem::replace(&mut self.phase, Phase::Idle);
+        let mut response = Vec::new();
+        match (*tag, phase) {
+            (REQUEST_COMMIT, _) => {
+                let req = CommitRequest::<E>::deserialize_compressed(&mut payload)?;
+                let m = req.a.len() / 2;
+                if req.a.len() != 2 * m
+                    || m == 0
+                    || req.b.len() != req.a.len()
+                    || req.c.len() != req.a.len()
+                    || !req.vkey.has_correct_len(req.a.len())
+                    || !req.wkey.has_correct_len(req.a.len())
+                    || req.half > MAX_SRS_SIZE as u64
+                    || req
+                        .offset
+                        .checked_add(m as u64)
+                        .is_none_or(|end| end > req.half)
+                {
+                    return Err(Error::Worker("invalid slices".to_string()));
+                }
+                let (refa, refb, refc) = (&req.a, &req.b, &req.c);
+                let (refvkey, refwkey) = (&req.vkey, &req.wkey);
+                try_par! {
+                    let com_ab = commitment::pair::<E>(refvkey, refwkey, refa, refb),
+                    let com_c = commitment::single_g1::<E>(refvkey, refc)
+                };
+                CommitResponse::<E> { com_ab, com_c }.serialize_compressed(&mut response)?;
+                self.phase = Phase::Committed {
+                    offset: req.offset,
+                    half: req.half,
+                    a: req.a,
+                    b: req.b,
+                    c: req.c,
+                    vkey: req.vkey,
+                    wkey: req.wkey,
+                };
+            }
+            (
+                REQUEST_SCALE,
+                Phase::Committed {
+                    offset,
+                    half,
+                    a,
+                    b,
+                    c,
+                    vkey,
+                    wkey,
+                },
+            ) => {
+                let r = E::ScalarField::deserialize_compressed(&mut payload)?;
+                let m = a.len() / 2;
+                // r^offset, ..., r^{offset + m - 1} on the left range and the
+                // same powers shifted by n/2 on the right range
+                let left_start = r.pow([offset]);
+                let right_start = r.pow([half + offset]);
+                let mut r_vec = Vec::with_capacity(2 * m);
+                for start in [left_start, right_start] {
+                    let mut ri = start;
+                    for _ in 0..m {
+                        r_vec.push(ri);
+                        ri.mul_assign(&r);
+                    }
+                }
+                let r_inv = cfg_iter!(r_vec)
+                    .map(|ri| ri.inverse())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| Error::Worker("r is zero".to_string()))?;
+                let b_r = cfg_iter!(b)
+                    .zip(cfg_iter!(r_vec))
+                    .map(|(bi, ri)| mul!(bi.into_group(), *ri).into_affine())
+                    .collect::<Vec<_>>();
+                let (refa, refb_r, refc, refr_vec) = (&a, &b_r, &c, &r_vec);
+                try_par! {
+                    let ip_ab = ip::pairing::<E>(refa, refb_r),
+                    let agg_c = ip::multiexponentiation::<E::G1Affine>(refc, refr_vec)
+                };
+                let wkey_r_inv = wkey.scale(&r_inv)?;
+                ScaleResponse::<E> {
+                    ip_ab: ip_ab.0,
+                    agg_c: agg_c.into_affine(),
+                }
+                .serialize_compressed(&mut response)?;
+                self.phase = Phase::Scaled {
+                    a,
+                    b_r,
+                    c,
+                    r_vec,
+                    vkey,
+                    wkey_r_inv,
+                };
+            }
+            (
+                REQUEST_FOLD,
+                Phase::Scaled {
+                    a,
+                    b_r,
+                    c,
+                    r_vec,
+                    vkey,
+                    wkey_r_inv,
+                },
+            ) => {
+                let req = FoldRequest::<E>::deserialize_compressed(&mut payload)?;
+                let response_struct = fold::<E>(a, b_r, c, r_vec, vkey, wkey_r_inv, &req)?;
+                response_struct.serialize_compressed(&mut response)?;
+            }
+            _ => return Err(Error::Worker("unexpected request".to_string())),
+        }
+        Ok(response)
+    }
+}
+
+/// Computes the share of a worker of the first GIPA round, exactly like the
+/// first round of `gipa_rounds` restricted to the range of the worker.
+fn fold<E: Pairing>(
+    mut m_a: Vec<E::G1Affine>,
+    mut m_b: Vec<E::G2Affine>,
+    mut m_c: Vec<E::G1Affine>,
+    mut m_r: Vec<E::ScalarField>,
+    vkey: VKey<E>,
+    wkey: WKey<E>,
+    req: &FoldRequest<E>,
+) -> Result<FoldResponse<E>, Error> {
+    let (c, c_inv) = (req.c, req.c_inv);
+    let split = m_a.len() / 2;
+    let (a_left, a_right) = m_a.split_at(split);
+    let (b_left, b_right) = m_b.split_at(split);
+    let (c_left, c_right) = m_c.split_at(split);
+    let (r_left, r_right) = m_r.split_at(split);
+    let (vk_left, vk_right) = vkey.split(split);
+    let (wk_left, wk_right) = wkey.split(split);
+
+    let (rvk_left, rvk_right) = (&vk_left, &vk_right);
+    let (rwk_left, rwk_right) = (&wk_left, &wk_right);
+    try_par! {
+        let tab_l = commitment::pair::<E>(rvk_left, rwk_right, a_right, b_left),
+        let tab_r = commitment::pair::<E>(rvk_right, rwk_left, a_left, b_right),
+        let zab_l = ip::pairing::<E>(a_right, b_left),
+        let zab_r = ip::pairing::<E>(a_left, b_right),
+        let zc_l = ip::multiexponentiation::<E::G1Affine>(c_right, r_left),
+        let zc_r = ip::multiexponentiation::<E::G1Affine>(c_left, r_right),
+        let tuc_l = commitment::single_g1::<E>(rvk_left, c_right),
+        let tuc_r = commitment::single_g1::<E>(rvk_right, c_left)
+    };
+
+    compress(&mut m_a, split, &c);
+    compress(&mut m_b, split, &c_inv);
+    compress(&mut m_c, split, &c);
+    let (r_left, r_right) = m_r.split_at_mut(split);
+    cfg_iter_mut!(r_left)
+        .zip(cfg_iter_mut!(r_right))
+        .for_each(|(r_l, r_r)| {
+            r_r.mul_assign(&c_inv);
+            r_l.add_assign(*r_r);
+        });
+    m_r.resize(split, E::ScalarField::zero());
+
+    Ok(FoldResponse {
+        tab_l,
+        tab_r,
+        tuc_l,
+        tuc_r,
+        zab_l: zab_l.0,
+        zab_r: zab_r.0,
+        zc_l: zc_l.into_affine(),
+        zc_r: zc_r.into_affine(),
+        a: m_a,
+        b: m_b,
+        c: m_c,
+        r: m_r,
+        vkey: vk_left.compress(&vk_right, &c_inv)?,
+        wkey: wk_left.compress(&wk_right, &c)?,
+    })
+}
+
+/// InProcessWorker runs a worker in the memory of the coordinator. Requests
+/// and responses still go through their serialized form.
+pub struct InProcessWorker<E: Pairing> {
+    state: WorkerState<E>,
+}
+
+impl<E: Pairing> Default for InProcessWorker<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Pairing> InProcessWorker<E> {
+    pub fn new() -> Self {
+        Self {
+            state: WorkerState::new(),
+        }
+    }
+}
+
+impl<E: Pairing> Worker for InProcessWorker<E> {
+    fn call(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        self.state.handle(request)
+    }
+}
+
+/// StreamWorker talks to a worker served by `serve_worker` at the other end
+/// of a stream, for example a `TcpStream` or a `UnixStream`. Messages are
+/// framed by their length as a little endian u64.
+pub struct StreamWorker<S: Read + Write> {
+    stream: S,
+}
+
+impl<S: Read + Write> StreamWorker<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+}
+
+impl<S: Read + Write> Worker for StreamWorker<S> {
+    fn call(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        write_frame(&mut self.stream, request)?;
+        let mut response = read_frame(&mut self.stream)?;
+        match response.first() {
+            Some(&STATUS_OK) => Ok(response.split_off(1)),
+            Some(&STATUS_ERR) => Err(Error::Worker(
+                String::from_utf8_lossy(&response[1..]).to_string(),
+            )),
+            _ => Err(Error::Worker("invalid response".to_string())),
+        }
+    }
+}
+
+/// Serves the requests of a coordinator arriving on the stream until the
+/// coordinator closes it. Errors of the worker are sent back to the
+/// coordinator.
+pub fn serve_worker<E: Pairing, S: Read + Write>(mut stream: S) -> Result<(), Error> {
+    let mut state = WorkerState::<E>::new();
+    loop {
+        let request = match read_frame(&mut stream) {
+            Ok(request) => request,
+            Err(Error::Serialization(SerializationError::IoError(e)))
+                if e.kind() == ErrorKind::UnexpectedEof =>
+            {
+                return Ok(())
+            }
+            Err(e) => return Err(e),
+        };
+        let response = match state.handle(&request) {
+            Ok(mut response) => {
+                response.insert(0, STATUS_OK);
+                response
+            }
+            Err(e) => {
+                let mut response = vec![STATUS_ERR];
+                response.extend_from_slice(e.to_string().as_bytes());
+                response
+            }
+        };
+        write_frame(&mut stream, &response)?;
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    writer
+        .write_all(&(bytes.len() as u64).to_le_bytes())
+        .and_then(|_| writer.write_all(bytes))
+        .and_then(|_| writer.flush())
+        .map_err(|e| Error::Serialization(e.into()))
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let mut len = [0u8; 8];
+    reader
+        .read_exact(&mut len)
+        .map_err(|e| Error::Serialization(e.into()))?;
+    let len = u64::from_le_bytes(len);
+    let mut bytes = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::Serialization(e.into()))?;
+    if bytes.len() as u64 != len {
+        return Err(Error::Worker("truncated frame".to_string()));
+    }
+    Ok(bytes)
+}
+
+/// Sends one request to each worker in parallel and deserializes the
+/// responses. Workers are called from their own threads rather than from the
+/// rayon pool, which may be needed by the workers themselves while the calls
+/// are blocked.
+fn call_workers<W: Worker + Send, R: CanonicalDeserialize + Send>(
+    workers: &mut [W],
+    requests: Vec<Vec<u8>>,
+) -> Result<Vec<R>, Error> {
+    std::thread::scope(|s| {
+        let handles = workers
+            .iter_mut()
+            .zip(requests.iter())
+            .map(|(worker, request)| {
+                s.spawn(move || {
+                    let response = worker.call(request)?;
+                    Ok(R::deserialize_compressed(&response[..])?)
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .map_err(|_| Error::Worker("worker thread panicked".to_string()))?
+            })
+            .collect()
+    })
+}
+
+fn encode<S: CanonicalSerialize>(tag: u8, payload: &S) -> Result<Vec<u8>, Error> {
+    let mut request = vec![tag];
+    payload.serialize_compressed(&mut request)?;
+    Ok(request)
+}
+
+/// Returns the range of the left half followed by the same range in the right
+/// half of the vector.
+fn worker_slice<T: Clone>(v: &[T], range: &Range<usize>, half: usize) -> Vec<T> {
+    let mut slice = v[range.clone()].to_vec();
+    slice.extend_from_slice(&v[range.start + half..range.end + half]);
+    slice
+}
+
+/// Returns the product of the commitments computed by the workers.
+fn product<E: Pairing>(
+    outputs: impl Iterator<Item = Output<<E as Pairing>::TargetField>>,
+) -> Output<<E as Pairing>::TargetField> {
+    outputs.fold(
+        Output(E::TargetField::one(), E::TargetField::one()),
+        |mut acc, o| {
+            acc.0.mul_assign(&o.0);
+            acc.1.mul_assign(&o.1);
+            acc
+        },
+    )
+}
+
+/// Aggregate `n` zkSnark proofs with the help of the given workers, where `n`
+/// must be a power of two. The statement is bound in the transcript as in
+/// `aggregate_proofs` and the proof is identical to the one returned by
+/// `aggregate_proofs` for the same transcript.
+pub fn aggregate_proofs_distributed<
+    E: Pairing + std::fmt::Debug,
+    T: Transcript,
+    W: Worker + Send,
+>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &[Vec<E::ScalarField>],
+    proofs: &[Proof<E>],
+    workers: &mut [W],
+    transcript: &mut T,
+) -> Result<AggregateProof<E>, Error> {
+    if public_inputs.len() != proofs.len() {
+        return Err(Error::InvalidProof(
+            "public inputs len != number of proofs".to_string(),
+        ));
+    }
+    bind_statement(transcript, vk, &srs.to_verifier_srs(), public_inputs)?;
+    aggregate_proofs_distributed_unchecked(srs, transcript, proofs, workers)
+}
+
+/// Aggregate `n` zkSnark proofs with the help of the given workers, without
+/// binding any statement in the transcript. The proof is identical to the one
+/// returned by `aggregate_proofs_unchecked` for the same transcript. There can
+/// be at most $n/2$ workers. The workers fold the first round of GIPA with the
+/// challenge derived before it, so transcripts binding every round, see
+/// `Transcript::bind_gipa_rounds`, are not supported.
+pub fn aggregate_proofs_distributed_unchecked<
+    E: Pairing + std::fmt::Debug,
+    T: Transcript,
+    W: Worker + Send,
+>(
+    srs: &ProverSRS<E>,
+    transcript: &mut T,
+    proofs: &[Proof<E>],
+    workers: &mut [W],
+) -> Result<AggregateProof<E>, Error> {
+    if proofs.len() < 2 {
+        return Err(Error::InvalidProof("invalid proof size < 2".to_string()));
+    }
+    if !proofs.len().is_power_of_two() {
+        return Err(Error::InvalidProof(
+            "invalid proof size: not power of two".to_string(),
+        ));
+    }
+    if !srs.has_correct_len(proofs.len()) {
+        return Err(Error::InvalidSRS("SRS len != proofs len".to_string()));
+    }
+    if transcript.bind_gipa_rounds() {
+        return Err(Error::Worker(
+            "the workers cannot fold a first round bound by the transcript".to_string(),
+        ));
+    }
+    let half = proofs.len() / 2;
+    if workers.is_empty() || workers.len() > half {
+        return Err(Error::Worker(format!(
+            "number of workers must be between 1 and {}",
+            half
+        )));
+    }
+
+    // split the left half as evenly as possible between the workers
+    let (size, rest) = (half / workers.len(), half % workers.len());
+    let ranges = (0..workers.len())
+        .map(|i| {
+            let start = i * size + i.min(rest);
+            start..start + size + usize::from(i < rest)
+        })
+        .collect::<Vec<_>>();
+
+    // commitments to A B and C
+    let requests = ranges
+        .iter()
+        .map(|range| {
+            let a = worker_slice(proofs, range, half);
+            encode(
+                REQUEST_COMMIT,
+                &CommitRequest::<E> {
+                    offset: range.start as u64,
+                    half: half as u64,
+                    a: a.iter().map(|p| p.a).collect(),
+                    b: a.iter().map(|p| p.b).collect(),
+                    c: a.iter().map(|p| p.c).collect(),
+                    vkey: VKey::<E> {
+                        a: worker_slice(&srs.vkey.a, range, half),
+                        b: worker_slice(&srs.vkey.b, range, half),
+                    },
+                    wkey: WKey::<E> {
+                        a: worker_slice(&srs.wkey.a, range, half),
+                        b: worker_slice(&srs.wkey.b, range, half),
+                    },
+                },
+            )
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let responses: Vec<CommitResponse<E>> = call_workers(workers, requests)?;
+    let (com_ab, com_c): (Vec<_>, Vec<_>) =
+        responses.into_iter().map(|r| (r.com_ab, r.com_c)).unzip();
+    let com_ab = product::<E>(com_ab.into_iter());
+    let com_c = product::<E>(com_c.into_iter());
+
+    transcript.append(b"AB-commitment", &com_ab);
+    transcript.append(b"C-commitment", &com_c);
+    let r = transcript.challenge_scalar::<E::ScalarField>(b"r-random-fiatshamir");
+
+    // A * B^r and C^r
+    let requests = vec![encode(REQUEST_SCALE, &r)?; workers.len()];
+    let responses: Vec<ScaleResponse<E>> = call_workers(workers, requests)?;
+    let mut ip_ab = E::TargetField::one();
+    let mut agg_c = E::G1::zero();
+    for response in responses {
+        ip_ab.mul_assign(&response.ip_ab);
+        agg_c.add_assign(&response.agg_c);
+    }
+    let agg_c = agg_c.into_affine();
+
+    transcript.append(b"inner-product-ab", &ip_ab);
+    transcript.append_point(b"comm-c", &agg_c);
+    let c_inv = transcript.challenge_scalar::<E::ScalarField>(b"first-challenge");
+    let c = c_inv.inverse().unwrap();
+
+    // first round of GIPA
+    let requests = vec![encode(REQUEST_FOLD, &FoldRequest::<E> { c, c_inv })?; workers.len()];
+    let responses: Vec<FoldResponse<E>> = call_workers(workers, requests)?;
+    let mut state = GipaState::<E> {
+        nproofs: proofs.len() as u32,
+        m_a: Vec::with_capacity(half),
+        m_b: Vec::with_capacity(half),
+        m_c: Vec::with_capacity(half),
+        m_r: Vec::with_capacity(half),
+        m_extras: Vec::new(),
+        vkey: VKey::<E> {
+            a: Vec::with_capacity(half),
+            b: Vec::with_capacity(half),
+        },
+        wkey: WKey::<E> {
+            a: Vec::with_capacity(half),
+            b: Vec::with_capacity(half),
+        },
+        comms_ab: Vec::new(),
+        comms_c: Vec::new(),
+        z_ab: Vec::new(),
+        z_c: Vec::new(),
+        mipp_extras: Vec::new(),
+        challenges: vec![c],
+        challenges_inv: vec![c_inv],
+    };
+    let mut tab = Vec::with_capacity(workers.len());
+    let mut tuc = Vec::with_capacity(workers.len());
+    let (mut zab_l, mut zab_r) = (E::TargetField::one(), E::TargetField::one());
+    let (mut zc_l, mut zc_r) = (E::G1::zero(), E::G1::zero());
+    for mut response in responses {
+        tab.push((response.tab_l, response.tab_r));
+        tuc.push((response.tuc_l, response.tuc_r));
+        zab_l.mul_assign(&response.zab_l);
+        zab_r.mul_assign(&response.zab_r);
+        zc_l.add_assign(&response.zc_l);
+        zc_r.add_assign(&response.zc_r);
+        state.m_a.append(&mut response.a);
+        state.m_b.append(&mut response.b);
+        state.m_c.append(&mut response.c);
+        state.m_r.append(&mut response.r);
+        state.vkey.a.append(&mut response.vkey.a);
+        state.vkey.b.append(&mut response.vkey.b);
+        state.wkey.a.append(&mut response.wkey.a);
+        state.wkey.b.append(&mut response.wkey.b);
+    }
+    if state.m_a.len() != half
+        || state.m_b.len() != half
+        || state.m_c.len() != half
+        || state.m_r.len() != half
+        || !state.vkey.has_correct_len(half)
+        || !state.wkey.has_correct_len(half)
+    {
+        return Err(Error::Worker("invalid compressed slices".to_string()));
+    }
+    let (tab_l, tab_r): (Vec<_>, Vec<_>) = tab.into_iter().unzip();
+    let (tuc_l, tuc_r): (Vec<_>, Vec<_>) = tuc.into_iter().unzip();
+    state.comms_ab.push((
+        product::<E>(tab_l.into_iter()),
+        product::<E>(tab_r.into_iter()),
+    ));
+    state.comms_c.push((
+        product::<E>(tuc_l.into_iter()),
+        product::<E>(tuc_r.into_iter()),
+    ));
+    state.z_ab.push((zab_l, zab_r));
+    state.z_c.push((zc_l.into_affine(), zc_r.into_affine()));
+
+    // the coordinator finishes the recursion and the KZG openings
+    let (gipa, _, challenges, challenges_inv) = gipa_rounds(transcript, state, c_inv)?;
+    let tmipp = prove_commitment_keys(srs, transcript, gipa, &[], challenges, challenges_inv, &r)?;
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        ip_ab,
+        agg_c,
+        tmipp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aggregate_proofs_unchecked, srs, transcript::new_merlin_transcript};
+    use ark_bn254::{Bn254, Fr, G1Projective, G2Projective};
+    use ark_std::UniformRand;
+    use rand_core::SeedableRng;
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_distributed_aggregation() {
+        let n = 8;
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let generic = srs::setup_fake_srs::<Bn254, _>(&mut rng, n);
+        let (prover_srs, _) = generic.specialize(n);
+        // the aggregation doesn't need valid proofs to be reproduced
+        let proofs = (0..n)
+            .map(|_| Proof::<Bn254> {
+                a: G1Projective::rand(&mut rng).into_affine(),
+                b: G2Projective::rand(&mut rng).into_affine(),
+                c: G1Projective::rand(&mut rng).into_affine(),
+            })
+            .collect::<Vec<_>>();
+        let mut transcript = new_merlin_transcript(b"test");
+        let expected = aggregate_proofs_unchecked(&prover_srs, &mut transcript, &proofs).unwrap();
+
+        for nworkers in [1, 3, 4] {
+            let mut workers = (0..nworkers)
+                .map(|_| InProcessWorker::<Bn254>::new())
+                .collect::<Vec<_>>();
+            let mut transcript = new_merlin_transcript(b"test");
+            let proof = aggregate_proofs_distributed_unchecked(
+                &prover_srs,
+                &mut transcript,
+                &proofs,
+                &mut workers,
+            )
+            .unwrap();
+            assert_eq!(proof, expected);
+        }
+
+        // workers listening on a local socket
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                std::thread::spawn(move || serve_worker::<Bn254, _>(stream).unwrap());
+            }
+        });
+        let mut workers = (0..2)
+            .map(|_| StreamWorker::new(TcpStream::connect(addr).unwrap()))
+            .collect::<Vec<_>>();
+        server.join().unwrap();
+        let mut transcript = new_merlin_transcript(b"test");
+        let proof = aggregate_proofs_distributed_unchecked(
+            &prover_srs,
+            &mut transcript,
+            &proofs,
+            &mut workers,
+        )
+        .unwrap();
+        assert_eq!(proof, expected);
+
+        // requests out of order are rejected and reported to the coordinator
+        let mut worker = workers.pop().unwrap();
+        let request = encode(REQUEST_SCALE, &Fr::one()).unwrap();
+        assert!(matches!(worker.call(&request), Err(Error::Worker(_))));
+        let mut workers = (0..5)
+            .map(|_| InProcessWorker::<Bn254>::new())
+            .collect::<Vec<_>>();
+        let mut transcript = new_merlin_transcript(b"test");
+        aggregate_proofs_distributed_unchecked(&prover_srs, &mut transcript, &proofs, &mut workers)
+            .expect_err("too many workers");
+
+        // malformed requests are rejected without panicking
+        let range = 0..1;
+        let slice = worker_slice(&proofs, &range, n / 2);
+        let commit = |offset: u64| {
+            encode(
+                REQUEST_COMMIT,
+                &CommitRequest::<Bn254> {
+                    offset,
+                    half: (n / 2) as u64,
+                    a: slice.iter().map(|p| p.a).collect(),
+                    b: slice.iter().map(|p| p.b).collect(),
+                    c: slice.iter().map(|p| p.c).collect(),
+                    vkey: VKey::<Bn254> {
+                        a: worker_slice(&prover_srs.vkey.a, &range, n / 2),
+                        b: worker_slice(&prover_srs.vkey.b, &range, n / 2),
+                    },
+                    wkey: WKey::<Bn254> {
+                        a: worker_slice(&prover_srs.wkey.a, &range, n / 2),
+                        b: worker_slice(&prover_srs.wkey.b, &range, n / 2),
+                    },
+                },
+            )
+            .unwrap()
+        };
+        let mut worker = WorkerState::<Bn254>::new();
+        assert!(matches!(
+            worker.handle(&commit(u64::MAX)),
+            Err(Error::Worker(_))
+        ));
+        worker.handle(&commit(0)).unwrap();
+        let request = encode(REQUEST_SCALE, &Fr::zero()).unwrap();
+        assert!(matches!(worker.handle(&request), Err(Error::Worker(_))));
+    }
+}
@@ -0,0 +1,261 @@
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_groth16::VerifyingKey;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use ark_std::io::{Read, Write};
+use ark_std::{string::ToString, vec::Vec};
+use sha2::{Digest, Sha256};
+
+use super::{
+    proof::{AggregateProof, CommittedAggregateProof, SuccinctAggregateProof},
+    srs::VerifierSRS,
+    statement::vk_digest,
+};
+use crate::Error;
+
+/// Magic bytes starting every envelope.
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"SNPK";
+
+/// Version of the envelope format written by `write_envelope`.
+pub const ENVELOPE_VERSION: u16 = 1;
+
+/// ProofVariant is the kind of aggregate proof wrapped in an envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofVariant {
+    Aggregate = 0,
+    Committed = 1,
+    Succinct = 2,
+}
+
+impl TryFrom<u8> for ProofVariant {
+    type Error = Error;
+
+    fn try_from(v: u8) -> Result<Self, Error> {
+        match v {
+            0 => Ok(ProofVariant::Aggregate),
+            1 => Ok(ProofVariant::Committed),
+            2 => Ok(ProofVariant::Succinct),
+            _ => Err(Error::InvalidEnvelope(format!(
+                "unknown proof variant {}",
+                v
+            ))),
+        }
+    }
+}
+
+/// EnvelopeProof is an aggregate proof that can be wrapped in an envelope,
+/// encoded with its compressed canonical serialization.
+pub trait EnvelopeProof<E: Pairing>: CanonicalSerialize + CanonicalDeserialize {
+    const VARIANT: ProofVariant;
+
+    /// Number of proofs aggregated.
+    fn nproofs(&self) -> u32;
+
+    /// Checks that the proof is well formed and that all its elements are in
+    /// the prime order subgroups.
+    fn validate(&self) -> Result<(), Error>;
+}
+
+impl<E: Pairing> EnvelopeProof<E> for AggregateProof<E> {
+    const VARIANT: ProofVariant = ProofVariant::Aggregate;
+
+    fn nproofs(&self) -> u32 {
+        self.tmipp.gipa.nproofs
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        AggregateProof::validate(self)
+    }
+}
+
+impl<E: Pairing> EnvelopeProof<E> for CommittedAggregateProof<E> {
+    const VARIANT: ProofVariant = ProofVariant::Committed;
+
+    fn nproofs(&self) -> u32 {
+        self.proof.tmipp.gipa.nproofs
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        CommittedAggregateProof::validate(self)
+    }
+}
+
+impl<E: Pairing> EnvelopeProof<E> for SuccinctAggregateProof<E> {
+    const VARIANT: ProofVariant = ProofVariant::Succinct;
+
+    fn nproofs(&self) -> u32 {
+        self.proof.tmipp.gipa.nproofs
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        SuccinctAggregateProof::validate(self)
+    }
+}
+
+/// Returns the identifier of the curve written in envelopes: the first 8
+/// bytes of the SHA256 hash of the compressed generators of G1 and G2, which
+/// tells curves apart without any registry of curves.
+pub fn curve_id<E: Pairing>() -> [u8; 8] {
+    let mut bytes = Vec::new();
+    (E::G1Affine::generator(), E::G2Affine::generator())
+        .serialize_compressed(&mut bytes)
+        .expect("serialization failed");
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&Sha256::digest(&bytes)[..8]);
+    id
+}
+
+/// EnvelopeHeader describes the aggregate proof following it in an envelope,
+/// so that a proof given to the wrong verifier is rejected with an explicit
+/// error before it is even deserialized.
+///
+/// The version 1 of the envelope is, with integers in little endian:
+/// * the magic bytes `SNPK` and the version, on 2 bytes
+/// * the proof variant, on 1 byte
+/// * the identifier of the curve given by `curve_id`, on 8 bytes
+/// * the number of proofs aggregated, on 4 bytes
+/// * the hash of the verifier SRS, on 32 bytes
+/// * 1 followed by the digest of the Groth16 verifying key given by
+///   `vk_digest` on 32 bytes, or 0 when the key is not bound
+/// * the length of the proof on 8 bytes, followed by its compressed
+///   encoding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvelopeHeader {
+    pub version: u16,
+    pub variant: ProofVariant,
+    pub curve_id: [u8; 8],
+    pub nproofs: u32,
+    pub srs_hash: [u8; 32],
+    pub vk_hash: Option<[u8; 32]>,
+    /// length of the encoding of the proof following the header
+    pub proof_len: u64,
+}
+
+impl EnvelopeHeader {
+    /// Writes the header in the format of its version.
+    pub fn write<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        ENVELOPE_MAGIC.serialize_compressed(&mut out)?;
+        self.version.serialize_compressed(&mut out)?;
+        match self.version {
+            1 => {
+                (self.variant as u8).serialize_compressed(&mut out)?;
+                (self.curve_id, self.nproofs, self.srs_hash).serialize_compressed(&mut out)?;
+                (self.vk_hash, self.proof_len).serialize_compressed(&mut out)?;
+                Ok(())
+            }
+            v => Err(Error::InvalidEnvelope(format!("unsupported version {}", v))),
+        }
+    }
+
+    /// Reads a header, dispatching on its version.
+    pub fn read<R: Read>(mut source: R) -> Result<Self, Error> {
+        let magic = <[u8; 4]>::deserialize_compressed(&mut source)?;
+        if magic != ENVELOPE_MAGIC {
+            return Err(Error::InvalidEnvelope("invalid magic bytes".to_string()));
+        }
+        let version = u16::deserialize_compressed(&mut source)?;
+        match version {
+            1 => {
+                let variant = u8::deserialize_compressed(&mut source)?.try_into()?;
+                let (curve_id, nproofs, srs_hash) =
+                    CanonicalDeserialize::deserialize_compressed(&mut source)?;
+                let (vk_hash, proof_len) =
+                    CanonicalDeserialize::deserialize_compressed(&mut source)?;
+                Ok(Self {
+                    version,
+                    variant,
+                    curve_id,
+                    nproofs,
+                    srs_hash,
+                    vk_hash,
+                    proof_len,
+                })
+            }
+            v => Err(Error::InvalidEnvelope(format!("unsupported version {}", v))),
+        }
+    }
+
+    /// Checks that the proof described is to be verified on the curve `E`
+    /// with the verifier SRS and, if both the envelope and the caller give
+    /// one, the Groth16 verifying key.
+    pub fn check<E: Pairing>(
+        &self,
+        v_srs: &VerifierSRS<E>,
+        vk: Option<&VerifyingKey<E>>,
+    ) -> Result<(), Error> {
+        if self.curve_id != curve_id::<E>() {
+            return Err(Error::InvalidEnvelope("curve mismatch".to_string()));
+        }
+        if self.nproofs as usize != v_srs.n {
+            return Err(Error::InvalidEnvelope(format!(
+                "proof of {} proofs for an SRS of {} proofs",
+                self.nproofs, v_srs.n
+            )));
+        }
+        if self.srs_hash[..] != v_srs.hash()[..] {
+            return Err(Error::InvalidEnvelope("SRS mismatch".to_string()));
+        }
+        match (self.vk_hash, vk) {
+            (Some(hash), Some(vk)) if hash[..] != vk_digest(vk)[..] => {
+                Err(Error::InvalidEnvelope("verifying key mismatch".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes the proof in an envelope of the current version, binding the
+/// verifier SRS and, if given, the Groth16 verifying key.
+pub fn write_envelope<E: Pairing, P: EnvelopeProof<E>, W: Write>(
+    proof: &P,
+    v_srs: &VerifierSRS<E>,
+    vk: Option<&VerifyingKey<E>>,
+    mut out: W,
+) -> Result<(), Error> {
+    let mut srs_hash = [0u8; 32];
+    srs_hash.copy_from_slice(&v_srs.hash());
+    let vk_hash = vk.map(|vk| {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&vk_digest(vk));
+        hash
+    });
+    let header = EnvelopeHeader {
+        version: ENVELOPE_VERSION,
+        variant: P::VARIANT,
+        curve_id: curve_id::<E>(),
+        nproofs: proof.nproofs(),
+        srs_hash,
+        vk_hash,
+        proof_len: proof.compressed_size() as u64,
+    };
+    header.write(&mut out)?;
+    proof.serialize_compressed(&mut out)?;
+    Ok(())
+}
+
+/// Reads a proof of type `P` from an envelope, checking the header against
+/// the curve, the verifier SRS and the verifying key given with
+/// `EnvelopeHeader::check` before reading the proof itself, validated with
+/// `EnvelopeProof::validate`.
+pub fn read_envelope<E: Pairing, P: EnvelopeProof<E>, R: Read>(
+    mut source: R,
+    v_srs: &VerifierSRS<E>,
+    vk: Option<&VerifyingKey<E>>,
+) -> Result<P, Error> {
+    let header = EnvelopeHeader::read(&mut source)?;
+    if header.variant != P::VARIANT {
+        return Err(Error::InvalidEnvelope(format!(
+            "{:?} proof instead of {:?} proof",
+            header.variant,
+            P::VARIANT
+        )));
+    }
+    header.check(v_srs, vk)?;
+    let proof = P::deserialize_with_mode(&mut source, Compress::Yes, Validate::No)?;
+    if proof.nproofs() != header.nproofs || proof.compressed_size() as u64 != header.proof_len {
+        return Err(Error::InvalidEnvelope(
+            "proof inconsistent with the header".to_string(),
+        ));
+    }
+    proof.validate()?;
+    Ok(proof)
+}
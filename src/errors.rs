@@ -1,26 +1,60 @@
 use ark_serialize::SerializationError;
-use thiserror::Error;
+use ark_std::{fmt, string::String};
 
-#[derive(Debug, Error)]
+#[derive(Debug)]
 pub enum Error {
-    #[error("Serialization error: {0}")]
-    Serialization(#[from] SerializationError),
+    Serialization(SerializationError),
 
-    #[error("Commitment key length invalid")]
     InvalidKeyLength,
 
-    #[error("Vectors length do not match for inner product (IP)")]
     InvalidIPVectorLength,
 
-    #[error("Invalid pairing result")]
     InvalidPairing,
 
-    #[error("Invalid SRS: {0}")]
     InvalidSRS(String),
 
-    #[error("Invalid proof: {0}")]
     InvalidProof(String),
 
-    #[error("Malformed Groth16 verifying key")]
     MalformedVerifyingKey,
+
+    Worker(String),
+
+    PolicyViolation(String),
+
+    InvalidEnvelope(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Serialization(e) => write!(f, "Serialization error: {}", e),
+            Error::InvalidKeyLength => write!(f, "Commitment key length invalid"),
+            Error::InvalidIPVectorLength => {
+                write!(f, "Vectors length do not match for inner product (IP)")
+            }
+            Error::InvalidPairing => write!(f, "Invalid pairing result"),
+            Error::InvalidSRS(e) => write!(f, "Invalid SRS: {}", e),
+            Error::InvalidProof(e) => write!(f, "Invalid proof: {}", e),
+            Error::MalformedVerifyingKey => write!(f, "Malformed Groth16 verifying key"),
+            Error::Worker(e) => write!(f, "Distributed aggregation worker error: {}", e),
+            Error::PolicyViolation(e) => write!(f, "Verification policy violated: {}", e),
+            Error::InvalidEnvelope(e) => write!(f, "Invalid proof envelope: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SerializationError> for Error {
+    fn from(e: SerializationError) -> Self {
+        Error::Serialization(e)
+    }
 }
@@ -0,0 +1,230 @@
+use ark_ff::PrimeField;
+use ark_std::{cfg_iter, string::ToString, vec::Vec};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::Error;
+
+/// PublicInputs is a source of the public inputs of all the aggregated proofs
+/// for the verifier. It lets the verifier read the inputs column by column,
+/// i.e. the same input of all proofs at once, without requiring them to be
+/// materialized proof by proof.
+///
+/// It is implemented for the row-major `[Vec<F>]` and `Vec<Vec<F>>`, with one
+/// vector per proof, for the column-major `ColumnMajorInputs` and for the
+/// `LazyInputs` generated on the fly.
+pub trait PublicInputs<F: PrimeField>: Sync {
+    /// Returns the number of proofs.
+    fn num_proofs(&self) -> usize;
+
+    /// Returns the number of public inputs of each proof, or None if the
+    /// proofs have different numbers of inputs.
+    fn num_inputs(&self) -> Option<usize>;
+
+    /// Returns the i-th public input of the j-th proof.
+    fn input(&self, j: usize, i: usize) -> F;
+
+    /// Returns the value of the i-th public input if it is the same for all
+    /// proofs. The verifier then computes the combination of the column in
+    /// closed form.
+    fn constant(&self, _i: usize) -> Option<F> {
+        None
+    }
+
+    /// Returns $\sum_j x_{j,i} r^j$, the combination of the i-th inputs of all
+    /// proofs with the powers of $r$.
+    fn combine_column(&self, i: usize, powers: &[F]) -> F {
+        (0..self.num_proofs())
+            .map(|j| self.input(j, i) * powers[j])
+            .sum()
+    }
+}
+
+impl<F: PrimeField> PublicInputs<F> for [Vec<F>] {
+    fn num_proofs(&self) -> usize {
+        self.len()
+    }
+
+    fn num_inputs(&self) -> Option<usize> {
+        let len = self.first().map(|p| p.len()).unwrap_or(0);
+        self.iter().all(|p| p.len() == len).then_some(len)
+    }
+
+    fn input(&self, j: usize, i: usize) -> F {
+        self[j][i]
+    }
+}
+
+impl<F: PrimeField> PublicInputs<F> for Vec<Vec<F>> {
+    fn num_proofs(&self) -> usize {
+        self.as_slice().num_proofs()
+    }
+
+    fn num_inputs(&self) -> Option<usize> {
+        self.as_slice().num_inputs()
+    }
+
+    fn input(&self, j: usize, i: usize) -> F {
+        self[j][i]
+    }
+}
+
+/// A column of public inputs, i.e. the same public input of all proofs.
+#[derive(Clone, Debug)]
+pub enum Column<'a, F> {
+    /// the input of each proof, in the order of the proofs
+    Values(&'a [F]),
+    /// an input equal for all proofs
+    Constant(F),
+}
+
+/// Public inputs stored column by column.
+#[derive(Clone, Debug)]
+pub struct ColumnMajorInputs<'a, F> {
+    nproofs: usize,
+    columns: Vec<Column<'a, F>>,
+}
+
+impl<'a, F: PrimeField> ColumnMajorInputs<'a, F> {
+    /// Returns the inputs of `nproofs` proofs made of the given columns, in
+    /// the order of the inputs. Each column of values must contain one value
+    /// per proof.
+    pub fn new(nproofs: usize, columns: Vec<Column<'a, F>>) -> Result<Self, Error> {
+        let valid = columns.iter().all(|c| match c {
+            Column::Values(values) => values.len() == nproofs,
+            Column::Constant(_) => true,
+        });
+        if !valid {
+            return Err(Error::InvalidProof(
+                "public inputs column len != number of proofs".to_string(),
+            ));
+        }
+        Ok(Self { nproofs, columns })
+    }
+}
+
+impl<'a, F: PrimeField> PublicInputs<F> for ColumnMajorInputs<'a, F> {
+    fn num_proofs(&self) -> usize {
+        self.nproofs
+    }
+
+    fn num_inputs(&self) -> Option<usize> {
+        Some(self.columns.len())
+    }
+
+    fn input(&self, j: usize, i: usize) -> F {
+        match &self.columns[i] {
+            Column::Values(values) => values[j],
+            Column::Constant(c) => *c,
+        }
+    }
+
+    fn constant(&self, i: usize) -> Option<F> {
+        match &self.columns[i] {
+            Column::Values(_) => None,
+            Column::Constant(c) => Some(*c),
+        }
+    }
+
+    fn combine_column(&self, i: usize, powers: &[F]) -> F {
+        match &self.columns[i] {
+            Column::Values(values) => cfg_iter!(values)
+                .zip(cfg_iter!(powers))
+                .map(|(x, r)| *x * r)
+                .sum(),
+            Column::Constant(c) => *c * powers.iter().sum::<F>(),
+        }
+    }
+}
+
+/// Public inputs generated on the fly, e.g. derived from a seed, by a function
+/// returning the i-th input of the j-th proof given `(j, i)`.
+#[derive(Clone, Debug)]
+pub struct LazyInputs<F, G> {
+    nproofs: usize,
+    constants: Vec<Option<F>>,
+    generator: G,
+}
+
+impl<F: PrimeField, G: Fn(usize, usize) -> F + Sync> LazyInputs<F, G> {
+    pub fn new(nproofs: usize, ninputs: usize, generator: G) -> Self {
+        Self {
+            nproofs,
+            constants: vec![None; ninputs],
+            generator,
+        }
+    }
+
+    /// Sets the i-th input of all proofs to the given value. The generator is
+    /// not called for this input anymore.
+    pub fn with_constant(mut self, i: usize, value: F) -> Self {
+        self.constants[i] = Some(value);
+        self
+    }
+}
+
+impl<F: PrimeField, G: Fn(usize, usize) -> F + Sync> PublicInputs<F> for LazyInputs<F, G> {
+    fn num_proofs(&self) -> usize {
+        self.nproofs
+    }
+
+    fn num_inputs(&self) -> Option<usize> {
+        Some(self.constants.len())
+    }
+
+    fn input(&self, j: usize, i: usize) -> F {
+        self.constants[i].unwrap_or_else(|| (self.generator)(j, i))
+    }
+
+    fn constant(&self, i: usize) -> Option<F> {
+        self.constants[i]
+    }
+
+    fn combine_column(&self, i: usize, powers: &[F]) -> F {
+        cfg_iter!(powers)
+            .enumerate()
+            .map(|(j, r)| self.input(j, i) * r)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_std::UniformRand;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_public_inputs_layouts() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let n = 8;
+        let first = (0..n).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+        let constant = Fr::from(42u64);
+        let rows = first.iter().map(|x| vec![*x, constant]).collect::<Vec<_>>();
+        let columns =
+            ColumnMajorInputs::new(n, vec![Column::Values(&first), Column::Constant(constant)])
+                .unwrap();
+        let lazy = LazyInputs::new(n, 2, |j, _| first[j]).with_constant(1, constant);
+        let powers = (0..n).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+
+        assert_eq!(rows.num_inputs(), Some(2));
+        assert_eq!(columns.constant(1), Some(constant));
+        assert_eq!(lazy.constant(0), None);
+        for i in 0..2 {
+            let expected = rows.combine_column(i, &powers);
+            assert_eq!(columns.combine_column(i, &powers), expected);
+            assert_eq!(lazy.combine_column(i, &powers), expected);
+            for j in 0..n {
+                assert_eq!(columns.input(j, i), rows[j][i]);
+                assert_eq!(lazy.input(j, i), rows[j][i]);
+            }
+        }
+
+        let mut ragged = rows;
+        ragged[3].pop();
+        assert_eq!(ragged.num_inputs(), None);
+        ColumnMajorInputs::new(n + 1, vec![Column::Values(&first)])
+            .expect_err("column of the wrong length");
+    }
+}
@@ -1,25 +1,63 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+
 #[macro_use]
 mod macros;
 
+#[cfg(feature = "std")]
+mod aggregator;
+pub mod bellperson;
 mod commitment;
+mod committed;
+mod cost;
+#[cfg(feature = "std")]
+pub mod distributed;
+mod envelope;
 mod errors;
+mod inputs;
 mod ip;
 mod pairing_check;
+mod policy;
+mod prepared;
 mod proof;
 mod prover;
 pub mod srs;
+mod statement;
+mod succinct;
+mod sync;
+mod torus;
 pub mod transcript;
 mod verifier;
 
+#[cfg(feature = "std")]
+pub use aggregator::*;
+pub use committed::*;
+pub use cost::*;
+#[cfg(feature = "std")]
+pub use distributed::*;
+pub use envelope::*;
 pub use errors::*;
+pub use inputs::*;
+pub use pairing_check::*;
+pub use policy::*;
+pub use prepared::*;
+pub use proof::*;
 pub use prover::*;
+pub use statement::*;
+pub use succinct::*;
+pub use torus::*;
 pub use transcript::*;
 pub use verifier::*;
 
 use ark_ec::{AffineRepr, CurveGroup};
 use ark_ff::Field;
-use ark_std::ops::AddAssign;
+use ark_std::{cfg_iter, cfg_iter_mut, ops::AddAssign, vec::Vec};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+
 /// Returns the vector used for the linear combination fo the inner pairing product
 /// between A and B for the Groth16 aggregation: A^r * B. It is required as it
 /// is not enough to simply prove the ipp of A*B, we need a random linear
@@ -37,8 +75,8 @@ pub(crate) fn structured_scalar_power<F: Field>(num: usize, s: &F) -> Vec<F> {
 /// vec[i+split]^scaler$. The `vec` vector is half of its size after this call.
 pub(crate) fn compress<C: AffineRepr>(vec: &mut Vec<C>, split: usize, scaler: &C::ScalarField) {
     let (left, right) = vec.split_at_mut(split);
-    left.par_iter_mut()
-        .zip(right.par_iter())
+    cfg_iter_mut!(left)
+        .zip(cfg_iter!(right))
         .for_each(|(a_l, a_r)| {
             //let mut x = mul!(a_r.into_group(), scaler.clone());
             let sc = scaler.clone();
@@ -49,3 +87,19 @@ pub(crate) fn compress<C: AffineRepr>(vec: &mut Vec<C>, split: usize, scaler: &C
     let len = left.len();
     vec.resize(len, C::zero());
 }
+
+/// Runs both closures and returns their results, in parallel with rayon if the
+/// `parallel` feature is enabled or one after the other otherwise.
+pub(crate) fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "parallel")]
+    return rayon::join(a, b);
+
+    #[cfg(not(feature = "parallel"))]
+    (a(), b())
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "parallel")]
 macro_rules! try_par {
     ($(let $name:ident = $f:expr),+) => {
         $(
@@ -16,6 +17,7 @@ macro_rules! try_par {
     };
 }
 
+#[cfg(feature = "parallel")]
 macro_rules! par {
     ($(let $name:ident = $f:expr),+) => {
         $(
@@ -55,6 +57,65 @@ macro_rules! par {
     }
 }
 
+/// Sequential fallback of `try_par!` when the `parallel` feature is disabled:
+/// the expressions are evaluated one after the other.
+#[cfg(not(feature = "parallel"))]
+macro_rules! try_par {
+    ($(let $name:ident = $f:expr),+) => {
+        $(
+            let $name = $f?;
+        )+
+    };
+}
+
+/// Sequential fallback of `par!` when the `parallel` feature is disabled: the
+/// expressions are evaluated one after the other.
+#[cfg(not(feature = "parallel"))]
+macro_rules! par {
+    ($(let $name:ident = $f:expr),+) => {
+        $(
+            let $name = $f;
+        )+
+    };
+
+    ($(let ($name1:ident, $name2:ident) = $f:block),+) => {
+        $(
+            let ($name1, $name2) = $f;
+        )+
+    }
+}
+
+/// Folds the items of an iterator created with the `cfg_*` macros of ark-std
+/// as `ParallelIterator::fold` does, given the function returning the initial
+/// value and the operation. It returns an iterator over the partial results,
+/// which are a single one if the `parallel` feature is disabled.
+macro_rules! cfg_fold {
+    ($e:expr, $init:expr, $op:expr) => {{
+        #[cfg(feature = "parallel")]
+        let result = $e.fold($init, $op);
+
+        #[cfg(not(feature = "parallel"))]
+        let result = ark_std::iter::once($e.fold($init(), $op));
+
+        result
+    }};
+}
+
+/// Reduces the items of an iterator created with the `cfg_*` macros of
+/// ark-std as `ParallelIterator::reduce` does, given the function returning
+/// the identity and the operation.
+macro_rules! cfg_reduce {
+    ($e:expr, $identity:expr, $op:expr) => {{
+        #[cfg(feature = "parallel")]
+        let result = $e.reduce($identity, $op);
+
+        #[cfg(not(feature = "parallel"))]
+        let result = $e.fold($identity(), $op);
+
+        result
+    }};
+}
+
 macro_rules! mul {
     ($a:expr, $b:expr) => {{
         let mut a = $a;
@@ -70,3 +131,13 @@ macro_rules! sub {
         a
     }};
 }
+
+/// Without `std`, the debugging information printed by `dbg!` is discarded.
+#[cfg(not(feature = "std"))]
+macro_rules! dbg {
+    ($($e:expr),* $(,)?) => {{
+        $(
+            let _ = &$e;
+        )*
+    }};
+}
@@ -4,10 +4,12 @@ use ark_ec::{
 };
 // {AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::{Field, PrimeField};
-use ark_std::{ops::Mul, rand::Rng, sync::Mutex, One, UniformRand, Zero};
+use ark_std::{cfg_into_iter, cfg_iter, ops::Mul, rand::Rng, vec::Vec, One, UniformRand, Zero};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
-use std::ops::MulAssign;
+use super::sync::Mutex;
+use ark_std::ops::MulAssign;
 
 /// PairingCheck represents a check of the form e(A,B)e(C,D)... = T. Checks can
 /// be aggregated together using random linear combination. The efficiency comes
@@ -18,6 +20,11 @@ use std::ops::MulAssign;
 /// before going into a final exponentiation result
 /// - a right side result which is already in the right subgroup Gt which is to
 /// be compared to the left side when "final_exponentiatiat"-ed
+///
+/// The pending check of an aggregated proof, returned by
+/// `aggregate_proof_pairing_check`, is fully randomized so any randomized
+/// check of the application, and at most one non randomized, can be merged
+/// into it to share a single final exponentiation.
 #[derive(Debug, Copy, Clone)]
 pub struct PairingCheck<E: Pairing> {
     left: <E as Pairing>::TargetField,
@@ -27,6 +34,12 @@ pub struct PairingCheck<E: Pairing> {
     non_randomized: u8,
 }
 
+impl<E: Pairing> Default for PairingCheck<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<E> PairingCheck<E>
 where
     E: Pairing,
@@ -96,33 +109,47 @@ where
     /// e(rA,B)e(rC,D) ... = out^r <=>
     /// e(A,B)^r e(C,D)^r = out^r <=> e(g,h)^{abr + cdr} = out^r
     /// (e(g,h)^{ab + cd})^r = out^r
-    pub fn rand<'a, R: Rng + Send>(
-        rng: &Mutex<R>,
+    pub fn rand<'a, R: Rng + ?Sized>(
+        rng: &mut R,
         it: &[(&'a E::G1Affine, &'a E::G2Affine)],
         out: &'a <E as Pairing>::TargetField,
     ) -> PairingCheck<E> {
-        let coeff = rand_fr::<E, R>(&rng);
-        let miller_out = it
-            .into_par_iter()
+        Self::rand_with_coeff(nonzero_fr::<E, R>(rng), it, out)
+    }
+
+    /// returns a pairing tuple that is scaled by the given coefficient, as in
+    /// `rand`. The coefficient must be non zero and unpredictable by the
+    /// prover, e.g. derived from a transcript containing the whole proof.
+    pub fn rand_with_coeff<'a>(
+        coeff: E::ScalarField,
+        it: &[(&'a E::G1Affine, &'a E::G2Affine)],
+        out: &'a <E as Pairing>::TargetField,
+    ) -> PairingCheck<E> {
+        let prepared = cfg_iter!(it)
+            .map(|(_, b)| E::G2Prepared::from(**b))
+            .collect::<Vec<_>>();
+        let it = it
+            .iter()
+            .zip(prepared.iter())
+            .map(|((a, _), b)| (*a, b))
+            .collect::<Vec<_>>();
+        Self::rand_prepared_with_coeff(coeff, &it, out)
+    }
+
+    /// Similar to `rand_with_coeff` but with the G2 points already prepared
+    /// for the miller loop, e.g. fixed points of a verifying key.
+    pub fn rand_prepared_with_coeff<'a>(
+        coeff: E::ScalarField,
+        it: &[(&'a E::G1Affine, &'a E::G2Prepared)],
+        out: &'a <E as Pairing>::TargetField,
+    ) -> PairingCheck<E> {
+        let miller_out = cfg_into_iter!(it)
             .map(|(a, b)| {
                 let na = a.mul(coeff).into_affine();
-                (E::G1Prepared::from(na), E::G2Prepared::from(**b))
+                (E::G1Prepared::from(na), (*b).clone())
             })
-            .map(|(a, b)| E::miller_loop(a, b))
-            .fold(
-                || <E as Pairing>::TargetField::one(),
-                |mut acc, res| {
-                    acc.mul_assign(&(res.0));
-                    acc
-                },
-            )
-            .reduce(
-                || <E as Pairing>::TargetField::one(),
-                |mut acc, res| {
-                    acc.mul_assign(&res);
-                    acc
-                },
-            );
+            .map(|(a, b)| E::miller_loop(a, b).0)
+            .product::<<E as Pairing>::TargetField>();
         let mut outt = out.clone();
         if out != &<E as Pairing>::TargetField::one() {
             // we only need to make this expensive operation is the output is
@@ -142,7 +169,7 @@ where
         mul_if_not_one::<E>(&mut self.left, &p2.left);
         mul_if_not_one::<E>(&mut self.right, &p2.right);
         // A merged PairingCheck is only randomized if both of its contributors are.
-        self.non_randomized += p2.non_randomized;
+        self.non_randomized = self.non_randomized.saturating_add(p2.non_randomized);
     }
 
     /// Returns false if there is more than 1 non-random check and otherwise
@@ -162,8 +189,21 @@ where
     }
 }
 
-fn rand_fr<E: Pairing, R: Rng + Send>(r: &Mutex<R>) -> E::ScalarField {
-    let rng: &mut R = &mut r.lock().unwrap();
+impl<E: Pairing> PartialEq for PairingCheck<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left
+            && self.right == other.right
+            && self.non_randomized == other.non_randomized
+    }
+}
+
+impl<E: Pairing> Eq for PairingCheck<E> {}
+
+pub(crate) fn rand_fr<E: Pairing, R: Rng + Send>(r: &Mutex<R>) -> E::ScalarField {
+    nonzero_fr::<E, R>(&mut r.lock().unwrap())
+}
+
+fn nonzero_fr<E: Pairing, R: Rng + ?Sized>(rng: &mut R) -> E::ScalarField {
     loop {
         let c = E::ScalarField::rand(rng);
         if c != E::ScalarField::zero() {
@@ -189,7 +229,7 @@ fn mul_if_not_one<E: Pairing>(
 #[cfg(test)]
 mod test {
     use super::*;
-    use ark_bn254::{Bn254, G1Projective, G2Projective};
+    use ark_bn254::{Bn254, Fq12, G1Projective, G2Projective};
     use ark_std::{rand::Rng, UniformRand};
     use rand_core::SeedableRng;
 
@@ -197,9 +237,8 @@ mod test {
         let g1r = G1Projective::rand(r);
         let g2r = G2Projective::rand(r);
         let exp = Bn254::pairing(g1r.clone(), g2r.clone());
-        let mr = Mutex::new(r);
         let tuple =
-            PairingCheck::<Bn254>::rand(&mr, &[(&g1r.into_affine(), &g2r.into_affine())], &exp.0);
+            PairingCheck::<Bn254>::rand(r, &[(&g1r.into_affine(), &g2r.into_affine())], &exp.0);
         assert!(tuple.verify());
         tuple
     }
@@ -217,4 +256,19 @@ mod test {
             });
         assert!(final_tuple.verify());
     }
+
+    #[test]
+    fn test_merge_non_randomized() {
+        // 1 = 1 holds but can't be randomized
+        let check = PairingCheck::<Bn254>::from_products(vec![], Fq12::one());
+        assert!(check.verify());
+        let mut acc = PairingCheck::default();
+        acc.merge(&check);
+        assert!(acc.verify());
+        // the counter must not wrap around to zero
+        for _ in 0..300 {
+            acc.merge(&check);
+            assert!(!acc.verify());
+        }
+    }
 }
@@ -0,0 +1,86 @@
+use ark_ec::pairing::Pairing;
+use ark_groth16::VerifyingKey;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::{inputs::PublicInputs, proof::AggregateProof, srs::VerifierSRS};
+use crate::Error;
+
+/// VerificationPolicy bounds the work a verifier accepts to do for an
+/// aggregated proof received from an untrusted party. The policy is checked
+/// before anything else, then the verifier runs the cheap checks first,
+/// namely the parsing of the proof, the subgroup membership of its elements and
+/// the relations of MIPP requiring no pairing, and only computes the pairings
+/// once they all passed. It rejects the proof as soon as any check fails.
+///
+/// The default policy sets no limit; it is meant to be restricted with the
+/// setters, e.g.
+/// `VerificationPolicy::default().max_nproofs(1 << 16).allow_srs(&srs)`.
+#[derive(Clone, Debug, Default)]
+pub struct VerificationPolicy {
+    max_nproofs: Option<usize>,
+    allowed_srs: Vec<Vec<u8>>,
+    max_public_inputs: Option<usize>,
+}
+
+impl VerificationPolicy {
+    /// Sets the maximum number of aggregated proofs.
+    pub fn max_nproofs(mut self, max: usize) -> Self {
+        self.max_nproofs = Some(max);
+        self
+    }
+
+    /// Adds the verifier SRS to the ones accepted. Once any SRS is allowed,
+    /// proofs verified with any other SRS are rejected.
+    pub fn allow_srs<E: Pairing>(self, srs: &VerifierSRS<E>) -> Self {
+        self.allow_srs_hash(srs.hash())
+    }
+
+    /// Adds the hash of a verifier SRS, as returned by `VerifierSRS::hash`, to
+    /// the ones accepted.
+    pub fn allow_srs_hash(mut self, hash: Vec<u8>) -> Self {
+        self.allowed_srs.push(hash);
+        self
+    }
+
+    /// Sets the maximum number of public inputs of each proof.
+    pub fn max_public_inputs(mut self, max: usize) -> Self {
+        self.max_public_inputs = Some(max);
+        self
+    }
+
+    /// Checks the statement and the aggregated proof against the policy, then
+    /// validates the proof with `AggregateProof::validate`: it must be well
+    /// formed and its G1, G2 and target group elements in the prime order
+    /// subgroups. None of these checks requires a pairing nor reading the
+    /// public inputs.
+    pub fn check<E: Pairing, I: PublicInputs<E::ScalarField> + ?Sized>(
+        &self,
+        srs: &VerifierSRS<E>,
+        vk: &VerifyingKey<E>,
+        public_inputs: &I,
+        proof: &AggregateProof<E>,
+    ) -> Result<(), Error> {
+        if let Some(max) = self.max_nproofs {
+            let nproofs = proof.tmipp.gipa.nproofs as usize;
+            if nproofs > max || public_inputs.num_proofs() > max || srs.n > max {
+                return Err(Error::PolicyViolation(format!(
+                    "number of proofs {} > {}",
+                    nproofs, max
+                )));
+            }
+        }
+        if let Some(max) = self.max_public_inputs {
+            if vk.gamma_abc_g1.len() > max + 1 {
+                return Err(Error::PolicyViolation(format!(
+                    "number of public inputs {} > {}",
+                    vk.gamma_abc_g1.len() - 1,
+                    max
+                )));
+            }
+        }
+        if !self.allowed_srs.is_empty() && !self.allowed_srs.contains(&srs.hash()) {
+            return Err(Error::PolicyViolation("SRS not allowed".to_string()));
+        }
+        proof.validate()
+    }
+}
@@ -0,0 +1,208 @@
+use ark_ec::{pairing::Pairing, scalar_mul::fixed_base::FixedBase, CurveGroup};
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, PreparedVerifyingKey, VerifyingKey};
+use ark_std::ops::Neg;
+use ark_std::{cfg_iter, rand::Rng, vec::Vec};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::{
+    inputs::PublicInputs,
+    pairing_check::PairingCheck,
+    policy::VerificationPolicy,
+    proof::AggregateProof,
+    srs::VerifierSRS,
+    statement::bind_statement,
+    transcript::Transcript,
+    verifier::{
+        pending_check, verify_aggregate_proof_with_extensions, verify_with_policy, Extensions,
+        VerifierKeys,
+    },
+};
+use crate::Error;
+
+/// Window size of the tables of the SRS generators, used four times each per
+/// verification.
+const SRS_WINDOW: usize = 8;
+/// Window size of the tables of the verifying key elements, used once each
+/// per verification.
+const VK_WINDOW: usize = 6;
+
+/// FixedBaseTable contains the multiples of a fixed base needed to multiply it
+/// by any scalar with a windowed multiplication, with no doubling at all.
+#[derive(Clone, Debug)]
+pub(crate) struct FixedBaseTable<G: CurveGroup> {
+    window: usize,
+    outerc: usize,
+    table: Vec<Vec<G::Affine>>,
+}
+
+impl<G: CurveGroup> FixedBaseTable<G> {
+    pub fn new(base: G, window: usize) -> Self {
+        let scalar_bits = G::ScalarField::MODULUS_BIT_SIZE as usize;
+        Self {
+            window,
+            outerc: scalar_bits.div_ceil(window),
+            table: FixedBase::get_window_table::<G>(scalar_bits, window, base),
+        }
+    }
+
+    pub fn mul(&self, scalar: &G::ScalarField) -> G {
+        FixedBase::windowed_mul::<G>(self.outerc, self.window, &self.table, scalar)
+    }
+}
+
+/// PreparedAggregateVerifier verifies aggregated proofs of a single verifying
+/// key and verifier SRS, computing once everything that only depends on them:
+/// the G2 line coefficients of $\gamma$, $\delta$ and $-h$ and the fixed-base
+/// tables of the elements of the verifying key and of $g$ and $h$.
+/// The term $e(\alpha r_{sum}, \beta)$ of the Groth16 equation is derived from
+/// the prepared $e(\alpha, \beta)$ instead of a Miller loop.
+/// It is worth it as soon as more than a few proofs are verified.
+#[derive(Clone, Debug)]
+pub struct PreparedAggregateVerifier<E: Pairing> {
+    pub(crate) srs: VerifierSRS<E>,
+    pub(crate) pvk: PreparedVerifyingKey<E>,
+    pub(crate) g: FixedBaseTable<E::G1>,
+    pub(crate) h: FixedBaseTable<E::G2>,
+    pub(crate) gamma_abc_g1: Vec<FixedBaseTable<E::G1>>,
+    pub(crate) gamma_g2: E::G2Prepared,
+    pub(crate) delta_g2: E::G2Prepared,
+    pub(crate) neg_h: E::G2Prepared,
+}
+
+impl<E: Pairing + ark_std::fmt::Debug> PreparedAggregateVerifier<E> {
+    pub fn new(srs: VerifierSRS<E>, vk: &VerifyingKey<E>) -> Self {
+        let pvk = prepare_verifying_key(vk);
+        par! {
+            let g = FixedBaseTable::new(srs.g, SRS_WINDOW),
+            let h = FixedBaseTable::new(srs.h, SRS_WINDOW),
+            let gamma_abc_g1 = cfg_iter!(vk.gamma_abc_g1)
+                .map(|p| FixedBaseTable::new((*p).into(), VK_WINDOW))
+                .collect::<Vec<_>>()
+        };
+        Self {
+            gamma_g2: vk.gamma_g2.into(),
+            delta_g2: vk.delta_g2.into(),
+            neg_h: srs.h.neg().into_affine().into(),
+            srs,
+            pvk,
+            g,
+            h,
+            gamma_abc_g1,
+        }
+    }
+
+    pub fn srs(&self) -> &VerifierSRS<E> {
+        &self.srs
+    }
+
+    pub fn pvk(&self) -> &PreparedVerifyingKey<E> {
+        &self.pvk
+    }
+
+    /// Verifies the aggregated proof as `verify_aggregate_proof` does, binding
+    /// the statement in the transcript first.
+    pub fn verify<R: Rng + Send, T: Transcript + Send, I: PublicInputs<E::ScalarField> + ?Sized>(
+        &self,
+        public_inputs: &I,
+        proof: &AggregateProof<E>,
+        rng: R,
+        transcript: &mut T,
+    ) -> Result<(), Error> {
+        bind_statement(transcript, &self.pvk.vk, &self.srs, public_inputs)?;
+        self.verify_unchecked(public_inputs, proof, rng, transcript)
+    }
+
+    /// Verifies the aggregated proof as `verify_aggregate_proof_unchecked`
+    /// does, without binding any statement in the transcript.
+    pub fn verify_unchecked<
+        R: Rng + Send,
+        T: Transcript + Send,
+        I: PublicInputs<E::ScalarField> + ?Sized,
+    >(
+        &self,
+        public_inputs: &I,
+        proof: &AggregateProof<E>,
+        rng: R,
+        transcript: &mut T,
+    ) -> Result<(), Error> {
+        verify_aggregate_proof_with_extensions(
+            &VerifierKeys::prepared(self),
+            public_inputs,
+            proof,
+            &Extensions::default(),
+            rng,
+            transcript,
+        )
+    }
+
+    /// Verifies the aggregated proof as `verify_aggregate_proof_with_policy`
+    /// does.
+    pub fn verify_with_policy<
+        R: Rng + Send,
+        T: Transcript + Send,
+        I: PublicInputs<E::ScalarField> + ?Sized,
+    >(
+        &self,
+        policy: &VerificationPolicy,
+        public_inputs: &I,
+        proof: &AggregateProof<E>,
+        rng: R,
+        transcript: &mut T,
+    ) -> Result<(), Error> {
+        verify_with_policy(
+            &VerifierKeys::prepared(self),
+            policy,
+            public_inputs,
+            proof,
+            rng,
+            transcript,
+        )
+    }
+
+    /// Returns the pending pairing check of the aggregated proof as
+    /// `aggregate_proof_pairing_check` does.
+    pub fn pairing_check<
+        R: Rng + Send,
+        T: Transcript + Send,
+        I: PublicInputs<E::ScalarField> + ?Sized,
+    >(
+        &self,
+        public_inputs: &I,
+        proof: &AggregateProof<E>,
+        rng: R,
+        transcript: &mut T,
+    ) -> Result<PairingCheck<E>, Error> {
+        pending_check(
+            &VerifierKeys::prepared(self),
+            None,
+            public_inputs,
+            proof,
+            rng,
+            transcript,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fr, G1Projective, G2Projective};
+    use ark_ec::Group;
+    use ark_std::UniformRand;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_fixed_base_table() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let g1 = FixedBaseTable::new(G1Projective::generator(), SRS_WINDOW);
+        let g2 = FixedBaseTable::new(G2Projective::rand(&mut rng), VK_WINDOW);
+        for _ in 0..10 {
+            let s = Fr::rand(&mut rng);
+            assert_eq!(g1.mul(&s), G1Projective::generator() * s);
+            assert_eq!(g2.mul(&s), g2.mul(&Fr::from(1u64)) * s);
+        }
+        assert_eq!(g1.mul(&Fr::from(0u64)), G1Projective::default());
+    }
+}
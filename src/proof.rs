@@ -1,13 +1,16 @@
 use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{Field, One, PrimeField};
 use ark_serialize::{
     CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
 };
-use std::io::{Read, Write};
+use ark_std::io::{Read, Write};
+use ark_std::{string::ToString, vec::Vec};
 
 use super::Error;
 use super::{
     commitment::{self, Output},
     srs,
+    torus::{read_torus, write_torus, TorusCompressible},
 };
 
 /// AggregateProof contains all elements to verify n aggregated Groth16 proofs
@@ -41,31 +44,34 @@ impl<E: Pairing> AggregateProof<E> {
     /// Performs some high level checks on the length of vectors and others to
     /// make sure all items in the proofs are consistent with each other.
     pub fn parsing_check(&self) -> Result<(), Error> {
-        let gipa = &self.tmipp.gipa;
-        // 1. Check length of the proofs
-        if gipa.nproofs < 2 || gipa.nproofs as usize > srs::MAX_SRS_SIZE {
-            return Err(Error::InvalidProof(
-                "Proof length out of bounds".to_string(),
-            ));
-        }
-        // 2. Check if it's a power of two
-        if !gipa.nproofs.is_power_of_two() {
-            return Err(Error::InvalidProof(
-                "Proof length not a power of two".to_string(),
-            ));
-        }
-        // 3. Check all vectors are of the same length and of the correct length
-        let ref_len = (gipa.nproofs as f32).log2().ceil() as usize;
-        let all_same = ref_len == gipa.comms_ab.len()
-            && ref_len == gipa.comms_c.len()
-            && ref_len == gipa.z_ab.len()
-            && ref_len == gipa.z_c.len();
-        if !all_same {
-            return Err(Error::InvalidProof(
-                "Proof vectors unequal sizes".to_string(),
-            ));
-        }
-        Ok(())
+        self.tmipp.gipa.parsing_check()
+    }
+
+    /// Checks that the proof is well formed, as `parsing_check` does, and that
+    /// all its elements are valid, as `GipaProof::validate` does for the
+    /// elements of the GIPA loop: all G1, G2 and target group elements must be
+    /// in the prime order subgroups. Proofs received from untrusted parties
+    /// must be validated before being verified, which `read_validated` does.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.tmipp.gipa.validate()?;
+        let g1 = [
+            &self.agg_c,
+            &self.tmipp.wkey_opening.0,
+            &self.tmipp.wkey_opening.1,
+        ];
+        let g2 = [&self.tmipp.vkey_opening.0, &self.tmipp.vkey_opening.1];
+        g1.into_iter()
+            .try_for_each(Valid::check)
+            .and_then(|_| g2.into_iter().try_for_each(Valid::check))
+            .map_err(|_| {
+                Error::InvalidProof("point not in the prime order subgroup".to_string())
+            })?;
+        check_target_subgroup::<E>(
+            [&self.com_ab, &self.com_c]
+                .into_iter()
+                .flat_map(|c| [&c.0, &c.1])
+                .chain([&self.ip_ab]),
+        )
     }
 
     /// Writes the aggregate proof to the given destination. This method is for
@@ -77,12 +83,164 @@ impl<E: Pairing> AggregateProof<E> {
             .map_err(|e| Error::Serialization(e))
     }
 
-    /// Reads the aggregate proof to the given destination. This method is for
-    /// high level protocol to use it as a library. If you want to use within
-    /// another arkwork protocol, you can use the underlying implementation of
-    /// `CanonicalSerialize`.
-    pub fn read<R: Read>(mut source: R) -> Result<Self, Error> {
-        Self::deserialize_compressed(&mut source).map_err(|e| Error::Serialization(e))
+    /// Reads the aggregate proof to the given destination, validated with
+    /// `read_validated`. This method is for high level protocol to use it as
+    /// a library. If you want to use within another arkwork protocol, you can
+    /// use the underlying implementation of `CanonicalSerialize`, which only
+    /// validates the elements of the GIPA loop.
+    pub fn read<R: Read>(source: R) -> Result<Self, Error> {
+        Self::read_validated(source)
+    }
+
+    /// Reads the aggregate proof and checks it with `validate`, rejecting any
+    /// malformed proof before it reaches the verifier.
+    pub fn read_validated<R: Read>(source: R) -> Result<Self, Error> {
+        let proof = Self::deserialize_with_mode(source, Compress::Yes, Validate::No)?;
+        proof.validate()?;
+        Ok(proof)
+    }
+}
+
+/// Checks that all the elements are in the prime order subgroup of the target
+/// group, of order the order of the scalar field. Other elements of the
+/// multiplicative group of the target field, such as zero or elements of small
+/// order, would let a prover cancel out terms of the randomized checks.
+pub(crate) fn check_target_subgroup<'a, E: Pairing>(
+    elements: impl IntoIterator<Item = &'a E::TargetField>,
+) -> Result<(), Error> {
+    let modulus = E::ScalarField::MODULUS;
+    if elements.into_iter().all(|x| x.pow(modulus).is_one()) {
+        Ok(())
+    } else {
+        Err(Error::InvalidProof(
+            "target group element not in the prime order subgroup".to_string(),
+        ))
+    }
+}
+
+impl<E: Pairing> AggregateProof<E> {
+    /// Writes the aggregate proof in the layout of `write_torus`, each element
+    /// of the target group being written by `write_target`.
+    pub(crate) fn write_with_target<W: Write>(
+        &self,
+        mut out: W,
+        write_target: impl Fn(&E::TargetField, &mut W) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.parsing_check()?;
+        let gipa = &self.tmipp.gipa;
+        let write_output = |output: &Output<E::TargetField>, out: &mut W| {
+            write_target(&output.0, out)?;
+            write_target(&output.1, out)
+        };
+        write_output(&self.com_ab, &mut out)?;
+        write_output(&self.com_c, &mut out)?;
+        write_target(&self.ip_ab, &mut out)?;
+        self.agg_c.serialize_compressed(&mut out)?;
+
+        gipa.nproofs.serialize_compressed(&mut out)?;
+        for (l, r) in gipa.comms_ab.iter().chain(&gipa.comms_c) {
+            write_output(l, &mut out)?;
+            write_output(r, &mut out)?;
+        }
+        for (l, r) in &gipa.z_ab {
+            write_target(l, &mut out)?;
+            write_target(r, &mut out)?;
+        }
+        for z in &gipa.z_c {
+            z.serialize_compressed(&mut out)?;
+        }
+        (gipa.final_a, gipa.final_b, gipa.final_c).serialize_compressed(&mut out)?;
+        (gipa.final_vkey, gipa.final_wkey).serialize_compressed(&mut out)?;
+        self.tmipp.vkey_opening.serialize_compressed(&mut out)?;
+        self.tmipp.wkey_opening.serialize_compressed(&mut out)?;
+        Ok(())
+    }
+
+    /// Reads an aggregate proof written by `write_with_target`, each element
+    /// of the target group being read by `read_target`, which must check
+    /// that it is in the prime order subgroup.
+    pub(crate) fn read_with_target<R: Read>(
+        mut source: R,
+        read_target: impl Fn(&mut R) -> Result<E::TargetField, Error>,
+    ) -> Result<Self, Error> {
+        let read_output = |source: &mut R| -> Result<Output<E::TargetField>, Error> {
+            Ok(Output(
+                read_target(&mut *source)?,
+                read_target(&mut *source)?,
+            ))
+        };
+        let com_ab = read_output(&mut source)?;
+        let com_c = read_output(&mut source)?;
+        let ip_ab = read_target(&mut source)?;
+        let agg_c = E::G1Affine::deserialize_compressed(&mut source)?;
+
+        let nproofs = u32::deserialize_compressed(&mut source)?;
+        GipaProof::<E>::check_nproofs(nproofs)?;
+        let log_proofs = GipaProof::<E>::log_proofs(nproofs as usize);
+        let read_pairs = |source: &mut R| {
+            (0..log_proofs)
+                .map(|_| Ok((read_output(&mut *source)?, read_output(&mut *source)?)))
+                .collect::<Result<Vec<_>, Error>>()
+        };
+        let comms_ab = read_pairs(&mut source)?;
+        let comms_c = read_pairs(&mut source)?;
+        let z_ab = (0..log_proofs)
+            .map(|_| Ok((read_target(&mut source)?, read_target(&mut source)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let z_c = (0..log_proofs)
+            .map(|_| CanonicalDeserialize::deserialize_compressed(&mut source))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (final_a, final_b, final_c) =
+            CanonicalDeserialize::deserialize_compressed(&mut source)?;
+        let (final_vkey, final_wkey) = CanonicalDeserialize::deserialize_compressed(&mut source)?;
+        let (vkey_opening, wkey_opening) =
+            CanonicalDeserialize::deserialize_compressed(&mut source)?;
+
+        let proof = AggregateProof {
+            com_ab,
+            com_c,
+            ip_ab,
+            agg_c,
+            tmipp: TippMippProof {
+                gipa: GipaProof {
+                    nproofs,
+                    comms_ab,
+                    comms_c,
+                    z_ab,
+                    z_c,
+                    final_a,
+                    final_b,
+                    final_c,
+                    final_vkey,
+                    final_wkey,
+                },
+                vkey_opening,
+                wkey_opening,
+            },
+        };
+        // the points have been validated when read and the elements of the
+        // target group when decompressed
+        proof.parsing_check()?;
+        proof.tmipp.gipa.check_final_keys()?;
+        Ok(proof)
+    }
+}
+
+impl<E: Pairing> AggregateProof<E>
+where
+    E::TargetField: TorusCompressible,
+{
+    /// Writes the aggregate proof like `write`, but with the elements of the
+    /// target group compressed on the torus, see `TorusCompressible`. They
+    /// make most of the proof, which is about half the size.
+    pub fn write_torus<W: Write>(&self, out: W) -> Result<(), Error> {
+        self.write_with_target(out, |x, out| write_torus::<E, _>(x, out))
+    }
+
+    /// Reads an aggregate proof written by `write_torus`, checking that all
+    /// elements are in the prime order subgroups.
+    pub fn read_torus<R: Read>(source: R) -> Result<Self, Error> {
+        Self::read_with_target(source, |source| read_torus::<E, _>(source))
     }
 }
 
@@ -128,7 +286,85 @@ impl<E: Pairing> PartialEq for GipaProof<E> {
 
 impl<E: Pairing> GipaProof<E> {
     fn log_proofs(nproofs: usize) -> usize {
-        (nproofs as f32).log2().ceil() as usize
+        ark_std::log2(nproofs) as usize
+    }
+
+    /// Checks that the number of proofs is a power of two supported by the
+    /// SRS.
+    fn check_nproofs(nproofs: u32) -> Result<(), Error> {
+        // 1. Check length of the proofs
+        if nproofs < 2 || nproofs as usize > srs::MAX_SRS_SIZE {
+            return Err(Error::InvalidProof(
+                "Proof length out of bounds".to_string(),
+            ));
+        }
+        // 2. Check if it's a power of two
+        if !nproofs.is_power_of_two() {
+            return Err(Error::InvalidProof(
+                "Proof length not a power of two".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Performs some high level checks on the length of vectors and others to
+    /// make sure all items in the proofs are consistent with each other.
+    pub fn parsing_check(&self) -> Result<(), Error> {
+        Self::check_nproofs(self.nproofs)?;
+        // 3. Check all vectors are of the same length and of the correct length
+        let ref_len = Self::log_proofs(self.nproofs as usize);
+        let all_same = ref_len == self.comms_ab.len()
+            && ref_len == self.comms_c.len()
+            && ref_len == self.z_ab.len()
+            && ref_len == self.z_c.len();
+        if !all_same {
+            return Err(Error::InvalidProof(
+                "Proof vectors unequal sizes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the final commitment keys are not the identity. The final
+    /// values of the loop are only bound to the final commitments through
+    /// these keys, which the KZG openings prove to be derived from the SRS:
+    /// an honest key is the identity with negligible probability only.
+    fn check_final_keys(&self) -> Result<(), Error> {
+        let (v1, v2) = &self.final_vkey;
+        let (w1, w2) = &self.final_wkey;
+        if v1.is_zero() || v2.is_zero() || w1.is_zero() || w2.is_zero() {
+            return Err(Error::InvalidProof(
+                "final commitment key is the identity".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the proof is well formed with `parsing_check`, that all its
+    /// G1, G2 and target group elements are in the prime order subgroups and
+    /// that the final commitment keys are not the identity.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.parsing_check()?;
+        let mut g1 = self.z_c.iter().flat_map(|(l, r)| [l, r]).chain([
+            &self.final_a,
+            &self.final_c,
+            &self.final_wkey.0,
+            &self.final_wkey.1,
+        ]);
+        let g2 = [&self.final_b, &self.final_vkey.0, &self.final_vkey.1];
+        g1.try_for_each(Valid::check)
+            .and_then(|_| g2.into_iter().try_for_each(Valid::check))
+            .map_err(|_| {
+                Error::InvalidProof("point not in the prime order subgroup".to_string())
+            })?;
+        check_target_subgroup::<E>(
+            self.comms_ab
+                .iter()
+                .chain(&self.comms_c)
+                .flat_map(|(l, r)| [&l.0, &l.1, &r.0, &r.1])
+                .chain(self.z_ab.iter().flat_map(|(l, r)| [l, r])),
+        )?;
+        self.check_final_keys()
     }
 }
 
@@ -144,12 +380,12 @@ impl<E: Pairing> CanonicalSerialize for GipaProof<E> {
                     + self.z_ab[0].0.serialized_size(compress)
                     + self.z_ab[0].1.serialized_size(compress)
                     + self.z_c[0].0.serialized_size(compress)
-                    + self.z_c[0].1.serialized_size(compress)
-                    + self.final_a.serialized_size(compress)
-                    + self.final_b.serialized_size(compress)
-                    + self.final_c.serialized_size(compress)
-                    + self.final_vkey.serialized_size(compress)
-                    + self.final_wkey.serialized_size(compress))
+                    + self.z_c[0].1.serialized_size(compress))
+            + self.final_a.serialized_size(compress)
+            + self.final_b.serialized_size(compress)
+            + self.final_c.serialized_size(compress)
+            + self.final_vkey.serialized_size(compress)
+            + self.final_wkey.serialized_size(compress)
     }
     fn serialize_with_mode<W: Write>(
         &self,
@@ -207,7 +443,7 @@ where
     E: Pairing,
 {
     fn check(&self) -> Result<(), SerializationError> {
-        Ok(())
+        self.validate().map_err(|_| SerializationError::InvalidData)
     }
 }
 
@@ -222,9 +458,8 @@ where
     ) -> Result<Self, SerializationError> {
         let result = {
             let nproofs = u32::deserialize_compressed(&mut source)?;
-            if nproofs < 2 {
-                return Err(SerializationError::InvalidData);
-            }
+            // bound the number of proofs before allocating anything from it
+            Self::check_nproofs(nproofs).map_err(|_| SerializationError::InvalidData)?;
 
             let log_proofs = Self::log_proofs(nproofs as usize);
 
@@ -234,12 +469,12 @@ where
                     Output::<<E as Pairing>::TargetField>::deserialize_with_mode(
                         &mut source,
                         compress,
-                        validate,
+                        Validate::No,
                     )?,
                     Output::<<E as Pairing>::TargetField>::deserialize_with_mode(
                         &mut source,
                         compress,
-                        validate,
+                        Validate::No,
                     )?,
                 ));
             }
@@ -250,12 +485,12 @@ where
                     Output::<<E as Pairing>::TargetField>::deserialize_with_mode(
                         &mut source,
                         compress,
-                        validate,
+                        Validate::No,
                     )?,
                     Output::<<E as Pairing>::TargetField>::deserialize_with_mode(
                         &mut source,
                         compress,
-                        validate,
+                        Validate::No,
                     )?,
                 ));
             }
@@ -266,12 +501,12 @@ where
                     <E as Pairing>::TargetField::deserialize_with_mode(
                         &mut source,
                         compress,
-                        validate,
+                        Validate::No,
                     )?,
                     <E as Pairing>::TargetField::deserialize_with_mode(
                         &mut source,
                         compress,
-                        validate,
+                        Validate::No,
                     )?,
                 ));
             }
@@ -279,37 +514,24 @@ where
             let mut z_c = Vec::with_capacity(log_proofs);
             for _ in 0..log_proofs {
                 z_c.push((
-                    E::G1Affine::deserialize_with_mode(&mut source, compress, validate)?,
-                    E::G1Affine::deserialize_with_mode(&mut source, compress, validate)?,
+                    E::G1Affine::deserialize_with_mode(&mut source, compress, Validate::No)?,
+                    E::G1Affine::deserialize_with_mode(&mut source, compress, Validate::No)?,
                 ));
             }
 
-            let final_a = E::G1Affine::deserialize_with_mode(&mut source, compress, validate)?;
-            let final_b = E::G2Affine::deserialize_with_mode(&mut source, compress, validate)?;
-            let final_c = E::G1Affine::deserialize_with_mode(&mut source, compress, validate)?;
+            let final_a = E::G1Affine::deserialize_with_mode(&mut source, compress, Validate::No)?;
+            let final_b = E::G2Affine::deserialize_with_mode(&mut source, compress, Validate::No)?;
+            let final_c = E::G1Affine::deserialize_with_mode(&mut source, compress, Validate::No)?;
 
             let final_vkey = (
-                E::G2Affine::deserialize_with_mode(&mut source, compress, validate)?,
-                E::G2Affine::deserialize_with_mode(&mut source, compress, validate)?,
+                E::G2Affine::deserialize_with_mode(&mut source, compress, Validate::No)?,
+                E::G2Affine::deserialize_with_mode(&mut source, compress, Validate::No)?,
             );
             let final_wkey = (
-                E::G1Affine::deserialize_with_mode(&mut source, compress, validate)?,
-                E::G1Affine::deserialize_with_mode(&mut source, compress, validate)?,
+                E::G1Affine::deserialize_with_mode(&mut source, compress, Validate::No)?,
+                E::G1Affine::deserialize_with_mode(&mut source, compress, Validate::No)?,
             );
 
-            if let Validate::Yes = validate {
-                nproofs.check()?;
-                comms_ab.check()?;
-                comms_c.check()?;
-                z_ab.check()?;
-                z_c.check()?;
-                final_a.check()?;
-                final_b.check()?;
-                final_c.check()?;
-                final_vkey.check()?;
-                final_wkey.check()?;
-            }
-
             GipaProof {
                 nproofs,
                 comms_ab,
@@ -323,6 +545,9 @@ where
                 final_wkey,
             }
         };
+        if let Validate::Yes = validate {
+            result.check()?;
+        }
         Ok(result)
     }
 }
@@ -344,6 +569,164 @@ impl<E: Pairing> PartialEq for TippMippProof<E> {
     }
 }
 
+/// Left and right commitments sent at each step of the GIPA loop.
+pub type OutputPair<E> = (
+    commitment::Output<<E as Pairing>::TargetField>,
+    commitment::Output<<E as Pairing>::TargetField>,
+);
+
+/// MippExtension contains the elements derived in the GIPA loop for an extra
+/// vector of G1 elements proven with MIPP alongside C. It shares the challenges
+/// of the loop, hence the KZG opening of v, with the TIPP and MIPP proof.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Debug, Clone)]
+pub struct MippExtension<E: Pairing> {
+    pub comms: Vec<OutputPair<E>>,
+    pub z: Vec<(E::G1Affine, E::G1Affine)>,
+    pub final_x: E::G1Affine,
+}
+
+impl<E: Pairing> PartialEq for MippExtension<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.comms == other.comms && self.z == other.z && self.final_x == other.final_x
+    }
+}
+
+/// CommittedAggregateProof aggregates Groth16 proofs carrying a Pedersen
+/// commitment $D$ and its proof of knowledge, as produced by gnark or
+/// LegoGroth16 style provers. The commitments are given to the verifier, which
+/// derives from each of them a public input of its proof, and the proofs of
+/// knowledge are aggregated with an extra MIPP proof alongside C.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Debug, Clone)]
+pub struct CommittedAggregateProof<E: Pairing> {
+    pub proof: AggregateProof<E>,
+    /// commitment $D$ of each proof, in the order of the proofs
+    pub commitments: Vec<E::G1Affine>,
+    /// commitment to the proofs of knowledge using the single commitment scheme
+    pub com_pok: commitment::Output<<E as Pairing>::TargetField>,
+    /// $PoK^r$ is used in the aggregated proof of knowledge equation
+    pub agg_pok: E::G1Affine,
+    pub mipp_pok: MippExtension<E>,
+}
+
+impl<E: Pairing> PartialEq for CommittedAggregateProof<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof == other.proof
+            && self.commitments == other.commitments
+            && self.com_pok == other.com_pok
+            && self.agg_pok == other.agg_pok
+            && self.mipp_pok == other.mipp_pok
+    }
+}
+
+impl<E: Pairing> CommittedAggregateProof<E> {
+    /// Performs the checks of `AggregateProof::parsing_check` and checks that
+    /// there is one commitment per proof and that the MIPP extension has as
+    /// many elements as the GIPA proof.
+    pub fn parsing_check(&self) -> Result<(), Error> {
+        self.proof.parsing_check()?;
+        if self.commitments.len() != self.proof.tmipp.gipa.nproofs as usize {
+            return Err(Error::InvalidProof(
+                "commitments len != number of proofs".to_string(),
+            ));
+        }
+        let ref_len = self.proof.tmipp.gipa.comms_c.len();
+        if self.mipp_pok.comms.len() != ref_len || self.mipp_pok.z.len() != ref_len {
+            return Err(Error::InvalidProof(
+                "Proof extension vectors unequal sizes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Performs the checks of `AggregateProof::validate` and of
+    /// `parsing_check`, and checks that the commitments and all the elements
+    /// of the MIPP extension are in the prime order subgroups.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.parsing_check()?;
+        self.proof.validate()?;
+        let mut g1 = self
+            .mipp_pok
+            .z
+            .iter()
+            .flat_map(|(l, r)| [l, r])
+            .chain(&self.commitments)
+            .chain([&self.mipp_pok.final_x, &self.agg_pok]);
+        g1.try_for_each(Valid::check).map_err(|_| {
+            Error::InvalidProof("point not in the prime order subgroup".to_string())
+        })?;
+        check_target_subgroup::<E>(
+            self.mipp_pok
+                .comms
+                .iter()
+                .flat_map(|(l, r)| [l, r])
+                .chain([&self.com_pok])
+                .flat_map(|c| [&c.0, &c.1]),
+        )
+    }
+
+    pub fn write<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        self.serialize_compressed(&mut out)
+            .map_err(Error::Serialization)
+    }
+
+    /// Reads the proof and checks it with `validate`.
+    pub fn read<R: Read>(source: R) -> Result<Self, Error> {
+        let proof = Self::deserialize_with_mode(source, Compress::Yes, Validate::No)?;
+        proof.validate()?;
+        Ok(proof)
+    }
+}
+
+/// InputsOpening proves the evaluations at $r$ of the polynomials whose
+/// coefficients are the columns of public inputs, i.e. the same input of all
+/// proofs. The polynomials are committed with KZG and opened in a batch.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Debug, Clone, PartialEq)]
+pub struct InputsOpening<E: Pairing> {
+    /// evaluation at $r$ of the polynomial of each column, in the order of
+    /// the inputs
+    pub evaluations: Vec<E::ScalarField>,
+    /// KZG opening at $r$ of the random linear combination of the columns
+    pub opening: E::G1Affine,
+}
+
+/// SuccinctAggregateProof is an aggregate proof verifiable from commitments to
+/// the columns of public inputs instead of the public inputs themselves, with
+/// a cost logarithmic in the number of proofs.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Debug, Clone, PartialEq)]
+pub struct SuccinctAggregateProof<E: Pairing> {
+    pub proof: AggregateProof<E>,
+    pub inputs: InputsOpening<E>,
+}
+
+impl<E: Pairing> SuccinctAggregateProof<E> {
+    /// Performs the checks of `AggregateProof::parsing_check`.
+    pub fn parsing_check(&self) -> Result<(), Error> {
+        self.proof.parsing_check()
+    }
+
+    /// Performs the checks of `AggregateProof::validate` and checks that the
+    /// KZG opening of the public inputs is in the prime order subgroup.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.proof.validate()?;
+        self.inputs
+            .opening
+            .check()
+            .map_err(|_| Error::InvalidProof("point not in the prime order subgroup".to_string()))
+    }
+
+    pub fn write<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        self.serialize_compressed(&mut out)
+            .map_err(Error::Serialization)
+    }
+
+    /// Reads the proof and checks it with `validate`.
+    pub fn read<R: Read>(source: R) -> Result<Self, Error> {
+        let proof = Self::deserialize_with_mode(source, Compress::Yes, Validate::No)?;
+        proof.validate()?;
+        Ok(proof)
+    }
+}
+
 /// KZGOpening represents the KZG opening of a commitment key (which is a tuple
 /// given commitment keys are a tuple).
 #[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
@@ -361,6 +744,7 @@ mod tests {
 
     use crate::commitment::Output as O;
     use ark_bn254::{Bn254, G1Affine, G2Affine};
+    use ark_std::{UniformRand, Zero};
 
     fn fake_proof() -> AggregateProof<Bn254> {
         // create pairing, as pairing results can be compressed
@@ -405,6 +789,57 @@ mod tests {
         assert_eq!(proof, out);
     }
 
+    #[test]
+    fn test_proof_torus_io() {
+        let proof = fake_proof();
+        let mut buffer = Vec::new();
+        proof.write_torus(&mut buffer).unwrap();
+        let estimator = crate::CostEstimator::<Bn254>::new(4, 0).unwrap();
+        assert_eq!(buffer.len(), estimator.torus_proof_size());
+        let out = AggregateProof::<Bn254>::read_torus(&buffer[..]).unwrap();
+        assert_eq!(proof, out);
+        assert!(AggregateProof::<Bn254>::read_torus(&buffer[1..]).is_err());
+    }
+
+    #[test]
+    fn test_proof_validate() {
+        let mut rng = ark_std::test_rng();
+        let proof = fake_proof();
+        proof.validate().expect("proof should be valid");
+
+        // the number of proofs is bounded before anything is allocated
+        let mut buffer = Vec::new();
+        proof.write(&mut buffer).unwrap();
+        let offset = 5 * proof.ip_ab.compressed_size() + proof.agg_c.compressed_size();
+        for nproofs in [u32::MAX, 1 << 31, 6] {
+            let mut bad = buffer.clone();
+            bad[offset..offset + 4].copy_from_slice(&nproofs.to_le_bytes());
+            assert!(AggregateProof::<Bn254>::read(&bad[..]).is_err());
+            assert!(AggregateProof::<Bn254>::deserialize_uncompressed_unchecked(&bad[..]).is_err());
+        }
+
+        // elements of the target field outside of the prime order subgroup
+        let not_gt = <Bn254 as Pairing>::TargetField::rand(&mut rng);
+        let mut bad = fake_proof();
+        bad.tmipp.gipa.z_ab[1].0 = not_gt;
+        assert!(bad.validate().is_err());
+        let mut buffer = Vec::new();
+        bad.write(&mut buffer).unwrap();
+        assert!(AggregateProof::<Bn254>::read_validated(&buffer[..]).is_err());
+        assert!(TippMippProof::<Bn254>::deserialize_compressed(&buffer[offset..]).is_err());
+        let mut bad = fake_proof();
+        bad.ip_ab = <Bn254 as Pairing>::TargetField::zero();
+        assert!(bad.validate().is_err());
+
+        // identity final commitment keys
+        let mut bad = fake_proof();
+        bad.tmipp.gipa.final_wkey.1 = G1Affine::zero();
+        bad.validate().expect_err("identity key should be rejected");
+        let mut buffer = Vec::new();
+        bad.write(&mut buffer).unwrap();
+        assert!(AggregateProof::<Bn254>::read(&buffer[..]).is_err());
+    }
+
     #[test]
     fn test_proof_check() {
         let p = G1Affine::generator();
@@ -1,25 +1,53 @@
 use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_ff::{Field, One};
-use ark_groth16::Proof;
+use ark_groth16::{Proof, VerifyingKey};
 use ark_poly::polynomial::{univariate::DensePolynomial, DenseUVPolynomial};
-use ark_std::Zero;
+use ark_std::{cfg_iter, cfg_iter_mut, string::ToString, vec::Vec, Zero};
 
+use ark_std::ops::{AddAssign, MulAssign, Neg};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::ops::{AddAssign, MulAssign, Neg};
 
 use super::{
     commitment,
-    commitment::{VKey, WKey},
+    commitment::{Output, VKey, WKey},
     compress,
     errors::Error,
-    ip,
-    proof::{AggregateProof, GipaProof, KZGOpening, TippMippProof},
+    ip, join,
+    proof::{
+        AggregateProof, GipaProof, InputsOpening, KZGOpening, MippExtension, OutputPair,
+        TippMippProof,
+    },
     srs::ProverSRS,
+    statement::bind_statement,
     structured_scalar_power,
+    succinct::prove_inputs_opening,
     transcript::Transcript,
 };
 
 /// Aggregate `n` zkSnark proofs, where `n` must be a power of two.
+/// The whole statement, namely the Groth16 verifying key, the verifier SRS,
+/// the number of proofs and ALL public inputs of ALL proofs, is bound in the
+/// transcript before aggregating. The verifier must call
+/// `verify_aggregate_proof` with the same transcript label and statement.
+pub fn aggregate_proofs<E: Pairing + ark_std::fmt::Debug, T: Transcript>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &[Vec<E::ScalarField>],
+    proofs: &[Proof<E>],
+    transcript: &mut T,
+) -> Result<AggregateProof<E>, Error> {
+    if public_inputs.len() != proofs.len() {
+        return Err(Error::InvalidProof(
+            "public inputs len != number of proofs".to_string(),
+        ));
+    }
+    bind_statement(transcript, vk, &srs.to_verifier_srs(), public_inputs)?;
+    aggregate_proofs_unchecked(srs, transcript, proofs)
+}
+
+/// Aggregate `n` zkSnark proofs, where `n` must be a power of two, without
+/// binding any statement in the transcript.
 /// WARNING: transcript_include represents everything that should be included in
 /// the transcript from outside the boundary of this function. This is especially
 /// relevant for ALL public inputs of ALL individual proofs. In the regular case,
@@ -30,11 +58,45 @@ use super::{
 /// number of proofs and public inputs (+100ms in our case). In the case of Filecoin, the only
 /// non-fixed part of the public inputs are the challenges derived from a seed. Even though this
 /// seed comes from a random beeacon, we are hashing this as a safety precaution.
-pub fn aggregate_proofs<E: Pairing + std::fmt::Debug, T: Transcript>(
+pub fn aggregate_proofs_unchecked<E: Pairing + ark_std::fmt::Debug, T: Transcript>(
     srs: &ProverSRS<E>,
     transcript: &mut T,
     proofs: &[Proof<E>],
 ) -> Result<AggregateProof<E>, Error> {
+    let (proof, _, _) = aggregate_proofs_with_extensions(srs, transcript, proofs, &[], None)?;
+    Ok(proof)
+}
+
+/// Output of the MIPP proof of an extra vector of G1 elements: the commitment
+/// to the vector, the vector aggregated with the powers of $r$ and the GIPA
+/// elements.
+pub(crate) type ExtensionOutput<E> = (
+    Output<<E as Pairing>::TargetField>,
+    <E as Pairing>::G1Affine,
+    MippExtension<E>,
+);
+
+/// Aggregate proof along with the outputs of its extensions and the opening of
+/// the columns of public inputs, if any.
+pub(crate) type ExtendedProof<E> = (
+    AggregateProof<E>,
+    Vec<ExtensionOutput<E>>,
+    Option<InputsOpening<E>>,
+);
+
+/// Aggregates the proofs like `aggregate_proofs_unchecked` and proves, in the
+/// same GIPA loop as C, a MIPP relation for each of the extra vectors of G1
+/// elements given. The commitments to the extra vectors are bound in the
+/// transcript before deriving the randomness $r$.
+/// If columns of public inputs are given, their evaluations at $r$ are proven
+/// right after $r$ is derived.
+pub(crate) fn aggregate_proofs_with_extensions<E: Pairing, T: Transcript>(
+    srs: &ProverSRS<E>,
+    transcript: &mut T,
+    proofs: &[Proof<E>],
+    extras: &[Vec<E::G1Affine>],
+    columns: Option<&[Vec<E::ScalarField>]>,
+) -> Result<ExtendedProof<E>, Error> {
     if proofs.len() < 2 {
         return Err(Error::InvalidProof("invalid proof size < 2".to_string()));
     }
@@ -65,24 +127,36 @@ pub fn aggregate_proofs<E: Pairing + std::fmt::Debug, T: Transcript>(
         let com_ab = commitment::pair::<E>(&srs.vkey, &srs.wkey, refa, refb),
         let com_c = commitment::single_g1::<E>(&srs.vkey, refc)
     };
+    if extras.iter().any(|x| x.len() != proofs.len()) {
+        return Err(Error::InvalidProof(
+            "extension len != number of proofs".to_string(),
+        ));
+    }
+    let com_extras = cfg_iter!(extras)
+        .map(|x| commitment::single_g1::<E>(&srs.vkey, x))
+        .collect::<Result<Vec<_>, Error>>()?;
 
     // Derive a random scalar to perform a linear combination of proofs
     transcript.append(b"AB-commitment", &com_ab);
     transcript.append(b"C-commitment", &com_c);
+    for com_x in &com_extras {
+        transcript.append(b"extension-commitment", com_x);
+    }
     let r = transcript.challenge_scalar::<E::ScalarField>(b"r-random-fiatshamir");
+    let inputs_opening = columns
+        .map(|columns| prove_inputs_opening(srs, transcript, columns, &r))
+        .transpose()?;
 
     // 1,r, r^2, r^3, r^4 ...
     let r_vec: Vec<E::ScalarField> = structured_scalar_power(proofs.len(), &r);
     // 1,r^-1, r^-2, r^-3
-    let r_inv = r_vec
-        .par_iter()
+    let r_inv = cfg_iter!(r_vec)
         .map(|ri| ri.inverse().unwrap())
         .collect::<Vec<_>>();
 
     // B^{r}
-    let b_r = b
-        .par_iter()
-        .zip(r_vec.par_iter())
+    let b_r = cfg_iter!(b)
+        .zip(cfg_iter!(r_vec))
         .map(|(bi, ri)| mul!(bi.into_group(), ri.clone()).into_affine())
         .collect::<Vec<_>>();
 
@@ -95,33 +169,59 @@ pub fn aggregate_proofs<E: Pairing + std::fmt::Debug, T: Transcript>(
         let agg_c = ip::multiexponentiation::<E::G1Affine>(&refc, &refr_vec)
     };
     let agg_c = agg_c.into_affine();
+    // compute X^r for each extra vector
+    let agg_extras = cfg_iter!(extras)
+        .map(|x| ip::multiexponentiation::<E::G1Affine>(x, &r_vec).map(|p| p.into_affine()))
+        .collect::<Result<Vec<_>, Error>>()?;
     // w^{r^{-1}}
     let wkey_r_inv = srs.wkey.scale(&r_inv)?;
 
     // we prove tipp and mipp using the same recursive loop
-    let proof = prove_tipp_mipp(
-        &srs,
-        transcript,
-        &a,
-        &b_r,
-        &c,
-        &wkey_r_inv,
-        &r_vec,
-        &ip_ab.0,
-        &agg_c,
-    )?;
+    let inputs = TippMippInputs {
+        a: &a,
+        b: &b_r,
+        c: &c,
+        extras,
+        ip_ab: &ip_ab.0,
+        agg_c: &agg_c,
+        agg_extras: &agg_extras,
+    };
+    let (proof, mipp_extras) = prove_tipp_mipp(srs, transcript, &inputs, &wkey_r_inv, &r_vec)?;
     debug_assert!({
         let computed_com_ab = commitment::pair::<E>(&srs.vkey, &wkey_r_inv, &a, &b_r).unwrap();
         com_ab == computed_com_ab
     });
 
-    Ok(AggregateProof {
-        com_ab,
-        com_c,
-        ip_ab: ip_ab.0,
-        agg_c,
-        tmipp: proof,
-    })
+    let extensions = com_extras
+        .into_iter()
+        .zip(agg_extras)
+        .zip(mipp_extras)
+        .map(|((com_x, agg_x), mipp_x)| (com_x, agg_x, mipp_x))
+        .collect();
+    Ok((
+        AggregateProof {
+            com_ab,
+            com_c,
+            ip_ab: ip_ab.0,
+            agg_c,
+            tmipp: proof,
+        },
+        extensions,
+        inputs_opening,
+    ))
+}
+
+/// The vectors proven with TIPP and MIPP along with the values they are
+/// proven to aggregate to: A and B with their inner pairing product, C and
+/// each extra vector with their combination with the powers of r.
+struct TippMippInputs<'a, E: Pairing> {
+    a: &'a [E::G1Affine],
+    b: &'a [E::G2Affine],
+    c: &'a [E::G1Affine],
+    extras: &'a [Vec<E::G1Affine>],
+    ip_ab: &'a <E as Pairing>::TargetField,
+    agg_c: &'a E::G1Affine,
+    agg_extras: &'a [E::G1Affine],
 }
 
 /// Proves a TIPP relation between A and B as well as a MIPP relation with C and
@@ -133,19 +233,37 @@ pub fn aggregate_proofs<E: Pairing + std::fmt::Debug, T: Transcript>(
 fn prove_tipp_mipp<E: Pairing, T: Transcript>(
     srs: &ProverSRS<E>,
     transcript: &mut T,
-    a: &[E::G1Affine],
-    b: &[E::G2Affine],
-    c: &[E::G1Affine],
+    inputs: &TippMippInputs<E>,
     wkey: &WKey<E>, // scaled key w^r^-1
     r_vec: &[E::ScalarField],
-    ip_ab: &<E as Pairing>::TargetField,
-    agg_c: &E::G1Affine,
-) -> Result<TippMippProof<E>, Error> {
+) -> Result<(TippMippProof<E>, Vec<MippExtension<E>>), Error> {
     let r_shift = r_vec[1].clone();
     // Run GIPA
-    let (proof, mut challenges, mut challenges_inv) =
-        gipa_tipp_mipp(transcript, a, b, c, &srs.vkey, &wkey, r_vec, ip_ab, agg_c)?;
+    let (proof, mipp_extras, challenges, challenges_inv) =
+        gipa_tipp_mipp(transcript, inputs, &srs.vkey, wkey, r_vec)?;
+    let proof = prove_commitment_keys(
+        srs,
+        transcript,
+        proof,
+        &mipp_extras,
+        challenges,
+        challenges_inv,
+        &r_shift,
+    )?;
+    Ok((proof, mipp_extras))
+}
 
+/// Proves the final commitment keys of the GIPA proof are wellformed with a KZG
+/// opening for v and one for w, and returns the complete TIPP/MIPP proof.
+pub(crate) fn prove_commitment_keys<E: Pairing, T: Transcript>(
+    srs: &ProverSRS<E>,
+    transcript: &mut T,
+    proof: GipaProof<E>,
+    mipp_extras: &[MippExtension<E>],
+    mut challenges: Vec<E::ScalarField>,
+    mut challenges_inv: Vec<E::ScalarField>,
+    r_shift: &E::ScalarField,
+) -> Result<TippMippProof<E>, Error> {
     // Prove final commitment keys are wellformed
     // we reverse the transcript so the polynomial in kzg opening is constructed
     // correctly - the formula indicates x_{l-j}. Also for deriving KZG
@@ -155,12 +273,7 @@ fn prove_tipp_mipp<E: Pairing, T: Transcript>(
     let r_inverse = r_shift.inverse().unwrap();
 
     // KZG challenge point
-    transcript.append(b"kzg-challenge", &challenges[0]);
-    transcript.append(b"vkey0", &proof.final_vkey.0);
-    transcript.append(b"vkey1", &proof.final_vkey.1);
-    transcript.append(b"wkey0", &proof.final_wkey.0);
-    transcript.append(b"wkey1", &proof.final_wkey.1);
-    let z = transcript.challenge_scalar::<E::ScalarField>(b"z-challenge");
+    let z = kzg_challenge(transcript, &proof, mipp_extras, &challenges[0]);
     // Complete KZG proofs
     par! {
         let vkey_opening = prove_commitment_v(
@@ -185,43 +298,180 @@ fn prove_tipp_mipp<E: Pairing, T: Transcript>(
     })
 }
 
+/// Derives the KZG challenge point from the last challenge of GIPA and the
+/// final commitment keys. When the transcript binds every round of GIPA, see
+/// `Transcript::bind_gipa_rounds`, the last challenge is first linked to the
+/// elements of the last round and the final values, and the final values are
+/// also bound in the KZG challenge.
+pub(crate) fn kzg_challenge<'a, E: Pairing>(
+    transcript: &mut impl Transcript,
+    gipa: &GipaProof<E>,
+    mipp_extras: impl IntoIterator<Item = &'a MippExtension<E>>,
+    last_challenge: &E::ScalarField,
+) -> E::ScalarField {
+    let link = match transcript.bind_gipa_rounds() {
+        false => *last_challenge,
+        true => {
+            let (zab_l, zab_r) = gipa.z_ab.last().unwrap();
+            let (zc_l, zc_r) = gipa.z_c.last().unwrap();
+            let (tab_l, tab_r) = gipa.comms_ab.last().unwrap();
+            let (tuc_l, tuc_r) = gipa.comms_c.last().unwrap();
+            transcript.append(b"last-challenge", last_challenge);
+            transcript.append_point(b"final-a", &gipa.final_a);
+            transcript.append_point(b"final-b", &gipa.final_b);
+            transcript.append_point(b"final-c", &gipa.final_c);
+            transcript.append(b"zab_l", zab_l);
+            transcript.append(b"zab_r", zab_r);
+            transcript.append_point(b"zc_l", zc_l);
+            transcript.append_point(b"zc_r", zc_r);
+            transcript.append(b"tab_l", tab_l);
+            transcript.append(b"tab_r", tab_r);
+            transcript.append(b"tuc_l", tuc_l);
+            transcript.append(b"tuc_r", tuc_r);
+            for x in mipp_extras {
+                let (tux_l, tux_r) = x.comms.last().unwrap();
+                let (zx_l, zx_r) = x.z.last().unwrap();
+                transcript.append_point(b"zx_l", zx_l);
+                transcript.append_point(b"zx_r", zx_r);
+                transcript.append(b"tux_l", tux_l);
+                transcript.append(b"tux_r", tux_r);
+                transcript.append_point(b"final-x", &x.final_x);
+            }
+            transcript.challenge_scalar::<E::ScalarField>(b"link-challenge")
+        }
+    };
+    transcript.append(b"kzg-challenge", &link);
+    transcript.append_point(b"vkey0", &gipa.final_vkey.0);
+    transcript.append_point(b"vkey1", &gipa.final_vkey.1);
+    transcript.append_point(b"wkey0", &gipa.final_wkey.0);
+    transcript.append_point(b"wkey1", &gipa.final_wkey.1);
+    if transcript.bind_gipa_rounds() {
+        transcript.append_point(b"final-a", &gipa.final_a);
+        transcript.append_point(b"final-b", &gipa.final_b);
+        transcript.append_point(b"final-c", &gipa.final_c);
+    }
+    transcript.challenge_scalar::<E::ScalarField>(b"z-challenge")
+}
+
+/// Output of the GIPA loop: the proof, the MIPP elements of the extra vectors,
+/// the challenges and their inverses.
+pub(crate) type GipaOutput<E> = (
+    GipaProof<E>,
+    Vec<MippExtension<E>>,
+    Vec<<E as Pairing>::ScalarField>,
+    Vec<<E as Pairing>::ScalarField>,
+);
+
+/// Values gathered at each round of GIPA for the MIPP of an extra vector.
+pub(crate) type MippRounds<E> = (
+    Vec<OutputPair<E>>,
+    Vec<(<E as Pairing>::G1Affine, <E as Pairing>::G1Affine)>,
+);
+
+/// GipaState holds the values of the GIPA recursion between two rounds: the
+/// vectors and commitment keys rescaled so far, and the values gathered for
+/// the proof during the previous rounds.
+pub(crate) struct GipaState<E: Pairing> {
+    /// number of proofs aggregated, i.e. length of the vectors before the
+    /// first round
+    pub(crate) nproofs: u32,
+    pub(crate) m_a: Vec<E::G1Affine>,
+    pub(crate) m_b: Vec<E::G2Affine>,
+    pub(crate) m_c: Vec<E::G1Affine>,
+    pub(crate) m_r: Vec<E::ScalarField>,
+    pub(crate) m_extras: Vec<Vec<E::G1Affine>>,
+    pub(crate) vkey: VKey<E>,
+    pub(crate) wkey: WKey<E>,
+    pub(crate) comms_ab: Vec<OutputPair<E>>,
+    pub(crate) comms_c: Vec<OutputPair<E>>,
+    pub(crate) z_ab: Vec<(E::TargetField, E::TargetField)>,
+    pub(crate) z_c: Vec<(E::G1Affine, E::G1Affine)>,
+    pub(crate) mipp_extras: Vec<MippRounds<E>>,
+    pub(crate) challenges: Vec<E::ScalarField>,
+    pub(crate) challenges_inv: Vec<E::ScalarField>,
+}
+
 /// gipa_tipp_mipp peforms the recursion of the GIPA protocol for TIPP and MIPP.
 /// It returns a proof containing all intermdiate committed values, as well as
 /// the challenges generated necessary to do the polynomial commitment proof
-/// later in TIPP.
+/// later in TIPP. The extra vectors are proven with MIPP exactly like C.
 fn gipa_tipp_mipp<E: Pairing>(
     transcript: &mut impl Transcript,
-    a: &[E::G1Affine],
-    b: &[E::G2Affine],
-    c: &[E::G1Affine],
+    inputs: &TippMippInputs<E>,
     vkey: &VKey<E>,
     wkey: &WKey<E>, // scaled key w^r^-1
     r: &[E::ScalarField],
-    ip_ab: &<E as Pairing>::TargetField,
-    agg_c: &E::G1Affine,
-) -> Result<(GipaProof<E>, Vec<E::ScalarField>, Vec<E::ScalarField>), Error> {
-    // the values of vectors A and B rescaled at each step of the loop
-    let (mut m_a, mut m_b) = (a.to_vec(), b.to_vec());
-    // the values of vectors C and r rescaled at each step of the loop
-    let (mut m_c, mut m_r) = (c.to_vec(), r.to_vec());
-    // the values of the commitment keys rescaled at each step of the loop
-    let (mut vkey, mut wkey) = (vkey.clone(), wkey.clone());
-
-    // storing the values for including in the proof
-    let mut comms_ab = Vec::new();
-    let mut comms_c = Vec::new();
-    let mut z_ab = Vec::new();
-    let mut z_c = Vec::new();
-    let mut challenges: Vec<E::ScalarField> = Vec::new();
-    let mut challenges_inv: Vec<E::ScalarField> = Vec::new();
+) -> Result<GipaOutput<E>, Error> {
+    let TippMippInputs {
+        a,
+        b,
+        c,
+        extras,
+        ip_ab,
+        agg_c,
+        agg_extras,
+    } = *inputs;
+    let state = GipaState {
+        nproofs: a.len() as u32, // TODO: ensure u32
+        // the values of vectors A and B rescaled at each step of the loop
+        m_a: a.to_vec(),
+        m_b: b.to_vec(),
+        // the values of vectors C and r rescaled at each step of the loop
+        m_c: c.to_vec(),
+        m_r: r.to_vec(),
+        // the values of the extra vectors rescaled at each step of the loop
+        m_extras: extras.to_vec(),
+        // the values of the commitment keys rescaled at each step of the loop
+        vkey: vkey.clone(),
+        wkey: wkey.clone(),
+        // storing the values for including in the proof
+        comms_ab: Vec::new(),
+        comms_c: Vec::new(),
+        z_ab: Vec::new(),
+        z_c: Vec::new(),
+        mipp_extras: extras.iter().map(|_| (Vec::new(), Vec::new())).collect(),
+        challenges: Vec::new(),
+        challenges_inv: Vec::new(),
+    };
 
     transcript.append(b"inner-product-ab", ip_ab);
-    transcript.append(b"comm-c", agg_c);
-    let mut c_inv: E::ScalarField =
-        transcript.challenge_scalar::<E::ScalarField>(b"first-challenge");
-    let mut c = c_inv.inverse().unwrap();
+    transcript.append_point(b"comm-c", agg_c);
+    for agg_x in agg_extras {
+        transcript.append_point(b"comm-extension", agg_x);
+    }
+    let c_inv: E::ScalarField = transcript.challenge_scalar::<E::ScalarField>(b"first-challenge");
+
+    gipa_rounds(transcript, state, c_inv)
+}
 
-    let mut i = 0;
+/// gipa_rounds runs the remaining rounds of the GIPA recursion from the given
+/// state until the vectors are of size one. If no round has been performed
+/// yet, `first_c_inv` is the challenge of the first round, derived outside of
+/// the loop, unless the transcript binds every round: the challenge of the
+/// first round is then derived from it and the elements of the round, like
+/// the challenge of each other round from the one of the previous round.
+pub(crate) fn gipa_rounds<E: Pairing>(
+    transcript: &mut impl Transcript,
+    state: GipaState<E>,
+    first_c_inv: E::ScalarField,
+) -> Result<GipaOutput<E>, Error> {
+    let GipaState {
+        nproofs,
+        mut m_a,
+        mut m_b,
+        mut m_c,
+        mut m_r,
+        mut m_extras,
+        mut vkey,
+        mut wkey,
+        mut comms_ab,
+        mut comms_c,
+        mut z_ab,
+        mut z_c,
+        mut mipp_extras,
+        mut challenges,
+        mut challenges_inv,
+    } = state;
 
     while m_a.len() > 1 {
         // recursive step
@@ -267,28 +517,48 @@ fn gipa_tipp_mipp<E: Pairing>(
             // u_r = c[:n'] * v[n':]
             let tuc_r = commitment::single_g1::<E>(&rvk_right, rc_left)
         };
+        let (zc_l, zc_r) = (zc_l.into_affine(), zc_r.into_affine());
+        // MIPP part for the extra vectors, computed as for c
+        let round_extras = cfg_iter!(m_extras)
+            .map(|x| {
+                let (x_left, x_right) = x.split_at(split);
+                Ok((
+                    commitment::single_g1::<E>(rvk_left, x_right)?,
+                    commitment::single_g1::<E>(rvk_right, x_left)?,
+                    ip::multiexponentiation::<E::G1Affine>(x_right, rr_left)?.into_affine(),
+                    ip::multiexponentiation::<E::G1Affine>(x_left, rr_right)?.into_affine(),
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
 
         // Fiat-Shamir challenge
         // combine both TIPP and MIPP transcript
-        if i == 0 {
-            // already generated c_inv and c outside of the loop
-        } else {
-            transcript.append(b"c_inv", &c_inv);
-            transcript.append(b"zab_l", &zab_l);
-            transcript.append(b"zab_r", &zab_r);
-            transcript.append(b"zc_l", &zc_l);
-            transcript.append(b"zc_r", &zc_r);
-            transcript.append(b"tab_l", &tab_l);
-            transcript.append(b"tab_r", &tab_r);
-            transcript.append(b"tuc_l", &tuc_l);
-            transcript.append(b"tuc_r", &tuc_r);
-            c_inv = transcript.challenge_scalar::<E::ScalarField>(b"challenge_i");
-
-            // Optimization for multiexponentiation to rescale G2 elements with
-            // 128-bit challenge Swap 'c' and 'c_inv' since can't control bit size
-            // of c_inv
-            c = c_inv.inverse().unwrap();
-        }
+        let c_inv = match challenges_inv.last() {
+            // first round: c_inv was already generated outside of the loop
+            None if !transcript.bind_gipa_rounds() => first_c_inv,
+            last => {
+                transcript.append(b"c_inv", last.unwrap_or(&first_c_inv));
+                transcript.append(b"zab_l", &zab_l);
+                transcript.append(b"zab_r", &zab_r);
+                transcript.append_point(b"zc_l", &zc_l);
+                transcript.append_point(b"zc_r", &zc_r);
+                transcript.append(b"tab_l", &tab_l);
+                transcript.append(b"tab_r", &tab_r);
+                transcript.append(b"tuc_l", &tuc_l);
+                transcript.append(b"tuc_r", &tuc_r);
+                for (tux_l, tux_r, zx_l, zx_r) in &round_extras {
+                    transcript.append_point(b"zx_l", zx_l);
+                    transcript.append_point(b"zx_r", zx_r);
+                    transcript.append(b"tux_l", tux_l);
+                    transcript.append(b"tux_r", tux_r);
+                }
+                transcript.challenge_scalar::<E::ScalarField>(b"challenge_i")
+            }
+        };
+        // Optimization for multiexponentiation to rescale G2 elements with
+        // 128-bit challenge Swap 'c' and 'c_inv' since can't control bit size
+        // of c_inv
+        let c = c_inv.inverse().unwrap();
 
         // Set up values for next step of recursion
         // A[:n'] + A[n':] ^ x
@@ -298,9 +568,11 @@ fn gipa_tipp_mipp<E: Pairing>(
 
         // c[:n'] + c[n':]^x
         compress(&mut m_c, split, &c);
-        r_left
-            .par_iter_mut()
-            .zip(r_right.par_iter_mut())
+        for m_x in m_extras.iter_mut() {
+            compress(m_x, split, &c);
+        }
+        cfg_iter_mut!(r_left)
+            .zip(cfg_iter_mut!(r_right))
             .for_each(|(r_l, r_r)| {
                 // r[:n'] + r[n':]^x^-1
                 r_r.mul_assign(&c_inv);
@@ -317,11 +589,14 @@ fn gipa_tipp_mipp<E: Pairing>(
         comms_ab.push((tab_l, tab_r));
         comms_c.push((tuc_l, tuc_r));
         z_ab.push((zab_l.0, zab_r.0));
-        z_c.push((zc_l.into_affine(), zc_r.into_affine()));
+        z_c.push((zc_l, zc_r));
+        for ((comms_x, z_x), (tux_l, tux_r, zx_l, zx_r)) in mipp_extras.iter_mut().zip(round_extras)
+        {
+            comms_x.push((tux_l, tux_r));
+            z_x.push((zx_l, zx_r));
+        }
         challenges.push(c);
         challenges_inv.push(c_inv);
-
-        i += 1;
     }
 
     assert!(m_a.len() == 1 && m_b.len() == 1);
@@ -331,10 +606,19 @@ fn gipa_tipp_mipp<E: Pairing>(
 
     let (final_a, final_b, final_c) = (m_a[0], m_b[0], m_c[0]);
     let (final_vkey, final_wkey) = (vkey.first(), wkey.first());
+    let mipp_extras = mipp_extras
+        .into_iter()
+        .zip(m_extras)
+        .map(|((comms, z), m_x)| MippExtension {
+            comms,
+            z,
+            final_x: m_x[0],
+        })
+        .collect();
 
     Ok((
         GipaProof {
-            nproofs: a.len() as u32, // TODO: ensure u32
+            nproofs,
             comms_ab,
             comms_c,
             z_ab,
@@ -345,6 +629,7 @@ fn gipa_tipp_mipp<E: Pairing>(
             final_vkey,
             final_wkey,
         },
+        mipp_extras,
         challenges,
         challenges_inv,
     ))
@@ -453,7 +738,7 @@ fn create_kzg_opening<G: AffineRepr>(
     // on the curve we are on). that's the extra cost of the commitment scheme
     // used which is compatible with Groth16 CRS insteaf of the original paper
     // of Bunz'19
-    let (a, b) = rayon::join(
+    let (a, b) = join(
         || {
             VariableBaseMSM::msm(&srs_powers_alpha_table, &quotient_polynomial_coeffs)
                 .expect("msm for a failed!")
@@ -4,16 +4,16 @@ use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, Group};
 // {AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::PrimeField;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
-use ark_std::{rand::Rng, One, UniformRand};
+use ark_std::io::{Read, Write};
+use ark_std::{rand::Rng, string::ToString, vec::Vec, One, UniformRand};
 use sha2::{Digest, Sha256};
-use std::io::{Read, Write};
 
-use std::clone::Clone;
+use ark_std::clone::Clone;
 
 use super::commitment::{VKey, WKey};
 use crate::Error;
 
-use std::ops::MulAssign;
+use ark_std::ops::MulAssign;
 /// Maximum size of the generic SRS constructed from Filecoin and Zcash power of
 /// taus.
 ///
@@ -111,6 +111,62 @@ impl<E: Pairing> ProverSRS<E> {
     }
 }
 
+impl<E: Pairing> ProverSRS<E> {
+    /// Returns the verifier SRS matching this prover SRS, i.e. the same one
+    /// `GenericSRS::specialize` returns alongside it.
+    pub fn to_verifier_srs(&self) -> VerifierSRS<E> {
+        VerifierSRS::<E> {
+            n: self.n,
+            g: self.g_alpha_powers_table[0].into_group(),
+            h: self.h_alpha_powers_table[0].into_group(),
+            g_alpha: self.g_alpha_powers_table[1].into_group(),
+            g_beta: self.g_beta_powers_table[1].into_group(),
+            h_alpha: self.h_alpha_powers_table[1].into_group(),
+            h_beta: self.h_beta_powers_table[1].into_group(),
+        }
+    }
+}
+
+impl<E: Pairing> VerifierSRS<E> {
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        (self.n as u32).serialize_compressed(&mut writer)?;
+        write_vec(&mut writer, &[self.g, self.g_alpha, self.g_beta])?;
+        write_vec(&mut writer, &[self.h, self.h_alpha, self.h_beta])?;
+        Ok(())
+    }
+
+    /// Reads a verifier SRS written by `write`, checking that the number of
+    /// proofs is a power of two within the bounds of the SRS and that all
+    /// points are in the prime order subgroups.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let n = u32::deserialize_compressed(&mut reader)? as usize;
+        if !(2..=MAX_SRS_SIZE).contains(&n) || !n.is_power_of_two() {
+            return Err(Error::InvalidSRS(
+                "number of proofs out of bounds".to_string(),
+            ));
+        }
+        let g = read_vec::<E::G1, _>(3, &mut reader)?;
+        let h = read_vec::<E::G2, _>(3, &mut reader)?;
+        Ok(Self {
+            n,
+            g: g[0],
+            g_alpha: g[1],
+            g_beta: g[2],
+            h: h[0],
+            h_alpha: h[1],
+            h_beta: h[2],
+        })
+    }
+
+    /// Returns the hash over all elements of this verifier srs, including the
+    /// number of proofs it is specialized for.
+    pub fn hash(&self) -> Vec<u8> {
+        let mut v = Vec::new();
+        self.write(&mut v).expect("failed to compute hash");
+        Sha256::digest(&v).to_vec()
+    }
+}
+
 impl<E: Pairing> GenericSRS<E> {
     /// specializes returns the prover and verifier SRS for a specific number of
     /// proofs to aggregate. The number of proofs MUST BE a power of two, it
@@ -136,6 +192,7 @@ impl<E: Pairing> GenericSRS<E> {
         let h_alpha_powers_table = self.h_alpha_powers[h_low..h_up].to_vec();
         let h_beta_powers_table = self.h_beta_powers[h_low..h_up].to_vec();
 
+        #[cfg(feature = "std")]
         println!(
             "\nPROVER SRS -- nun_proofs {}, tn {}, alpha_power_table {}\n",
             num_proofs,
@@ -246,34 +303,12 @@ pub fn setup_fake_srs<E: Pairing, R: Rng>(rng: &mut R, size: usize) -> GenericSR
     let g = E::G1::generator();
     let h = E::G2::generator();
 
-    let mut g_alpha_powers = Vec::new();
-    let mut g_beta_powers = Vec::new();
-    let mut h_alpha_powers = Vec::new();
-    let mut h_beta_powers = Vec::new();
-    rayon::scope(|s| {
-        let alpha = &alpha;
-        let h = &h;
-        let g = &g;
-        let beta = &beta;
-        let g_alpha_powers = &mut g_alpha_powers;
-        s.spawn(move |_| {
-            *g_alpha_powers = structured_generators_scalar_power(2 * size, g, alpha);
-        });
-        let g_beta_powers = &mut g_beta_powers;
-        s.spawn(move |_| {
-            *g_beta_powers = structured_generators_scalar_power(2 * size, g, beta);
-        });
-
-        let h_alpha_powers = &mut h_alpha_powers;
-        s.spawn(move |_| {
-            *h_alpha_powers = structured_generators_scalar_power(2 * size, h, alpha);
-        });
-
-        let h_beta_powers = &mut h_beta_powers;
-        s.spawn(move |_| {
-            *h_beta_powers = structured_generators_scalar_power(2 * size, h, beta);
-        });
-    });
+    par! {
+        let g_alpha_powers = structured_generators_scalar_power(2 * size, &g, &alpha),
+        let g_beta_powers = structured_generators_scalar_power(2 * size, &g, &beta),
+        let h_alpha_powers = structured_generators_scalar_power(2 * size, &h, &alpha),
+        let h_beta_powers = structured_generators_scalar_power(2 * size, &h, &beta)
+    };
 
     debug_assert!(h_alpha_powers[0] == E::G2Affine::generator());
     debug_assert!(h_beta_powers[0] == E::G2Affine::generator());
@@ -335,6 +370,28 @@ mod test {
     use rand_core::SeedableRng;
     use std::io::Cursor;
 
+    #[test]
+    fn test_verifier_srs_from_prover() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let srs = setup_fake_srs::<Bn254, _>(&mut rng, 8);
+        let (pk, vk) = srs.specialize(4);
+        let derived = pk.to_verifier_srs();
+        assert_eq!(vk, derived);
+        assert_eq!(vk.hash(), derived.hash());
+
+        let (_, vk8) = srs.specialize(8);
+        assert_ne!(vk.hash(), vk8.hash());
+
+        let mut buffer = Vec::new();
+        vk.write(&mut buffer).expect("writing to buffer failed");
+        let read = VerifierSRS::<Bn254>::read(Cursor::new(&buffer)).expect("can't read the srs");
+        assert_eq!(vk, read);
+        assert_eq!(vk.n, read.n);
+        buffer[0] = 3;
+        VerifierSRS::<Bn254>::read(Cursor::new(&buffer))
+            .expect_err("the number of proofs is not a power of two");
+    }
+
     #[test]
     fn test_srs_invalid_length() {
         let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
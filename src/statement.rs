@@ -0,0 +1,189 @@
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::VerifyingKey;
+use ark_serialize::CanonicalSerialize;
+use ark_std::{io, string::ToString, vec::Vec};
+use sha2::{Digest, Sha256};
+
+use super::{inputs::PublicInputs, srs::VerifierSRS, transcript::Transcript};
+use crate::Error;
+
+/// Version of the aggregation protocol that is bound in the transcript by
+/// `aggregate_proofs` and `verify_aggregate_proof`. Proofs created under a
+/// different version do not verify.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// HashWriter feeds the hasher with the serialized elements, as `Sha256` only
+/// implements the `Write` trait of `std`.
+struct HashWriter<'a>(&'a mut Sha256);
+
+impl io::Write for HashWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the SHA256 hash of the compressed encoding of the Groth16 verifying
+/// key.
+pub fn vk_digest<E: Pairing>(vk: &VerifyingKey<E>) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    vk.serialize_compressed(HashWriter(&mut hasher))
+        .expect("serialization failed");
+    hasher.finalize().to_vec()
+}
+
+/// Returns the SHA256 hash of all public inputs of all proofs. The number of
+/// proofs and the number of inputs per proof are hashed first, followed by
+/// every input in compressed form, proof after proof.
+/// The digest doesn't depend on how the inputs are stored. Proofs with
+/// different numbers of inputs are rejected, as the digest would not bind
+/// the shape of the inputs.
+pub fn public_inputs_digest<F: PrimeField, I: PublicInputs<F> + ?Sized>(
+    public_inputs: &I,
+) -> Result<Vec<u8>, Error> {
+    let mut hasher = Sha256::new();
+    let nproofs = public_inputs.num_proofs();
+    let len = match nproofs {
+        0 => 0,
+        _ => public_inputs
+            .num_inputs()
+            .ok_or_else(|| Error::InvalidProof("public inputs of different lengths".to_string()))?,
+    };
+    (nproofs as u64)
+        .serialize_compressed(HashWriter(&mut hasher))
+        .expect("serialization failed");
+    (len as u64)
+        .serialize_compressed(HashWriter(&mut hasher))
+        .expect("serialization failed");
+    for j in 0..nproofs {
+        for i in 0..len {
+            public_inputs
+                .input(j, i)
+                .serialize_compressed(HashWriter(&mut hasher))
+                .expect("serialization failed");
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Feeds the whole statement of an aggregation into the transcript, in this
+/// order:
+/// * the domain separator of the transcript
+/// * the protocol version
+/// * the digest of the Groth16 verifying key
+/// * the hash of the verifier SRS
+/// * the number of proofs aggregated
+/// * the digest of all public inputs
+///
+/// The prover and the verifier call it before anything else is appended by
+/// the aggregation protocol itself.
+pub(crate) fn bind_statement<
+    E: Pairing,
+    T: Transcript,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    transcript: &mut T,
+    vk: &VerifyingKey<E>,
+    v_srs: &VerifierSRS<E>,
+    public_inputs: &I,
+) -> Result<(), Error> {
+    check_public_inputs_shape(vk, public_inputs)?;
+    if public_inputs.num_proofs() != v_srs.n {
+        return Err(Error::InvalidProof(
+            "public inputs len != number of proofs".to_string(),
+        ));
+    }
+
+    transcript.domain_sep();
+    transcript.append(b"protocol-version", &PROTOCOL_VERSION);
+    transcript.append(b"vk-digest", &vk_digest(vk));
+    transcript.append(b"srs-digest", &v_srs.hash());
+    transcript.append(b"nproofs", &(public_inputs.num_proofs() as u32));
+    transcript.append(
+        b"public-inputs-digest",
+        &public_inputs_digest(public_inputs)?,
+    );
+    Ok(())
+}
+
+/// Feeds the statement of a succinct aggregation into the transcript, as
+/// `bind_statement` does but with the commitments to the columns of public
+/// inputs instead of the digest of all public inputs.
+pub(crate) fn bind_succinct_statement<E: Pairing, T: Transcript>(
+    transcript: &mut T,
+    vk: &VerifyingKey<E>,
+    v_srs: &VerifierSRS<E>,
+    commitments: &[E::G1Affine],
+) -> Result<(), Error> {
+    if commitments.len() + 1 != vk.gamma_abc_g1.len() {
+        return Err(Error::MalformedVerifyingKey);
+    }
+
+    transcript.domain_sep();
+    transcript.append(b"protocol-version", &PROTOCOL_VERSION);
+    transcript.append(b"vk-digest", &vk_digest(vk));
+    transcript.append(b"srs-digest", &v_srs.hash());
+    transcript.append(b"nproofs", &(v_srs.n as u32));
+    transcript.append(b"public-inputs-commitments", &commitments.to_vec());
+    Ok(())
+}
+
+/// Checks that each proof has as many public inputs as the verifying key
+/// expects.
+pub(crate) fn check_public_inputs_shape<E: Pairing, I: PublicInputs<E::ScalarField> + ?Sized>(
+    vk: &VerifyingKey<E>,
+    public_inputs: &I,
+) -> Result<(), Error> {
+    if public_inputs.num_proofs() > 0
+        && public_inputs.num_inputs().map(|n| n + 1) != Some(vk.gamma_abc_g1.len())
+    {
+        return Err(Error::MalformedVerifyingKey);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Bn254, Fr, G1Affine};
+    use ark_ec::AffineRepr;
+    use ark_ff::One;
+
+    #[test]
+    fn test_public_inputs_digest() {
+        let inputs = vec![vec![Fr::one(), Fr::one()]; 4];
+        let d1 = public_inputs_digest(&inputs).unwrap();
+        assert_eq!(d1, public_inputs_digest(&inputs.clone()).unwrap());
+
+        // same scalars but a different shape must not collide
+        let reshaped = vec![vec![Fr::one(); 4]; 2];
+        assert_ne!(d1, public_inputs_digest(&reshaped).unwrap());
+
+        let mut changed = inputs.clone();
+        changed[3][1] = Fr::from(2u64);
+        assert_ne!(d1, public_inputs_digest(&changed).unwrap());
+
+        // ragged inputs can't be hashed unambiguously
+        let mut ragged = inputs.clone();
+        ragged[1].push(Fr::one());
+        assert!(public_inputs_digest(&ragged).is_err());
+    }
+
+    #[test]
+    fn test_check_public_inputs_shape() {
+        let mut vk = VerifyingKey::<Bn254>::default();
+        let inputs = vec![vec![Fr::one(), Fr::one()]; 4];
+        // a verifying key without any IC point is rejected, not underflowed
+        assert!(check_public_inputs_shape(&vk, &inputs).is_err());
+
+        vk.gamma_abc_g1 = vec![G1Affine::generator(); 3];
+        assert!(check_public_inputs_shape(&vk, &inputs).is_ok());
+        vk.gamma_abc_g1.pop();
+        assert!(check_public_inputs_shape(&vk, &inputs).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use ark_ec::{pairing::Pairing, CurveGroup, VariableBaseMSM};
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::{PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_poly::{polynomial::univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_std::{cfg_into_iter, cfg_iter, rand::Rng, string::ToString, vec::Vec};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::{
+    inputs::PublicInputs,
+    proof::{InputsOpening, SuccinctAggregateProof},
+    prover::aggregate_proofs_with_extensions,
+    srs::{ProverSRS, VerifierSRS},
+    statement::{bind_succinct_statement, check_public_inputs_shape},
+    structured_scalar_power,
+    transcript::Transcript,
+    verifier::{verify_aggregate_proof_with_extensions, Extensions, SuccinctInputs, VerifierKeys},
+};
+use crate::Error;
+
+/// Returns the KZG commitments to the columns of public inputs: the i-th
+/// commitment is $\prod_j g^{x_{j,i} \alpha^j}$, the commitment to the
+/// polynomial whose coefficients are the i-th inputs of all proofs.
+/// These commitments are all a succinct verifier needs to know about the
+/// public inputs.
+pub fn commit_public_inputs<E: Pairing, I: PublicInputs<E::ScalarField> + ?Sized>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &I,
+) -> Result<Vec<E::G1Affine>, Error> {
+    let columns = input_columns(srs, vk, public_inputs)?;
+    Ok(commit_columns(srs, &columns))
+}
+
+/// Aggregates the proofs like `aggregate_proofs` but binds in the transcript
+/// the commitments to the columns of public inputs, as returned by
+/// `commit_public_inputs`, instead of all public inputs, and proves the
+/// evaluations of the columns needed by the aggregated Groth16 equation.
+/// The proof is verified with `verify_succinct_aggregate_proof`.
+pub fn aggregate_proofs_succinct<
+    E: Pairing,
+    T: Transcript,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &I,
+    proofs: &[Proof<E>],
+    transcript: &mut T,
+) -> Result<SuccinctAggregateProof<E>, Error> {
+    if public_inputs.num_proofs() != proofs.len() {
+        return Err(Error::InvalidProof(
+            "public inputs len != number of proofs".to_string(),
+        ));
+    }
+    let columns = input_columns(srs, vk, public_inputs)?;
+    let commitments = commit_columns(srs, &columns);
+    bind_succinct_statement(transcript, vk, &srs.to_verifier_srs(), &commitments)?;
+    let (proof, _, inputs) =
+        aggregate_proofs_with_extensions(srs, transcript, proofs, &[], Some(&columns))?;
+    Ok(SuccinctAggregateProof {
+        proof,
+        inputs: inputs.unwrap(),
+    })
+}
+
+/// Verifies an aggregate proof created by `aggregate_proofs_succinct` against
+/// the commitments to the columns of public inputs. On top of the checks of
+/// `verify_aggregate_proof`, it checks the batched KZG opening of the columns,
+/// whose evaluations replace the public inputs in the aggregated Groth16
+/// equation. Its cost is logarithmic in the number of proofs.
+pub fn verify_succinct_aggregate_proof<E: Pairing, R: Rng + Send, T: Transcript + Send>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    commitments: &[E::G1Affine],
+    proof: &SuccinctAggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    proof.parsing_check()?;
+    if proof.inputs.evaluations.len() != commitments.len() {
+        return Err(Error::InvalidProof(
+            "evaluations len != number of commitments".to_string(),
+        ));
+    }
+    if proof.proof.tmipp.gipa.nproofs as usize != ip_verifier_srs.n {
+        return Err(Error::InvalidProof(
+            "number of proofs != SRS size".to_string(),
+        ));
+    }
+    bind_succinct_statement(transcript, &pvk.vk, ip_verifier_srs, commitments)?;
+    let ext = Extensions {
+        inputs: Some(SuccinctInputs {
+            commitments,
+            opening: &proof.inputs,
+        }),
+        ..Default::default()
+    };
+    // the public inputs are replaced by the evaluations of their columns
+    let no_inputs: &[Vec<E::ScalarField>] = &[];
+    verify_aggregate_proof_with_extensions(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        no_inputs,
+        &proof.proof,
+        &ext,
+        rng,
+        transcript,
+    )
+}
+
+/// Appends the evaluations of the columns of public inputs to the transcript
+/// and derives the challenge batching their openings.
+pub(crate) fn inputs_challenge<F: PrimeField, T: Transcript>(
+    transcript: &mut T,
+    evaluations: &[F],
+) -> F {
+    transcript.append(b"public-inputs-evaluations", &evaluations.to_vec());
+    transcript.challenge_scalar::<F>(b"public-inputs-batching")
+}
+
+/// Proves the evaluations at $r$ of the polynomials of the columns of public
+/// inputs with a single KZG opening of their random linear combination.
+pub(crate) fn prove_inputs_opening<E: Pairing, T: Transcript>(
+    srs: &ProverSRS<E>,
+    transcript: &mut T,
+    columns: &[Vec<E::ScalarField>],
+    r: &E::ScalarField,
+) -> Result<InputsOpening<E>, Error> {
+    let evaluations = cfg_iter!(columns)
+        .map(|c| DensePolynomial::from_coefficients_slice(c).evaluate(r))
+        .collect::<Vec<_>>();
+    let gamma = inputs_challenge(transcript, &evaluations);
+    let gammas = structured_scalar_power(columns.len(), &gamma);
+
+    // P(X) = \sum_i gamma^i p_i(X)
+    let n = srs.n;
+    let combined = cfg_into_iter!(0..n)
+        .map(|j| columns.iter().zip(&gammas).map(|(c, g)| c[j] * g).sum())
+        .collect::<Vec<E::ScalarField>>();
+    // Q(X) = (P(X) - P(r)) / (X - r) by synthetic division
+    let mut quotient = vec![E::ScalarField::zero(); n - 1];
+    let mut acc = E::ScalarField::zero();
+    for k in (1..n).rev() {
+        acc = combined[k] + acc * r;
+        quotient[k - 1] = acc;
+    }
+    let opening = <E::G1 as VariableBaseMSM>::msm(&srs.g_alpha_powers_table[..n - 1], &quotient)
+        .map_err(|_| Error::InvalidSRS("SRS len < number of proofs".to_string()))?;
+    Ok(InputsOpening {
+        evaluations,
+        opening: opening.into_affine(),
+    })
+}
+
+/// Returns the public inputs column by column.
+fn input_columns<E: Pairing, I: PublicInputs<E::ScalarField> + ?Sized>(
+    srs: &ProverSRS<E>,
+    vk: &VerifyingKey<E>,
+    public_inputs: &I,
+) -> Result<Vec<Vec<E::ScalarField>>, Error> {
+    if vk.gamma_abc_g1.is_empty() {
+        return Err(Error::MalformedVerifyingKey);
+    }
+    check_public_inputs_shape(vk, public_inputs)?;
+    if public_inputs.num_proofs() != srs.n {
+        return Err(Error::InvalidProof(
+            "public inputs len != number of proofs".to_string(),
+        ));
+    }
+    Ok(cfg_into_iter!(0..vk.gamma_abc_g1.len() - 1)
+        .map(|i| {
+            (0..srs.n)
+                .map(|j| public_inputs.input(j, i))
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+fn commit_columns<E: Pairing>(
+    srs: &ProverSRS<E>,
+    columns: &[Vec<E::ScalarField>],
+) -> Vec<E::G1Affine> {
+    let commitments = cfg_iter!(columns)
+        .map(|c| {
+            <E::G1 as VariableBaseMSM>::msm(&srs.g_alpha_powers_table[..c.len()], c)
+                .expect("msm for commitment failed!")
+        })
+        .collect::<Vec<_>>();
+    E::G1::normalize_batch(&commitments)
+}
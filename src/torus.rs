@@ -0,0 +1,144 @@
+use ark_ec::pairing::Pairing;
+use ark_ff::{Field, QuadExtConfig, QuadExtField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::io::{Read, Write};
+use ark_std::{One, Zero};
+
+use super::proof::check_target_subgroup;
+use crate::Error;
+
+/// TorusCompressible is implemented by the quadratic extensions $F[w]/(w^2 -
+/// \beta)$ whose elements of norm one, among which the cyclotomic subgroup of
+/// the target group of a pairing, are compressed on the algebraic torus $T_2$
+/// to a single element of the base field, halving their size:
+/// $x = c_0 + c_1 w$ is compressed to $b = (c_0 + 1) / c_1$ and decompressed to
+/// $(b + w) / (b - w)$.
+///
+/// The elements with $c_1 = 0$, the identity and $-1$, have no compressed
+/// form.
+pub trait TorusCompressible: Field {
+    type Compressed: Field;
+
+    /// Compresses an element of norm one, or returns `None` for the identity
+    /// and $-1$. The result is meaningless for other elements.
+    fn torus_compress(&self) -> Option<Self::Compressed>;
+
+    /// Decompresses an element of the torus to an element of norm one.
+    fn torus_decompress(b: &Self::Compressed) -> Self;
+}
+
+impl<P: QuadExtConfig> TorusCompressible for QuadExtField<P> {
+    type Compressed = P::BaseField;
+
+    fn torus_compress(&self) -> Option<P::BaseField> {
+        self.c1
+            .inverse()
+            .map(|c1_inv| (self.c0 + P::BaseField::one()) * c1_inv)
+    }
+
+    fn torus_decompress(b: &P::BaseField) -> Self {
+        // (b + w) / (b - w) = (b^2 + beta + 2 b w) / (b^2 - beta), where
+        // b^2 - beta is never zero as beta is not a square
+        let b2 = b.square();
+        let den_inv = (b2 - P::NONRESIDUE).inverse().unwrap();
+        Self::new((b2 + P::NONRESIDUE) * den_inv, b.double() * den_inv)
+    }
+}
+
+/// Writes an element of the target group compressed on the torus. The
+/// identity, which has no compressed form, is written as $b = 0$, which would
+/// otherwise be decompressed to $-1$, an element outside of the target group.
+pub fn write_torus<E: Pairing, W: Write>(x: &E::TargetField, out: W) -> Result<(), Error>
+where
+    E::TargetField: TorusCompressible,
+{
+    x.torus_compress()
+        .unwrap_or_else(Zero::zero)
+        .serialize_compressed(out)?;
+    Ok(())
+}
+
+/// Reads an element of the target group compressed on the torus by
+/// `write_torus`, checking that its decompression is in the prime order
+/// subgroup.
+pub fn read_torus<E: Pairing, R: Read>(source: R) -> Result<E::TargetField, Error>
+where
+    E::TargetField: TorusCompressible,
+{
+    let b = <E::TargetField as TorusCompressible>::Compressed::deserialize_compressed(source)?;
+    if b.is_zero() {
+        return Ok(E::TargetField::one());
+    }
+    let x = E::TargetField::torus_decompress(&b);
+    check_target_subgroup::<E>([&x])?;
+    Ok(x)
+}
+
+/// Returns the size in bytes of an element of the target group compressed on
+/// the torus.
+pub fn torus_size<E: Pairing>() -> usize
+where
+    E::TargetField: TorusCompressible,
+{
+    <E::TargetField as TorusCompressible>::Compressed::zero().compressed_size()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::Bn254;
+    use ark_ec::AffineRepr;
+    use ark_std::{vec::Vec, UniformRand};
+
+    fn roundtrip<E: Pairing>()
+    where
+        E::TargetField: TorusCompressible,
+    {
+        let mut rng = ark_std::test_rng();
+        let g = E::pairing(E::G1Affine::generator(), E::G2Affine::generator());
+        for x in [
+            g.0,
+            E::TargetField::one(),
+            g.0 * g.0 * E::pairing(E::G1::rand(&mut rng), E::G2::rand(&mut rng)).0,
+        ] {
+            let mut buffer = Vec::new();
+            write_torus::<E, _>(&x, &mut buffer).unwrap();
+            assert_eq!(buffer.len(), torus_size::<E>());
+            assert_eq!(2 * buffer.len(), x.compressed_size());
+            assert_eq!(read_torus::<E, _>(&buffer[..]).unwrap(), x);
+        }
+    }
+
+    #[test]
+    fn test_torus_roundtrip() {
+        roundtrip::<Bn254>();
+        roundtrip::<Bls12_381>();
+        assert_eq!(torus_size::<Bls12_381>(), 288);
+    }
+
+    #[test]
+    fn test_torus_invalid() {
+        type Fq6 = <<Bn254 as Pairing>::TargetField as TorusCompressible>::Compressed;
+        let mut rng = ark_std::test_rng();
+        // a random element of the torus is of norm one but almost never in the
+        // prime order subgroup
+        let mut buffer = Vec::new();
+        Fq6::rand(&mut rng)
+            .serialize_compressed(&mut buffer)
+            .unwrap();
+        assert!(read_torus::<Bn254, _>(&buffer[..]).is_err());
+        // the identity and -1 have no compressed form, the identity is
+        // written as zero, which leaves no encoding of -1
+        let one = <Bn254 as Pairing>::TargetField::one();
+        assert!(one.torus_compress().is_none());
+        assert!((-one).torus_compress().is_none());
+        assert_eq!(
+            <Bn254 as Pairing>::TargetField::torus_decompress(&Fq6::zero()),
+            -one
+        );
+        let mut buffer = Vec::new();
+        write_torus::<Bn254, _>(&one, &mut buffer).unwrap();
+        assert_eq!(buffer, vec![0; torus_size::<Bn254>()]);
+    }
+}
@@ -1,10 +1,20 @@
-use ark_ff::fields::Field;
-use ark_serialize::{CanonicalSerialize, Compress};
+use ark_crypto_primitives::sponge::{
+    poseidon::{find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge},
+    Absorb, CryptographicSponge, FieldBasedCryptographicSponge,
+};
+use ark_ec::AffineRepr;
+use ark_ff::{
+    fields::{Field, PrimeField},
+    BigInteger, Zero,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+use ark_std::{fmt, string::String, vec::Vec};
 use merlin::Transcript as Merlin;
+use sha2::{Digest, Sha256};
 
 /// must be specific to the application.
-pub fn new_merlin_transcript(label: &'static [u8]) -> impl Transcript {
-    Merlin::new(label)
+pub fn new_merlin_transcript(label: &'static [u8]) -> impl Transcript + Clone {
+    MerlinTranscript::new(label)
 }
 
 /// Transcript is the application level transcript to derive the challenges
@@ -14,6 +24,99 @@ pub trait Transcript {
     fn domain_sep(&mut self);
     fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], point: &S);
     fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F;
+
+    /// Appends a curve point. By default the point is appended like any other
+    /// element; transcripts meant to be reimplemented outside of arkworks
+    /// absorb instead its affine coordinates in the encoding of their target.
+    fn append_point<G: AffineRepr>(&mut self, label: &'static [u8], point: &G) {
+        self.append(label, point);
+    }
+
+    /// Bit length of the challenges derived by `challenge_scalar`, or `None`
+    /// when they are uniform over the whole field. Shorter challenges make the
+    /// scalar multiplications by the challenges cheaper, at the cost of a
+    /// soundness error of $2^{-bits}$ per challenge instead of $1/p$.
+    fn challenge_bits(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the challenges bind every round of GIPA and its final values,
+    /// as in the version 2 of the aggregation of bellperson. By default the
+    /// first round is folded with the challenge derived before the loop, and
+    /// the KZG challenge binds the last challenge and the final commitment
+    /// keys only. Otherwise the challenge of the first round is derived again
+    /// from its elements, and the KZG challenge from a challenge linking the
+    /// last round to the final values of GIPA, and from these final values.
+    fn bind_gipa_rounds(&self) -> bool {
+        false
+    }
+}
+
+/// Security level in bits of the challenges uniform over the whole field:
+/// `hash_to_field` reduces that many more bits than the size of the modulus,
+/// so that the bias of the reduction is at most $2^{-128}$.
+pub const CHALLENGE_SECURITY_BITS: u32 = 128;
+
+/// Minimum bit length of short challenges, see `Transcript::challenge_bits`.
+pub const MIN_CHALLENGE_BITS: u32 = 128;
+
+/// Derives a field element from the output of an extendable output function,
+/// `expand`, following `hash_to_field` of RFC 9380 with $k = 128$: `expand`
+/// fills a buffer of $m \cdot L$ bytes, where $m$ is the extension degree of
+/// the field and $L = \lceil (\lceil \log_2 p \rceil + k) / 8 \rceil$, and
+/// each chunk of $L$ bytes, read as a big-endian integer, is reduced modulo
+/// $p$ to give one coefficient.
+///
+/// With `bits` smaller than the size of the modulus, each chunk is instead
+/// of $\lceil bits / 8 \rceil$ bytes, the leading bits beyond `bits` being
+/// cleared, and is a coefficient uniform in $[0, 2^{bits})$.
+///
+/// There is no rejection loop: the zero element, derived with a negligible
+/// probability, is replaced by one so that the challenge is always
+/// invertible.
+pub fn hash_to_field<F: Field>(bits: Option<u32>, expand: impl FnOnce(&mut [u8])) -> F {
+    let modulus_bits = F::BasePrimeField::MODULUS_BIT_SIZE;
+    let (len, mask) = match bits {
+        Some(bits) if bits < modulus_bits => {
+            let len = bits.div_ceil(8) as usize;
+            (len, 0xff >> (8 * len as u32 - bits))
+        }
+        _ => (
+            (modulus_bits + CHALLENGE_SECURITY_BITS).div_ceil(8) as usize,
+            0xff,
+        ),
+    };
+    let mut uniform = vec![0u8; len * F::extension_degree() as usize];
+    expand(&mut uniform);
+    let coeffs = uniform
+        .chunks_mut(len)
+        .map(|chunk| {
+            chunk[0] &= mask;
+            F::BasePrimeField::from_be_bytes_mod_order(chunk)
+        })
+        .collect::<Vec<_>>();
+    let challenge = F::from_base_prime_field_elems(&coeffs).unwrap();
+    if challenge.is_zero() {
+        F::one()
+    } else {
+        challenge
+    }
+}
+
+/// Derives a challenge like `Transcript::challenge_scalar` for Merlin did
+/// before `hash_to_field`: 64 bytes are squeezed from the transcript until
+/// `Field::from_random_bytes` maps them to a non-zero element. Only the bytes
+/// of one element are used, masked to the size of the modulus, and they are
+/// squeezed again whenever they exceed the modulus.
+fn legacy_challenge<F: Field>(transcript: &mut Merlin, label: &'static [u8]) -> F {
+    let mut buf = [0; 64];
+    loop {
+        transcript.challenge_bytes(label, &mut buf);
+        match F::from_random_bytes(&buf) {
+            Some(e) if !e.is_zero() => return e,
+            _ => continue,
+        }
+    }
 }
 
 impl Transcript for Merlin {
@@ -29,36 +132,647 @@ impl Transcript for Merlin {
         self.append_message(label, &buff);
     }
 
+    /// Derives the challenge with `hash_to_field`, Merlin being the
+    /// extendable output function and the label its domain separation tag.
+    fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
+        hash_to_field(self.challenge_bits(), |buf| {
+            self.challenge_bytes(label, buf)
+        })
+    }
+}
+
+/// MerlinTranscript is the default transcript, returned by
+/// `new_merlin_transcript`: a Merlin transcript whose challenges are derived
+/// with `hash_to_field`, optionally of a given bit length. The legacy mode
+/// derives the challenges of the previous versions of this crate instead, to
+/// verify the proofs they aggregated.
+#[derive(Clone)]
+pub struct MerlinTranscript {
+    inner: Merlin,
+    challenge_bits: Option<u32>,
+    legacy: bool,
+}
+
+impl MerlinTranscript {
+    pub fn new(label: &'static [u8]) -> Self {
+        Self {
+            inner: Merlin::new(label),
+            challenge_bits: None,
+            legacy: false,
+        }
+    }
+
+    /// Derives challenges of `bits` bits, see `Transcript::challenge_bits`.
+    /// Panics if `bits` is smaller than `MIN_CHALLENGE_BITS`.
+    pub fn with_challenge_bits(mut self, bits: u32) -> Self {
+        assert!(
+            bits >= MIN_CHALLENGE_BITS,
+            "challenges must be of at least {} bits",
+            MIN_CHALLENGE_BITS
+        );
+        self.challenge_bits = Some(bits);
+        self
+    }
+
+    /// Derives the challenges like the previous versions of this crate, with
+    /// a rejection loop, ignoring the bit length of the challenges.
+    pub fn legacy(mut self) -> Self {
+        self.legacy = true;
+        self
+    }
+}
+
+impl Transcript for MerlinTranscript {
+    fn domain_sep(&mut self) {
+        self.inner.domain_sep();
+    }
+
+    fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+        self.inner.append(label, element);
+    }
+
+    fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
+        match self.legacy {
+            true => legacy_challenge(&mut self.inner, label),
+            false => hash_to_field(self.challenge_bits, |buf| {
+                self.inner.challenge_bytes(label, buf)
+            }),
+        }
+    }
+
+    fn challenge_bits(&self) -> Option<u32> {
+        match self.legacy {
+            true => None,
+            false => self.challenge_bits,
+        }
+    }
+}
+
+/// TranscriptEvent is one operation recorded by a `RecordingTranscript`, with
+/// the compressed serialization of the element appended or of the challenge
+/// derived.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    DomainSep,
+    Append {
+        label: &'static [u8],
+        data: Vec<u8>,
+    },
+    Challenge {
+        label: &'static [u8],
+        value: Vec<u8>,
+    },
+}
+
+impl fmt::Display for TranscriptEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |f: &mut fmt::Formatter<'_>, bytes: &[u8]| {
+            bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+        };
+        match self {
+            TranscriptEvent::DomainSep => write!(f, "domain separator"),
+            TranscriptEvent::Append { label, data } => {
+                write!(
+                    f,
+                    "append \"{}\" ({} bytes) ",
+                    String::from_utf8_lossy(label),
+                    data.len()
+                )?;
+                hex(f, data)
+            }
+            TranscriptEvent::Challenge { label, value } => {
+                write!(f, "challenge \"{}\" -> ", String::from_utf8_lossy(label))?;
+                hex(f, value)
+            }
+        }
+    }
+}
+
+/// RecordingTranscript wraps a transcript and records every operation done on
+/// it, to find out why a prover and a verifier derive different challenges
+/// with `diff_recordings`. The challenges are the ones of the inner
+/// transcript.
+#[derive(Clone, Debug)]
+pub struct RecordingTranscript<T> {
+    inner: T,
+    events: Vec<TranscriptEvent>,
+}
+
+impl<T: Transcript> RecordingTranscript<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the operations recorded so far, in order.
+    pub fn events(&self) -> &[TranscriptEvent] {
+        &self.events
+    }
+
+    /// Returns the inner transcript and the operations recorded.
+    pub fn into_parts(self) -> (T, Vec<TranscriptEvent>) {
+        (self.inner, self.events)
+    }
+
+    fn record<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+        let mut data = Vec::with_capacity(element.serialized_size(Compress::Yes));
+        element
+            .serialize_compressed(&mut data)
+            .expect("serialization failed");
+        self.events.push(TranscriptEvent::Append { label, data });
+    }
+}
+
+impl<T: Transcript> Transcript for RecordingTranscript<T> {
+    fn domain_sep(&mut self) {
+        self.events.push(TranscriptEvent::DomainSep);
+        self.inner.domain_sep();
+    }
+
+    fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+        self.record(label, element);
+        self.inner.append(label, element);
+    }
+
+    fn append_point<G: AffineRepr>(&mut self, label: &'static [u8], point: &G) {
+        self.record(label, point);
+        self.inner.append_point(label, point);
+    }
+
+    fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
+        let challenge = self.inner.challenge_scalar::<F>(label);
+        let mut value = Vec::with_capacity(challenge.compressed_size());
+        challenge
+            .serialize_compressed(&mut value)
+            .expect("serialization failed");
+        self.events
+            .push(TranscriptEvent::Challenge { label, value });
+        challenge
+    }
+
+    fn challenge_bits(&self) -> Option<u32> {
+        self.inner.challenge_bits()
+    }
+
+    fn bind_gipa_rounds(&self) -> bool {
+        self.inner.bind_gipa_rounds()
+    }
+}
+
+/// TranscriptDivergence is the first operation on which two recordings differ,
+/// `None` on the side whose recording ended before.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptDivergence {
+    pub index: usize,
+    pub prover: Option<TranscriptEvent>,
+    pub verifier: Option<TranscriptEvent>,
+}
+
+impl fmt::Display for TranscriptDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "transcripts diverge at operation {}", self.index)?;
+        match &self.prover {
+            Some(e) => writeln!(f, "  prover:   {}", e)?,
+            None => writeln!(f, "  prover:   <end of recording>")?,
+        }
+        match &self.verifier {
+            Some(e) => write!(f, "  verifier: {}", e),
+            None => write!(f, "  verifier: <end of recording>"),
+        }
+    }
+}
+
+/// Returns the first operation on which the recordings of a prover and a
+/// verifier differ, or `None` if they are identical. Since every challenge
+/// depends on all the operations before it, only the first divergence is
+/// meaningful: a differing label, an extra `append` on one side or inputs
+/// appended in another order all show up there.
+pub fn diff_recordings(
+    prover: &[TranscriptEvent],
+    verifier: &[TranscriptEvent],
+) -> Option<TranscriptDivergence> {
+    (0..prover.len().max(verifier.len()))
+        .find(|&i| prover.get(i) != verifier.get(i))
+        .map(|index| TranscriptDivergence {
+            index,
+            prover: prover.get(index).cloned(),
+            verifier: verifier.get(index).cloned(),
+        })
+}
+
+/// Keccak256Transcript is a transcript built on Keccak-256 with a byte-exact
+/// format simple enough to be reimplemented by an EVM contract. Its state is a
+/// 32 bytes digest, initialized to `keccak256(label)`.
+///
+/// Appending an element sets the state to
+/// `keccak256(state || u32(len(label)) || label || u32(len(data)) || data)`,
+/// with lengths in big-endian. The `data` of a point appended with
+/// `append_point`, as the protocol does for every point appended alone, is
+/// its affine coordinates in big-endian, the coefficients of each coordinate
+/// in an extension field from the highest degree down. For BN254, it gives:
+/// * G1 points as `x || y` in 64 bytes, the EIP-196 encoding
+/// * G2 points as `x.c1 || x.c0 || y.c1 || y.c0` in 128 bytes, the EIP-197
+///   encoding
+/// * the point at infinity as zero coordinates, as in both EIPs
+///
+/// The `data` of any other element is its uncompressed arkworks
+/// serialization converted to big-endian: it is split into words of 32 bytes
+/// from its end, each word and the shorter leading chunk if any being
+/// reversed. For BN254, it gives:
+/// * scalars in 32 bytes and integers in their own size
+/// * elements of the target group as their 12 base field coefficients, in
+///   the order of arkworks
+/// * vectors as their length in 8 bytes followed by their elements
+///
+/// A challenge is derived by setting the state to
+/// `seed = keccak256(state || u32(len(label)) || label)` and reducing
+/// `keccak256(seed || u8(2i)) || keccak256(seed || u8(2i + 1))`, read in
+/// big-endian, modulo the prime for the i-th coefficient of the field, i.e.
+/// only i = 0 for a prime field. A zero challenge is rejected and derived
+/// again with the same label.
+///
+/// The test vectors of `tests/vectors/keccak256_transcript.txt` fix the
+/// challenges of a sequence of elements of BN254.
+#[derive(Clone, Debug)]
+pub struct Keccak256Transcript {
+    state: [u8; 32],
+}
+
+impl Keccak256Transcript {
+    pub fn new(label: &'static [u8]) -> Self {
+        Self {
+            state: keccak256(label),
+        }
+    }
+
+    /// Returns the current state of the transcript.
+    pub fn state(&self) -> [u8; 32] {
+        self.state
+    }
+
+    fn absorb(&mut self, label: &[u8], data: &[u8]) {
+        let mut input = Vec::with_capacity(40 + label.len() + data.len());
+        input.extend_from_slice(&self.state);
+        input.extend_from_slice(&(label.len() as u32).to_be_bytes());
+        input.extend_from_slice(label);
+        input.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        input.extend_from_slice(data);
+        self.state = keccak256(&input);
+    }
+
+    /// Returns the big-endian encoding of the element absorbed by `append`.
+    pub fn encode<S: CanonicalSerialize>(element: &S) -> Vec<u8> {
+        encode_be(element)
+    }
+
+    /// Returns the encoding of the point absorbed by `append_point`.
+    pub fn encode_point<G: AffineRepr>(point: &G) -> Vec<u8> {
+        let size = <G::BaseField as Field>::BasePrimeField::MODULUS_BIT_SIZE.div_ceil(8) as usize;
+        let zero = G::BaseField::zero();
+        let (x, y) = point.xy().unwrap_or((&zero, &zero));
+        let mut data = Vec::with_capacity(2 * size * G::BaseField::extension_degree() as usize);
+        for coordinate in [x, y] {
+            let coeffs = coordinate
+                .to_base_prime_field_elements()
+                .collect::<Vec<_>>();
+            for coeff in coeffs.iter().rev() {
+                let bytes = coeff.into_bigint().to_bytes_be();
+                data.extend_from_slice(&bytes[bytes.len() - size..]);
+            }
+        }
+        data
+    }
+}
+
+/// Returns the uncompressed arkworks serialization of the element converted
+/// to big-endian: it is split into words of 32 bytes from its end, each word
+/// and the shorter leading chunk if any being reversed.
+fn encode_be<S: CanonicalSerialize>(element: &S) -> Vec<u8> {
+    let mut data = Vec::with_capacity(element.serialized_size(Compress::No));
+    element
+        .serialize_uncompressed(&mut data)
+        .expect("serialization failed");
+    let lead = data.len() % 32;
+    data[..lead].reverse();
+    data[lead..].chunks_mut(32).for_each(|word| word.reverse());
+    data
+}
+
+impl Transcript for Keccak256Transcript {
+    fn domain_sep(&mut self) {
+        self.absorb(b"dom-sep", b"groth16-aggregation-snarkpack");
+    }
+
+    fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+        self.absorb(label, &Self::encode(element));
+    }
+
+    fn append_point<G: AffineRepr>(&mut self, label: &'static [u8], point: &G) {
+        self.absorb(label, &Self::encode_point(point));
+    }
+
     fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
-        // Reduce a double-width scalar to ensure a uniform distribution
-        let mut buf = [0; 64];
-        self.challenge_bytes(label, &mut buf);
-        let mut counter = 0;
         loop {
-            match F::from_random_bytes(&buf) {
-                Some(e) => {
-                    if let Some(_) = e.inverse() {
-                        return e;
-                    } else {
-                        continue;
+            let mut input = Vec::with_capacity(36 + label.len());
+            input.extend_from_slice(&self.state);
+            input.extend_from_slice(&(label.len() as u32).to_be_bytes());
+            input.extend_from_slice(label);
+            self.state = keccak256(&input);
+
+            let coeffs = (0..F::extension_degree() as u8)
+                .map(|i| {
+                    let mut wide = [0u8; 64];
+                    for (j, half) in wide.chunks_mut(32).enumerate() {
+                        let mut input = self.state.to_vec();
+                        input.push(2 * i + j as u8);
+                        half.copy_from_slice(&keccak256(&input));
                     }
-                }
-                None => {
-                    buf[0] = counter;
-                    counter += 1;
-                    self.challenge_bytes(label, &mut buf);
-                    continue;
-                }
+                    F::BasePrimeField::from_be_bytes_mod_order(&wide)
+                })
+                .collect::<Vec<_>>();
+            let challenge = F::from_base_prime_field_elems(&coeffs).unwrap();
+            if !challenge.is_zero() {
+                return challenge;
             }
         }
     }
 }
 
+/// Prefix of the tags of the tagged hashes of `Sha256Transcript`.
+pub const SHA256_TAG_PREFIX: &[u8] = b"snarkpack/";
+
+/// Sha256Transcript is a transcript built on BIP-340 tagged hashes of SHA-256,
+/// `tagged_hash(tag, msg) = sha256(sha256(tag) || sha256(tag) || msg)`, so it
+/// can be reproduced where SHA-256 is the only hash available, e.g. in Bitcoin
+/// script. The tag of a label is `"snarkpack/" || label` and the state is a 32
+/// bytes digest, initialized to `tagged_hash(tag(label), "")`.
+///
+/// Appending an element sets the state to
+/// `tagged_hash(tag(label), state || data)`, where `data` is the big-endian
+/// encoding of the element described on `Keccak256Transcript`, e.g. 32 bytes
+/// per coordinate of BN254.
+///
+/// A challenge is derived by setting the state to
+/// `seed = tagged_hash(tag(label), state)` and reducing the 64 bytes
+/// `tagged_hash("snarkpack/challenge", seed || u8(2i)) ||
+/// tagged_hash("snarkpack/challenge", seed || u8(2i + 1))`, read in
+/// big-endian, modulo the prime for the i-th coefficient of the field. The
+/// reduction of 512 bits makes the bias of the challenge negligible for
+/// primes of up to 384 bits. A zero challenge is rejected and derived again
+/// with the same label.
+///
+/// The state can be saved with `state` or serialized, and resumed with
+/// `from_state`, so a verifier can check the derivation one step at a time.
+/// The test vectors of `tests/vectors/sha256_transcript.txt` give the state
+/// after each step for a sequence of elements of BN254.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Sha256Transcript {
+    state: [u8; 32],
+}
+
+impl Sha256Transcript {
+    pub fn new(label: &'static [u8]) -> Self {
+        Self {
+            state: tagged_hash(&Self::tag(label), &[]),
+        }
+    }
+
+    /// Resumes a transcript from a state returned by `state`.
+    pub fn from_state(state: [u8; 32]) -> Self {
+        Self { state }
+    }
+
+    /// Returns the current state of the transcript.
+    pub fn state(&self) -> [u8; 32] {
+        self.state
+    }
+
+    /// Returns the tag of the tagged hashes of a label.
+    pub fn tag(label: &[u8]) -> Vec<u8> {
+        [SHA256_TAG_PREFIX, label].concat()
+    }
+
+    fn absorb(&mut self, label: &[u8], data: &[u8]) {
+        self.state = tagged_hash(&Self::tag(label), &[&self.state[..], data].concat());
+    }
+
+    /// Returns the big-endian encoding of the element absorbed by `append`.
+    pub fn encode<S: CanonicalSerialize>(element: &S) -> Vec<u8> {
+        encode_be(element)
+    }
+
+    /// Returns the 64 bytes reduced into the i-th coefficient of a challenge
+    /// derived from the seed.
+    pub fn wide_bytes(seed: &[u8; 32], i: u8) -> [u8; 64] {
+        let tag = Self::tag(b"challenge");
+        let mut wide = [0u8; 64];
+        for (j, half) in wide.chunks_mut(32).enumerate() {
+            half.copy_from_slice(&tagged_hash(
+                &tag,
+                &[&seed[..], &[2 * i + j as u8]].concat(),
+            ));
+        }
+        wide
+    }
+}
+
+impl Transcript for Sha256Transcript {
+    fn domain_sep(&mut self) {
+        self.absorb(b"dom-sep", b"groth16-aggregation-snarkpack");
+    }
+
+    fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+        self.absorb(label, &Self::encode(element));
+    }
+
+    fn challenge_scalar<F: Field>(&mut self, label: &'static [u8]) -> F {
+        loop {
+            self.absorb(label, &[]);
+            let coeffs = (0..F::extension_degree() as u8)
+                .map(|i| {
+                    F::BasePrimeField::from_be_bytes_mod_order(&Self::wide_bytes(&self.state, i))
+                })
+                .collect::<Vec<_>>();
+            let challenge = F::from_base_prime_field_elems(&coeffs).unwrap();
+            if !challenge.is_zero() {
+                return challenge;
+            }
+        }
+    }
+}
+
+/// Returns the BIP-340 tagged hash of the message,
+/// `sha256(sha256(tag) || sha256(tag) || msg)`.
+pub fn tagged_hash(tag: &[u8], msg: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(tag);
+    Sha256::new()
+        .chain_update(tag)
+        .chain_update(tag)
+        .chain_update(msg)
+        .finalize()
+        .into()
+}
+
+/// Number of full rounds of the Poseidon permutation of `PoseidonTranscript`.
+pub const POSEIDON_FULL_ROUNDS: usize = 8;
+/// Number of partial rounds of the Poseidon permutation of
+/// `PoseidonTranscript`.
+pub const POSEIDON_PARTIAL_ROUNDS: usize = 57;
+/// Exponent of the S-box of the Poseidon permutation of `PoseidonTranscript`.
+pub const POSEIDON_ALPHA: u64 = 5;
+/// Size in bytes of the limbs the elements appended to a `PoseidonTranscript`
+/// are split into.
+pub const POSEIDON_LIMB_BYTES: usize = 16;
+
+/// Returns the parameters of the Poseidon sponge of `PoseidonTranscript`: a
+/// state of 3 elements with a rate of 2 and a capacity of 1, x^5 as S-box, 8
+/// full rounds and 57 partial rounds, i.e. the parameters for 128 bits of
+/// security on 254 and 255 bits fields, and round constants and MDS matrix
+/// generated with the Grain LFSR of the Poseidon reference implementation
+/// without skipping any matrix.
+pub fn poseidon_config<F: PrimeField>() -> PoseidonConfig<F> {
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(
+        F::MODULUS_BIT_SIZE as u64,
+        2,
+        POSEIDON_FULL_ROUNDS as u64,
+        POSEIDON_PARTIAL_ROUNDS as u64,
+        0,
+    );
+    PoseidonConfig::new(
+        POSEIDON_FULL_ROUNDS,
+        POSEIDON_PARTIAL_ROUNDS,
+        POSEIDON_ALPHA,
+        mds,
+        ark,
+        2,
+        1,
+    )
+}
+
+/// PoseidonTranscript is a transcript built on a Poseidon sponge over the
+/// scalar field F of the verifying curve, so the challenges can be derived
+/// cheaply by a circuit verifying the aggregate proof. The sponge is the
+/// duplex `PoseidonSponge` of arkworks with the parameters of
+/// `poseidon_config`, hence it matches its `PoseidonSpongeVar` gadget.
+///
+/// The layout of the elements absorbed is the following:
+/// * a label is absorbed as the element whose little-endian encoding is the
+///   label, reduced modulo the prime
+/// * the transcript starts by absorbing its label
+/// * appending an element absorbs its label, the number of its limbs and its
+///   limbs. The limbs are obtained by splitting the uncompressed arkworks
+///   serialization of the element into chunks of 16 bytes from its end, the
+///   shorter leading chunk if any being a limb as well, each chunk being read
+///   as a little-endian integer. A coordinate of a G1 or G2 point, or a
+///   coefficient of an element of the target group, is thus two limbs, low
+///   first, the flag of the point at infinity being in the top bits of the
+///   last limb of the point; a vector starts with a limb holding its length.
+/// * a challenge absorbs its label and squeezes one element per coefficient
+///   of the field of the challenge, cast to this field. A zero challenge is
+///   rejected and squeezed again.
+///
+/// The test vectors of `tests/vectors/poseidon_transcript.txt` fix the
+/// challenges of a sequence of elements of BN254.
+#[derive(Clone)]
+pub struct PoseidonTranscript<F: PrimeField + Absorb> {
+    sponge: PoseidonSponge<F>,
+}
+
+impl<F: PrimeField + Absorb> PoseidonTranscript<F> {
+    /// Returns a new transcript with the parameters of `poseidon_config`,
+    /// which are computed on each call; `with_config` avoids it.
+    pub fn new(label: &'static [u8]) -> Self {
+        Self::with_config(label, &poseidon_config())
+    }
+
+    /// Returns a new transcript with the given sponge parameters, e.g. the
+    /// ones of `poseidon_config` computed once.
+    pub fn with_config(label: &'static [u8], config: &PoseidonConfig<F>) -> Self {
+        let mut sponge = PoseidonSponge::new(config);
+        sponge.absorb(&Self::encode_label(label));
+        Self { sponge }
+    }
+
+    fn encode_label(label: &[u8]) -> F {
+        F::from_le_bytes_mod_order(label)
+    }
+
+    /// Returns the limbs of the element absorbed by `append`.
+    pub fn encode<S: CanonicalSerialize>(element: &S) -> Vec<F> {
+        let mut data = Vec::with_capacity(element.serialized_size(Compress::No));
+        element
+            .serialize_uncompressed(&mut data)
+            .expect("serialization failed");
+        let (lead, words) = data.split_at(data.len() % POSEIDON_LIMB_BYTES);
+        Some(lead)
+            .filter(|lead| !lead.is_empty())
+            .into_iter()
+            .chain(words.chunks(POSEIDON_LIMB_BYTES))
+            .map(F::from_le_bytes_mod_order)
+            .collect()
+    }
+}
+
+impl<F: PrimeField + Absorb> Transcript for PoseidonTranscript<F> {
+    fn domain_sep(&mut self) {
+        self.append(b"dom-sep", &b"groth16-aggregation-snarkpack".to_vec());
+    }
+
+    fn append<S: CanonicalSerialize>(&mut self, label: &'static [u8], element: &S) {
+        let limbs = Self::encode(element);
+        self.sponge.absorb(&Self::encode_label(label));
+        self.sponge.absorb(&F::from(limbs.len() as u64));
+        self.sponge.absorb(&limbs);
+    }
+
+    fn challenge_scalar<C: Field>(&mut self, label: &'static [u8]) -> C {
+        self.sponge.absorb(&Self::encode_label(label));
+        loop {
+            let coeffs = self
+                .sponge
+                .squeeze_native_field_elements(C::extension_degree() as usize)
+                .into_iter()
+                .map(|x| C::BasePrimeField::from_le_bytes_mod_order(&x.into_bigint().to_bytes_le()))
+                .collect::<Vec<_>>();
+            let challenge = C::from_base_prime_field_elems(&coeffs).unwrap();
+            if !challenge.is_zero() {
+                return challenge;
+            }
+        }
+    }
+}
+
+/// Rate of Keccak-256 in bytes.
+const KECCAK256_RATE: usize = 136;
+
+/// Returns the Keccak-256 digest of the input, as used by the EVM, i.e. with
+/// the original padding of Keccak and not the one of SHA-3.
+pub fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut state = [0u64; 25];
+    let mut padded = input.to_vec();
+    padded.push(0x01);
+    padded.resize(padded.len().div_ceil(KECCAK256_RATE) * KECCAK256_RATE, 0);
+    *padded.last_mut().unwrap() |= 0x80;
+    for block in padded.chunks(KECCAK256_RATE) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        keccak::f1600(&mut state);
+    }
+    let mut digest = [0u8; 32];
+    for (bytes, lane) in digest.chunks_mut(8).zip(state.iter()) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    digest
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use ark_bn254::{Fr, G1Projective};
-    use ark_ec::Group;
+    use ark_ec::{CurveGroup, Group};
 
     #[test]
     fn transcript() {
@@ -70,4 +784,417 @@ mod test {
         let f2 = transcript2.challenge_scalar::<Fr>(b"scalar");
         assert_eq!(f1, f2);
     }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Replays the operations of the test vectors on a MerlinTranscript in
+    /// each of its modes and returns them in the format of the file: the
+    /// compressed elements appended and, for each challenge, the bytes
+    /// squeezed by `hash_to_field` and the challenge in big-endian.
+    fn merlin_vectors() -> Vec<String> {
+        use ark_bn254::{Bn254, G2Projective};
+        use ark_ec::pairing::Pairing;
+
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        let pairing = Bn254::pairing(g1, g2).0;
+        let inputs = vec![Fr::from(1u64), Fr::from(2u64)];
+        let modes = [
+            ("default", MerlinTranscript::new(b"snarkpack-test-vectors")),
+            (
+                "bits-128",
+                MerlinTranscript::new(b"snarkpack-test-vectors").with_challenge_bits(128),
+            ),
+            (
+                "legacy",
+                MerlinTranscript::new(b"snarkpack-test-vectors").legacy(),
+            ),
+        ];
+        let mut lines = Vec::new();
+        for (mode, mut transcript) in modes {
+            lines.push(format!("new snarkpack-test-vectors {}", mode));
+            macro_rules! append {
+                ($label:expr, $element:expr) => {
+                    let mut data = Vec::new();
+                    $element.serialize_compressed(&mut data).unwrap();
+                    lines.push(format!(
+                        "append {} {}",
+                        ark_std::str::from_utf8($label).unwrap(),
+                        hex(&data)
+                    ));
+                    transcript.append($label, $element);
+                };
+            }
+            append!(b"nproofs", &8u32);
+            append!(b"g1", &g1);
+            append!(b"g2", &g2);
+            append!(b"scalar", &Fr::from(42u64));
+            append!(b"gt", &pairing);
+            append!(b"inputs", &inputs);
+            for label in [b"r", b"x"] {
+                let mut uniform = "-".to_string();
+                if !transcript.legacy {
+                    let mut fork = transcript.inner.clone();
+                    let _: Fr = hash_to_field(transcript.challenge_bits(), |buf| {
+                        fork.challenge_bytes(label, buf);
+                        uniform = hex(buf);
+                    });
+                }
+                let c = transcript.challenge_scalar::<Fr>(label);
+                lines.push(format!(
+                    "challenge {} {} {}",
+                    ark_std::str::from_utf8(label).unwrap(),
+                    uniform,
+                    hex(&c.into_bigint().to_bytes_be())
+                ));
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn test_merlin_transcript_vectors() {
+        let vectors = include_str!("../tests/vectors/merlin_transcript.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(merlin_vectors(), vectors);
+    }
+
+    #[test]
+    fn test_hash_to_field() {
+        // 48 bytes are reduced for a 254-bit modulus, 16 for 128-bit challenges
+        let mut len = 0;
+        let _: Fr = hash_to_field(None, |buf| len = buf.len());
+        assert_eq!(len, 48);
+        let c: Fr = hash_to_field(Some(128), |buf| {
+            len = buf.len();
+            buf.fill(0xff);
+        });
+        assert_eq!(len, 16);
+        assert_eq!(c, Fr::from(u128::MAX));
+        let c: Fr = hash_to_field(Some(130), |buf| buf.fill(0xff));
+        assert_eq!(c, Fr::from(u128::MAX) * Fr::from(4u64) + Fr::from(3u64));
+
+        // a bit length larger than the modulus gives full challenges
+        let c: Fr = hash_to_field(Some(512), |buf| buf.fill(0xff));
+        assert_eq!(c, Fr::from_be_bytes_mod_order(&[0xff; 48]));
+
+        // zero is replaced by one
+        let c: Fr = hash_to_field(None, |buf| buf.fill(0));
+        assert_eq!(c, Fr::from(1u64));
+
+        // the legacy mode derives the challenges of the previous versions
+        let mut t1 = MerlinTranscript::new(b"test").legacy();
+        let mut t2 = Merlin::new(b"test");
+        t1.append(b"point", &G1Projective::generator());
+        t2.append(b"point", &G1Projective::generator());
+        let mut buf = [0u8; 64];
+        t2.challenge_bytes(b"c", &mut buf);
+        assert_eq!(
+            Some(t1.challenge_scalar::<Fr>(b"c")),
+            Fr::from_random_bytes(&buf)
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            hex(&keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex(&keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        // input of exactly one block, padded with a full block
+        assert_eq!(
+            hex(&keccak256(&[0x61; 136])),
+            "a6c4d403279fe3e0af03729caada8374b5ca54d8065329a3ebcaeb4b60aa386e"
+        );
+    }
+
+    /// Replays the operations of the test vectors on a Keccak256Transcript
+    /// and returns them in the format of the file: the data absorbed by
+    /// `append` and the challenges in big-endian.
+    fn keccak256_vectors() -> Vec<String> {
+        use ark_bn254::{Bn254, G2Projective};
+        use ark_ec::pairing::Pairing;
+
+        let mut lines = vec!["new snarkpack-test-vectors".to_string()];
+        let mut transcript = Keccak256Transcript::new(b"snarkpack-test-vectors");
+        let mut append = |t: &mut Keccak256Transcript, label: &'static [u8], data: Vec<u8>| {
+            lines.push(format!(
+                "append {} {}",
+                ark_std::str::from_utf8(label).unwrap(),
+                hex(&data)
+            ));
+            t.absorb(label, &data);
+        };
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        append(
+            &mut transcript,
+            b"nproofs",
+            Keccak256Transcript::encode(&8u32),
+        );
+        append(
+            &mut transcript,
+            b"g1",
+            Keccak256Transcript::encode_point(&g1.into_affine()),
+        );
+        append(
+            &mut transcript,
+            b"g2",
+            Keccak256Transcript::encode_point(&g2.into_affine()),
+        );
+        append(
+            &mut transcript,
+            b"scalar",
+            Keccak256Transcript::encode(&Fr::from(42u64)),
+        );
+        let pairing = Bn254::pairing(g1, g2).0;
+        append(
+            &mut transcript,
+            b"gt",
+            Keccak256Transcript::encode(&pairing),
+        );
+        let inputs = vec![Fr::from(1u64), Fr::from(2u64)];
+        append(
+            &mut transcript,
+            b"inputs",
+            Keccak256Transcript::encode(&inputs),
+        );
+        let r = transcript.challenge_scalar::<Fr>(b"r");
+        let x = transcript.challenge_scalar::<Fr>(b"x");
+        for (label, c) in [("r", r), ("x", x)] {
+            lines.push(format!(
+                "challenge {} {}",
+                label,
+                hex(&Keccak256Transcript::encode(&c))
+            ));
+        }
+        lines
+    }
+
+    #[test]
+    fn test_keccak256_transcript_vectors() {
+        let vectors = include_str!("../tests/vectors/keccak256_transcript.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(keccak256_vectors(), vectors);
+
+        // the transcript itself absorbs the same data
+        let mut t1 = Keccak256Transcript::new(b"test");
+        t1.append(b"point", &G1Projective::generator());
+        let mut t2 = Keccak256Transcript::new(b"test");
+        t2.absorb(
+            b"point",
+            &Keccak256Transcript::encode(&G1Projective::generator()),
+        );
+        assert_eq!(t1.state(), t2.state());
+        assert_eq!(
+            t1.challenge_scalar::<Fr>(b"c"),
+            t2.challenge_scalar::<Fr>(b"c")
+        );
+
+        // points appended alone are absorbed in the EIP-196/197 encoding
+        use ark_bn254::{G1Affine, G2Affine};
+        let g2 = G2Affine::generator();
+        t1.append_point(b"point", &g2);
+        t2.absorb(b"point", &Keccak256Transcript::encode_point(&g2));
+        assert_eq!(t1.state(), t2.state());
+        assert_eq!(
+            Keccak256Transcript::encode_point(&G1Affine::zero()),
+            vec![0u8; 64]
+        );
+        // no sign flag in the encoding of y
+        let neg = Keccak256Transcript::encode_point(&-G1Affine::generator());
+        let pos = Keccak256Transcript::encode_point(&G1Affine::generator());
+        assert_eq!(neg[..32], pos[..32]);
+        assert_eq!(neg[32] & 0xc0, 0);
+    }
+
+    /// Replays the operations of the test vectors on a PoseidonTranscript
+    /// and returns them in the format of the file: the limbs absorbed by
+    /// `append` and the challenges, as big-endian integers in hex.
+    fn poseidon_vectors() -> Vec<String> {
+        use ark_bn254::{Bn254, G2Projective};
+        use ark_ec::pairing::Pairing;
+
+        let fe = |x: &Fr| hex(&x.into_bigint().to_bytes_be());
+        let mut lines = vec!["new snarkpack-test-vectors".to_string()];
+        let mut transcript = PoseidonTranscript::<Fr>::new(b"snarkpack-test-vectors");
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        let pairing = Bn254::pairing(g1, g2).0;
+        let inputs = vec![Fr::from(1u64), Fr::from(2u64)];
+        macro_rules! append {
+            ($label:expr, $element:expr) => {
+                let limbs = PoseidonTranscript::<Fr>::encode($element);
+                lines.push(format!(
+                    "append {} {}",
+                    ark_std::str::from_utf8($label).unwrap(),
+                    limbs.iter().map(fe).collect::<Vec<_>>().join(",")
+                ));
+                transcript.append($label, $element);
+            };
+        }
+        append!(b"nproofs", &8u32);
+        append!(b"g1", &g1);
+        append!(b"g2", &g2);
+        append!(b"scalar", &Fr::from(42u64));
+        append!(b"gt", &pairing);
+        append!(b"inputs", &inputs);
+        let r = transcript.challenge_scalar::<Fr>(b"r");
+        let x = transcript.challenge_scalar::<Fr>(b"x");
+        for (label, c) in [("r", r), ("x", x)] {
+            lines.push(format!("challenge {} {}", label, fe(&c)));
+        }
+        lines
+    }
+
+    #[test]
+    fn test_poseidon_transcript_vectors() {
+        use ark_bn254::{Bn254, G2Projective};
+        use ark_ec::pairing::Pairing;
+
+        // limbs of two 128 bits per coordinate
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        let pairing = Bn254::pairing(g1, g2).0;
+        assert_eq!(PoseidonTranscript::<Fr>::encode(&Fr::from(1u64)).len(), 2);
+        assert_eq!(PoseidonTranscript::<Fr>::encode(&g1).len(), 4);
+        assert_eq!(PoseidonTranscript::<Fr>::encode(&g2).len(), 8);
+        assert_eq!(PoseidonTranscript::<Fr>::encode(&pairing).len(), 24);
+        assert_eq!(
+            PoseidonTranscript::<Fr>::encode(&g1),
+            vec![
+                Fr::from(1u64),
+                Fr::from(0u64),
+                Fr::from(2u64),
+                Fr::from(0u64)
+            ]
+        );
+
+        let vectors = include_str!("../tests/vectors/poseidon_transcript.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(poseidon_vectors(), vectors);
+    }
+
+    /// Replays the operations of the test vectors on a Sha256Transcript and
+    /// returns them in the format of the file, with the state after each
+    /// step.
+    fn sha256_vectors() -> Vec<String> {
+        use ark_bn254::{Bn254, G2Projective};
+        use ark_ec::pairing::Pairing;
+
+        let mut transcript = Sha256Transcript::new(b"snarkpack-test-vectors");
+        let mut lines = vec![format!(
+            "new snarkpack-test-vectors {}",
+            hex(&transcript.state())
+        )];
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        let pairing = Bn254::pairing(g1, g2).0;
+        let inputs = vec![Fr::from(1u64), Fr::from(2u64)];
+        macro_rules! append {
+            ($label:expr, $element:expr) => {
+                transcript.append($label, $element);
+                lines.push(format!(
+                    "append {} {} {}",
+                    ark_std::str::from_utf8($label).unwrap(),
+                    hex(&Sha256Transcript::encode($element)),
+                    hex(&transcript.state())
+                ));
+            };
+        }
+        append!(b"nproofs", &8u32);
+        append!(b"g1", &g1);
+        append!(b"g2", &g2);
+        append!(b"scalar", &Fr::from(42u64));
+        append!(b"gt", &pairing);
+        append!(b"inputs", &inputs);
+        for label in [b"r", b"x"] {
+            let c = transcript.challenge_scalar::<Fr>(label);
+            lines.push(format!(
+                "challenge {} {} {} {}",
+                ark_std::str::from_utf8(label).unwrap(),
+                hex(&transcript.state()),
+                hex(&Sha256Transcript::wide_bytes(&transcript.state(), 0)),
+                hex(&Sha256Transcript::encode(&c))
+            ));
+        }
+        lines
+    }
+
+    #[test]
+    fn test_sha256_transcript_vectors() {
+        let vectors = include_str!("../tests/vectors/sha256_transcript.txt")
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(sha256_vectors(), vectors);
+
+        // the state can be serialized and resumed
+        let mut t1 = Sha256Transcript::new(b"test");
+        t1.append(b"point", &G1Projective::generator());
+        let mut bytes = Vec::new();
+        t1.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes, t1.state().to_vec());
+        let mut t2 = Sha256Transcript::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(t2, Sha256Transcript::from_state(t1.state()));
+        assert_eq!(
+            t1.challenge_scalar::<Fr>(b"c"),
+            t2.challenge_scalar::<Fr>(b"c")
+        );
+    }
+
+    #[test]
+    fn test_diff_recordings() {
+        let g = G1Projective::generator();
+        let record = |points: &[(&'static [u8], G1Projective)]| {
+            let mut t = RecordingTranscript::new(new_merlin_transcript(b"test"));
+            t.domain_sep();
+            for (label, p) in points {
+                t.append(label, p);
+            }
+            let c = t.challenge_scalar::<Fr>(b"c");
+            let (_, events) = t.into_parts();
+            (c, events)
+        };
+        let (c1, prover) = record(&[(b"a", g), (b"b", g.double())]);
+        let (c2, verifier) = record(&[(b"a", g), (b"b", g.double())]);
+        assert_eq!(c1, c2);
+        assert_eq!(prover.len(), 4);
+        assert_eq!(diff_recordings(&prover, &verifier), None);
+
+        // elements appended in another order
+        let (_, verifier) = record(&[(b"a", g.double()), (b"b", g)]);
+        let diff = diff_recordings(&prover, &verifier).unwrap();
+        assert_eq!(diff.index, 1);
+        assert_eq!(diff.prover, Some(prover[1].clone()));
+        assert_eq!(diff.verifier, Some(verifier[1].clone()));
+
+        // an extra append on the verifier side
+        let (_, verifier) = record(&[(b"a", g), (b"b", g.double()), (b"c", g)]);
+        let diff = diff_recordings(&prover, &verifier).unwrap();
+        assert_eq!(diff.index, 3);
+        assert!(matches!(
+            diff.prover,
+            Some(TranscriptEvent::Challenge { .. })
+        ));
+        let diff = diff_recordings(&prover[..3], &verifier[..4]).unwrap();
+        assert_eq!(diff.prover, None);
+        assert!(diff.to_string().contains("<end of recording>"));
+    }
 }
@@ -1,29 +1,63 @@
 use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
-use ark_ff::{Field, PrimeField};
+use ark_ff::{CyclotomicMultSubgroup, Field, PrimeField};
 use ark_groth16::PreparedVerifyingKey;
-use ark_std::{rand::Rng, sync::Mutex, One, Zero};
-use crossbeam_channel::{bounded, Sender};
+use ark_std::ops::{AddAssign, Mul, MulAssign, Neg, SubAssign};
+use ark_std::{
+    cfg_into_iter, cfg_iter, end_timer, rand::Rng, start_timer, string::ToString, vec::Vec, One,
+    Zero,
+};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::ops::{AddAssign, Mul, MulAssign, Neg, SubAssign};
 
 use super::{
     commitment::Output,
-    ip,
-    pairing_check::PairingCheck,
-    proof::{AggregateProof, KZGOpening},
-    prover::polynomial_evaluation_product_form_from_transcript,
+    inputs::PublicInputs,
+    ip, join,
+    pairing_check::{rand_fr, PairingCheck},
+    policy::VerificationPolicy,
+    prepared::PreparedAggregateVerifier,
+    proof::{AggregateProof, InputsOpening, KZGOpening, MippExtension},
+    prover::{kzg_challenge, polynomial_evaluation_product_form_from_transcript},
     srs::VerifierSRS,
+    statement::{bind_statement, check_public_inputs_shape},
     structured_scalar_power,
+    succinct::inputs_challenge,
+    sync::Mutex,
     transcript::Transcript,
 };
 use crate::Error;
 
-use std::default::Default;
-use std::time::Instant;
-
 /// Verifies the aggregated proofs thanks to the Groth16 verifying key, the
 /// verifier SRS from the aggregation scheme, all the public inputs of the
 /// proofs and the aggregated proof.
+/// The whole statement, namely the Groth16 verifying key, the verifier SRS,
+/// the number of proofs and ALL public inputs of ALL proofs, is bound in the
+/// transcript before verifying, as done by `aggregate_proofs` on the prover
+/// side.
+/// The proof is expected to have been validated, as `AggregateProof::read`
+/// and `AggregateProof::read_validated` do, or checked with
+/// `AggregateProof::validate` when it was not deserialized by them.
+pub fn verify_aggregate_proof<
+    E: Pairing + ark_std::fmt::Debug,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    bind_statement(transcript, &pvk.vk, ip_verifier_srs, public_inputs)?;
+    verify_aggregate_proof_unchecked(ip_verifier_srs, pvk, public_inputs, proof, rng, transcript)
+}
+
+/// Verifies the aggregated proofs thanks to the Groth16 verifying key, the
+/// verifier SRS from the aggregation scheme, all the public inputs of the
+/// proofs and the aggregated proof, without binding any statement in the
+/// transcript.
 ///
 /// WARNING: transcript_include represents everything that should be included in
 /// the transcript from outside the boundary of this function. This is especially
@@ -35,72 +69,755 @@ use std::time::Instant;
 /// number of proofs and public inputs (+100ms in our case). In the case of Filecoin, the only
 /// non-fixed part of the public inputs are the challenges derived from a seed. Even though this
 /// seed comes from a random beeacon, we are hashing this as a safety precaution.
-pub fn verify_aggregate_proof<E: Pairing + std::fmt::Debug, R: Rng + Send, T: Transcript + Send>(
+pub fn verify_aggregate_proof_unchecked<
+    E: Pairing + ark_std::fmt::Debug,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
     ip_verifier_srs: &VerifierSRS<E>,
     pvk: &PreparedVerifyingKey<E>,
-    public_inputs: &[Vec<<E as Pairing>::ScalarField>],
+    public_inputs: &I,
     proof: &AggregateProof<E>,
     rng: R,
-    mut transcript: &mut T,
+    transcript: &mut T,
 ) -> Result<(), Error> {
-    dbg!("verify_aggregate_proof");
-    proof.parsing_check()?;
-    for pub_input in public_inputs {
-        if (pub_input.len() + 1) != pvk.vk.gamma_abc_g1.len() {
-            return Err(Error::MalformedVerifyingKey);
+    verify_aggregate_proof_with_extensions(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        public_inputs,
+        proof,
+        &Extensions::default(),
+        rng,
+        transcript,
+    )
+}
+
+/// Extra G1 vector proven with MIPP alongside C in the GIPA loop of an
+/// aggregate proof: its commitment, its aggregation with the powers of $r$
+/// and the GIPA elements.
+pub(crate) struct Extension<'a, E: Pairing> {
+    pub com: &'a Output<<E as Pairing>::TargetField>,
+    pub agg: &'a E::G1Affine,
+    pub mipp: &'a MippExtension<E>,
+}
+
+/// Everything an extended aggregate proof adds to the verification of a
+/// regular aggregate proof:
+/// * the extra vectors proven with MIPP
+/// * a point of each proof added to its public inputs term of the Groth16
+///   equation, combined with the powers of $r$ by the verifier itself
+/// * equations of the form $\prod e(A_i, B_i) = 1$ randomized and merged with
+///   the other checks
+/// * the evaluations of the columns of public inputs replacing the public
+///   inputs themselves
+pub(crate) struct Extensions<'a, E: Pairing> {
+    pub mipps: Vec<Extension<'a, E>>,
+    pub ic: Option<&'a [E::G1Affine]>,
+    pub equations: Vec<Vec<(EquationPoint<E>, E::G2Affine)>>,
+    pub inputs: Option<SuccinctInputs<'a, E>>,
+}
+
+/// G1 element of an extra equation: either a point given by the proof or the
+/// combination with the powers of $r$ of the points of `Extensions::ic`.
+pub(crate) enum EquationPoint<E: Pairing> {
+    Point(E::G1Affine),
+    Ic,
+}
+
+/// Commitments to the columns of public inputs and the proof of their
+/// evaluations at $r$, which replace the public inputs in the verification of
+/// a succinct aggregate proof.
+pub(crate) struct SuccinctInputs<'a, E: Pairing> {
+    pub commitments: &'a [E::G1Affine],
+    pub opening: &'a InputsOpening<E>,
+}
+
+impl<'a, E: Pairing> Default for Extensions<'a, E> {
+    fn default() -> Self {
+        Self {
+            mipps: Vec::new(),
+            ic: None,
+            equations: Vec::new(),
+            inputs: None,
         }
     }
+}
 
-    if public_inputs.len() != proof.tmipp.gipa.nproofs as usize {
-        return Err(Error::InvalidProof(
-            "public inputs len != number of proofs".to_string(),
-        ));
+/// Keys of the verifier, along with their precomputations when verifying with
+/// a `PreparedAggregateVerifier`.
+pub(crate) struct VerifierKeys<'a, E: Pairing> {
+    pub srs: &'a VerifierSRS<E>,
+    pub pvk: &'a PreparedVerifyingKey<E>,
+    pub prepared: Option<&'a PreparedAggregateVerifier<E>>,
+}
+
+impl<'a, E: Pairing> VerifierKeys<'a, E> {
+    pub fn new(srs: &'a VerifierSRS<E>, pvk: &'a PreparedVerifyingKey<E>) -> Self {
+        Self {
+            srs,
+            pvk,
+            prepared: None,
+        }
+    }
+
+    pub fn prepared(prepared: &'a PreparedAggregateVerifier<E>) -> Self {
+        Self {
+            srs: &prepared.srs,
+            pvk: &prepared.pvk,
+            prepared: Some(prepared),
+        }
+    }
+
+    fn mul_g(&self, x: &E::ScalarField) -> E::G1 {
+        match self.prepared {
+            Some(p) => p.g.mul(x),
+            None => mul!(self.srs.g, *x),
+        }
+    }
+
+    fn mul_h(&self, x: &E::ScalarField) -> E::G2 {
+        match self.prepared {
+            Some(p) => p.h.mul(x),
+            None => mul!(self.srs.h, *x),
+        }
+    }
+
+    fn neg_h(&self) -> E::G2Prepared {
+        match self.prepared {
+            Some(p) => p.neg_h.clone(),
+            None => self.srs.h.neg().into_affine().into(),
+        }
+    }
+
+    /// Returns $\sum_{i=0}^l S_i \cdot s_i$ where the $S_i$ are the elements
+    /// of the verifying key for the public inputs.
+    fn ic(&self, scalars: &[E::ScalarField]) -> E::G1 {
+        match self.prepared {
+            Some(p) => cfg_iter!(p.gamma_abc_g1)
+                .zip(cfg_iter!(scalars))
+                .map(|(table, s)| table.mul(s))
+                .sum(),
+            None => <E::G1 as VariableBaseMSM>::msm(&self.pvk.vk.gamma_abc_g1, scalars).unwrap(),
+        }
+    }
+}
+
+/// Verifies the aggregated proofs as `verify_aggregate_proof_unchecked` does
+/// and the extensions on top of it, merging all pairing checks together.
+pub(crate) fn verify_aggregate_proof_with_extensions<
+    E: Pairing,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    keys: &VerifierKeys<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    ext: &Extensions<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    let mut_rng = Mutex::new(rng);
+    let checks = aggregate_proof_checks(
+        keys,
+        public_inputs,
+        proof,
+        ext,
+        CheckOptions::default(),
+        &|_| rand_fr::<E, R>(&mut_rng),
+        transcript,
+    )?;
+    let res = merge_checks(&checks).verify();
+    dbg!(format!("aggregate verify done: valid ? {}", res));
+    match res {
+        true => Ok(()),
+        false => Err(Error::InvalidProof("Proof Verification Failed".to_string())),
+    }
+}
+
+/// Verifies the aggregated proof as `verify_aggregate_proof` does, after
+/// checking the statement and the proof against the policy. The proof is
+/// rejected as soon as a check fails and no pairing is computed until the
+/// checks that don't require any, including the relations $Z = C^r$ of MIPP,
+/// passed.
+pub fn verify_aggregate_proof_with_policy<
+    E: Pairing + ark_std::fmt::Debug,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    policy: &VerificationPolicy,
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    verify_with_policy(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        policy,
+        public_inputs,
+        proof,
+        rng,
+        transcript,
+    )
+}
+
+/// Verifies the aggregated proof with the given keys as
+/// `verify_aggregate_proof_with_policy` does.
+pub(crate) fn verify_with_policy<
+    E: Pairing,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    keys: &VerifierKeys<E>,
+    policy: &VerificationPolicy,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    policy.check(keys.srs, &keys.pvk.vk, public_inputs, proof)?;
+    bind_statement(transcript, &keys.pvk.vk, keys.srs, public_inputs)?;
+    let mut_rng = Mutex::new(rng);
+    let checks = aggregate_proof_checks(
+        keys,
+        public_inputs,
+        proof,
+        &Extensions::default(),
+        CheckOptions {
+            fail_fast: true,
+            ..Default::default()
+        },
+        &|_| rand_fr::<E, R>(&mut_rng),
+        transcript,
+    )?;
+    match merge_checks(&checks).verify() {
+        true => Ok(()),
+        false => Err(Error::InvalidProof("Proof Verification Failed".to_string())),
+    }
+}
+
+/// An aggregate proof to verify in a batch with `verify_aggregate_proofs_batch`
+/// along with its statement and its transcript. The statement is bound in the
+/// transcript as in `verify_aggregate_proof`.
+pub struct BatchItem<'a, E: Pairing, T: Transcript> {
+    pub srs: &'a VerifierSRS<E>,
+    pub pvk: &'a PreparedVerifyingKey<E>,
+    pub public_inputs: &'a [Vec<<E as Pairing>::ScalarField>],
+    pub proof: &'a AggregateProof<E>,
+    pub transcript: T,
+}
+
+/// Verifies many aggregated proofs at once. Proofs may come from different
+/// verifying keys and SRS and aggregate different numbers of proofs. All the
+/// pairing checks of all proofs, including their Groth16 equations which are
+/// randomized as well, are merged into a single check requiring one final
+/// exponentiation only.
+/// It returns an error if any of the proofs is invalid, without telling which
+/// one.
+pub fn verify_aggregate_proofs_batch<E: Pairing, R: Rng + Send, T: Transcript + Send>(
+    items: Vec<BatchItem<E, T>>,
+    rng: R,
+) -> Result<(), Error> {
+    let mut_rng = Mutex::new(rng);
+    let mut acc = PairingCheck::new();
+    for mut item in items {
+        bind_statement(
+            &mut item.transcript,
+            &item.pvk.vk,
+            item.srs,
+            item.public_inputs,
+        )?;
+        let checks = aggregate_proof_checks(
+            &VerifierKeys::new(item.srs, item.pvk),
+            item.public_inputs,
+            item.proof,
+            &Extensions::default(),
+            CheckOptions {
+                randomize_groth16: true,
+                ..Default::default()
+            },
+            &|_| rand_fr::<E, R>(&mut_rng),
+            &mut item.transcript,
+        )?;
+        acc.merge(&merge_checks(&checks));
+    }
+    match acc.verify() {
+        true => Ok(()),
+        false => Err(Error::InvalidProof("Proof Verification Failed".to_string())),
+    }
+}
+
+/// Returns the pending pairing check of the aggregated proof verified as
+/// `verify_aggregate_proof` does, without performing the final
+/// exponentiation. All its equations, including the Groth16 one, are
+/// randomized so other checks of the application, e.g. signatures or other
+/// proofs, can be merged into it with `PairingCheck::merge` to verify them all
+/// with a single `PairingCheck::verify`.
+pub fn aggregate_proof_pairing_check<
+    E: Pairing,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<PairingCheck<E>, Error> {
+    pending_check(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        None,
+        public_inputs,
+        proof,
+        rng,
+        transcript,
+    )
+}
+
+/// Returns the pending pairing check of the aggregated proof with the given
+/// keys as `aggregate_proof_pairing_check` does, after checking the proof
+/// against the policy if any.
+pub(crate) fn pending_check<
+    E: Pairing,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    keys: &VerifierKeys<E>,
+    policy: Option<&VerificationPolicy>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<PairingCheck<E>, Error> {
+    if let Some(policy) = policy {
+        policy.check(keys.srs, &keys.pvk.vk, public_inputs, proof)?;
+    }
+    bind_statement(transcript, &keys.pvk.vk, keys.srs, public_inputs)?;
+    let mut_rng = Mutex::new(rng);
+    let checks = aggregate_proof_checks(
+        keys,
+        public_inputs,
+        proof,
+        &Extensions::default(),
+        CheckOptions {
+            randomize_groth16: true,
+            fail_fast: policy.is_some(),
+        },
+        &|_| rand_fr::<E, R>(&mut_rng),
+        transcript,
+    )?;
+    Ok(merge_checks(&checks))
+}
+
+/// Relation checked by the verifier of an aggregated proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Relation {
+    /// $Z_{AB} = e(A, B)$ on the final values of TIPP
+    TippZ,
+    /// $T_{AB} = e(A, v_1) e(w_1, B)$ on the final values of TIPP
+    TippT,
+    /// $U_{AB} = e(A, v_2) e(w_2, B)$ on the final values of TIPP
+    TippU,
+    /// $T_C = e(C, v_1)$ on the final values of MIPP
+    MippT,
+    /// $U_C = e(C, v_2)$ on the final values of MIPP
+    MippU,
+    /// $Z_C = C^r$ on the final values of MIPP
+    MippZ,
+    /// KZG opening of the first final commitment key $v_1$
+    KzgV1,
+    /// KZG opening of the second final commitment key $v_2$
+    KzgV2,
+    /// KZG opening of the first final commitment key $w_1$
+    KzgW1,
+    /// KZG opening of the second final commitment key $w_2$
+    KzgW2,
+    /// the aggregated Groth16 equation
+    Groth16,
+    /// $T$ on the final values of the MIPP of the i-th extra vector
+    ExtensionMippT(usize),
+    /// $U$ on the final values of the MIPP of the i-th extra vector
+    ExtensionMippU(usize),
+    /// $Z$ on the final values of the MIPP of the i-th extra vector
+    ExtensionMippZ(usize),
+    /// the i-th extra equation of an extended aggregate proof
+    ExtensionEquation(usize),
+    /// KZG opening of the columns of public inputs of a succinct aggregate
+    /// proof
+    InputsOpening,
+}
+
+impl Relation {
+    /// Relations of an aggregated proof whose pairing check is randomized
+    /// by `verify_aggregate_proof`, in order.
+    pub const RANDOMIZED: [Relation; 9] = [
+        Relation::TippZ,
+        Relation::TippT,
+        Relation::TippU,
+        Relation::MippT,
+        Relation::MippU,
+        Relation::KzgV1,
+        Relation::KzgV2,
+        Relation::KzgW1,
+        Relation::KzgW2,
+    ];
+
+    /// Returns a unique code of the relation to feed a transcript with.
+    fn code(&self) -> u64 {
+        let (tag, index) = match self {
+            Relation::TippZ => (0, 0),
+            Relation::TippT => (1, 0),
+            Relation::TippU => (2, 0),
+            Relation::MippT => (3, 0),
+            Relation::MippU => (4, 0),
+            Relation::MippZ => (5, 0),
+            Relation::KzgV1 => (6, 0),
+            Relation::KzgV2 => (7, 0),
+            Relation::KzgW1 => (8, 0),
+            Relation::KzgW2 => (9, 0),
+            Relation::Groth16 => (10, 0),
+            Relation::ExtensionMippT(i) => (11, *i),
+            Relation::ExtensionMippU(i) => (12, *i),
+            Relation::ExtensionMippZ(i) => (13, *i),
+            Relation::ExtensionEquation(i) => (14, *i),
+            Relation::InputsOpening => (15, 0),
+        };
+        (tag << 32) | index as u64
+    }
+}
+
+/// VerificationReport tells which of the relations checked by the verifier
+/// of an aggregated proof hold.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// every relation checked along with its validity, in the order of
+    /// `Relation`
+    pub relations: Vec<(Relation, bool)>,
+}
+
+impl VerificationReport {
+    /// Returns true if all relations hold, i.e. the proof is valid.
+    pub fn is_valid(&self) -> bool {
+        self.relations.iter().all(|(_, valid)| *valid)
     }
 
+    /// Returns the relations that do not hold.
+    pub fn failures(&self) -> Vec<Relation> {
+        self.relations
+            .iter()
+            .filter(|(_, valid)| !valid)
+            .map(|(relation, _)| *relation)
+            .collect()
+    }
+}
+
+/// Verifies the aggregated proof like `verify_aggregate_proof` but checks
+/// each relation separately, with one final exponentiation per relation,
+/// and reports which ones hold. It is much slower than
+/// `verify_aggregate_proof` and meant to investigate an invalid proof.
+/// Errors are only returned when the proof or the statement are malformed,
+/// before any relation can be checked.
+pub fn verify_aggregate_proof_with_report<
+    E: Pairing + ark_std::fmt::Debug,
+    R: Rng + Send,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<VerificationReport, Error> {
+    bind_statement(transcript, &pvk.vk, ip_verifier_srs, public_inputs)?;
     let mut_rng = Mutex::new(rng);
+    let checks = aggregate_proof_checks(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        public_inputs,
+        proof,
+        &Extensions::default(),
+        CheckOptions::default(),
+        &|_| rand_fr::<E, R>(&mut_rng),
+        transcript,
+    )?;
+    let mut relations = cfg_iter!(checks)
+        .map(|(relation, check)| (*relation, check.verify()))
+        .collect::<Vec<_>>();
+    relations.sort();
+    Ok(VerificationReport { relations })
+}
+
+/// Verifies the aggregated proof like `verify_aggregate_proof` without any
+/// randomness: the coefficients randomizing the pairing checks are derived
+/// from a fork of the transcript fed with the whole statement and the whole
+/// proof, one coefficient per relation. Two verifiers given the same inputs
+/// thus perform exactly the same computation, whatever the scheduling of the
+/// parallel tasks.
+/// On success, it returns the coefficients used, in the order of `Relation`.
+pub fn verify_aggregate_proof_deterministic<
+    E: Pairing + ark_std::fmt::Debug,
+    T: Transcript + Clone + Send + Sync,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    transcript: &mut T,
+) -> Result<Vec<(Relation, E::ScalarField)>, Error> {
+    let (check, used) =
+        deterministic_check(ip_verifier_srs, pvk, public_inputs, proof, transcript)?;
+    if !check.verify() {
+        return Err(Error::InvalidProof("Proof Verification Failed".to_string()));
+    }
+    Ok(used)
+}
+
+/// Returns the merged pairing check of `verify_aggregate_proof_deterministic`
+/// along with the coefficients used, in the order of `Relation`.
+fn deterministic_check<
+    E: Pairing,
+    T: Transcript + Clone + Send + Sync,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    transcript: &mut T,
+) -> Result<(PairingCheck<E>, RelationCoefficients<E>), Error> {
+    bind_statement(transcript, &pvk.vk, ip_verifier_srs, public_inputs)?;
+    let fork = coefficients_transcript(transcript, proof);
+    let used = Mutex::new(Vec::new());
+    let coeffs = |relation: Relation| {
+        let coeff = derive_coefficient::<E, T>(&fork, relation);
+        used.lock().unwrap().push((relation, coeff));
+        coeff
+    };
+    let checks = aggregate_proof_checks(
+        &VerifierKeys::new(ip_verifier_srs, pvk),
+        public_inputs,
+        proof,
+        &Extensions::default(),
+        CheckOptions::default(),
+        &coeffs,
+        transcript,
+    )?;
+    let mut used = used.into_inner().unwrap();
+    used.sort_by_key(|(relation, _)| *relation);
+    Ok((merge_checks(&checks), used))
+}
+
+/// Returns the coefficients `verify_aggregate_proof_deterministic` uses for the
+/// given statement and proof, in the order of `Relation::RANDOMIZED`, without
+/// verifying the proof. The transcript must be in the same state as the one
+/// given to the verifier.
+pub fn deterministic_coefficients<
+    E: Pairing,
+    T: Transcript + Clone,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    ip_verifier_srs: &VerifierSRS<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    transcript: &mut T,
+) -> Result<Vec<(Relation, E::ScalarField)>, Error> {
+    bind_statement(transcript, &pvk.vk, ip_verifier_srs, public_inputs)?;
+    let fork = coefficients_transcript(transcript, proof);
+    Ok(Relation::RANDOMIZED
+        .iter()
+        .map(|relation| (*relation, derive_coefficient::<E, T>(&fork, *relation)))
+        .collect())
+}
+
+/// Coefficients of the relations, in the order of `Relation`.
+type RelationCoefficients<E> = Vec<(Relation, <E as Pairing>::ScalarField)>;
+
+/// Forks the transcript, in which the statement is already bound, and feeds
+/// the fork with the whole proof.
+fn coefficients_transcript<E: Pairing, T: Transcript + Clone>(
+    transcript: &T,
+    proof: &AggregateProof<E>,
+) -> T {
+    let mut fork = transcript.clone();
+    fork.append(b"batching-coefficients-proof", proof);
+    fork
+}
+
+/// Derives the coefficient of the relation from the fork of the transcript.
+fn derive_coefficient<E: Pairing, T: Transcript + Clone>(
+    fork: &T,
+    relation: Relation,
+) -> E::ScalarField {
+    let mut fork = fork.clone();
+    fork.append(b"relation", &relation.code());
+    fork.challenge_scalar::<E::ScalarField>(b"batching-coefficient")
+}
+
+/// Coefficients returns the coefficient randomizing the pairing check of a
+/// relation before all checks are merged.
+pub type Coefficients<'a, E> = &'a (dyn Fn(Relation) -> <E as Pairing>::ScalarField + Sync);
+
+/// Merges the checks of the relations into one. The checks are merged in the
+/// order of the relations, not in the order the parallel tasks sent them.
+fn merge_checks<E: Pairing>(checks: &[(Relation, PairingCheck<E>)]) -> PairingCheck<E> {
+    let mut sorted = checks.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|(relation, _)| relation.code());
+    let mut acc = PairingCheck::new();
+    for (_, check) in sorted {
+        acc.merge(check);
+    }
+    acc
+}
+
+/// Checks collects the pairing checks of the relations sent by the tasks of
+/// the verifier.
+#[derive(Clone)]
+pub(crate) struct Checks<'a, E: Pairing>(&'a Mutex<Vec<(Relation, PairingCheck<E>)>>);
+
+impl<E: Pairing> Checks<'_, E> {
+    pub fn send(&self, check: (Relation, PairingCheck<E>)) {
+        self.0.lock().unwrap().push(check);
+    }
+}
+
+/// Options of the computation of the pairing checks of an aggregated proof.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct CheckOptions {
+    /// randomizes the Groth16 equation so the checks can be merged with the
+    /// ones of other aggregated proofs; otherwise it is the single non
+    /// randomized check.
+    pub randomize_groth16: bool,
+    /// checks the relations requiring no pairing first and returns an error
+    /// as soon as one of them doesn't hold, before computing any pairing.
+    /// Otherwise they are checked in parallel with the pairings.
+    pub fail_fast: bool,
+}
+
+/// Returns the pairing checks of all relations of the aggregated proof and its
+/// extensions, without performing any final exponentiation, as set by the
+/// options.
+fn aggregate_proof_checks<
+    E: Pairing,
+    T: Transcript + Send,
+    I: PublicInputs<E::ScalarField> + ?Sized,
+>(
+    keys: &VerifierKeys<E>,
+    public_inputs: &I,
+    proof: &AggregateProof<E>,
+    ext: &Extensions<E>,
+    options: CheckOptions,
+    coeffs: Coefficients<E>,
+    transcript: &mut T,
+) -> Result<Vec<(Relation, PairingCheck<E>)>, Error> {
+    dbg!("verify_aggregate_proof");
+    let pvk = keys.pvk;
+    proof.parsing_check()?;
+    let ref_len = proof.tmipp.gipa.comms_c.len();
+    if ext
+        .mipps
+        .iter()
+        .any(|x| x.mipp.comms.len() != ref_len || x.mipp.z.len() != ref_len)
+    {
+        return Err(Error::InvalidProof(
+            "Proof extension vectors unequal sizes".to_string(),
+        ));
+    }
+    let nproofs = proof.tmipp.gipa.nproofs as usize;
+    if ext.ic.is_some_and(|points| points.len() != nproofs) {
+        return Err(Error::InvalidProof(
+            "points len != number of proofs".to_string(),
+        ));
+    }
+    match &ext.inputs {
+        Some(inputs) => {
+            if inputs.opening.evaluations.len() + 1 != pvk.vk.gamma_abc_g1.len()
+                || inputs.commitments.len() != inputs.opening.evaluations.len()
+            {
+                return Err(Error::MalformedVerifyingKey);
+            }
+        }
+        None => {
+            check_public_inputs_shape(&pvk.vk, public_inputs)?;
+            if public_inputs.num_proofs() != nproofs {
+                return Err(Error::InvalidProof(
+                    "public inputs len != number of proofs".to_string(),
+                ));
+            }
+        }
+    }
 
     // Random linear combination of proofs
     transcript.append(b"AB-commitment", &proof.com_ab);
     transcript.append(b"C-commitment", &proof.com_c);
+    for x in &ext.mipps {
+        transcript.append(b"extension-commitment", x.com);
+    }
     let r = transcript.challenge_scalar::<<E as Pairing>::ScalarField>(b"r-random-fiatshamir");
+    let inputs_gamma = ext
+        .inputs
+        .as_ref()
+        .map(|inputs| inputs_challenge(transcript, &inputs.opening.evaluations));
+    // the points of each proof added to their public inputs term, combined
+    // with the powers of r
+    let ic_r = ext
+        .ic
+        .map(|points| {
+            ip::multiexponentiation::<E::G1Affine>(points, &structured_scalar_power(nproofs, &r))
+        })
+        .transpose()?
+        .map(|p| p.into_affine());
 
-    // channels to send/recv pairing checks so we aggregate them all in a
-    // loop - 9 places where we send pairing checks
-    let (send_checks, rcv_checks) = bounded(9);
-    // channel to receive the final results so aggregate waits on all.
-    let (valid_send, valid_rcv) = bounded(1);
-    rayon::scope(move |s| {
-        // Continuous loop that aggregate pairing checks together
-        s.spawn(move |_| {
-            let mut acc = PairingCheck::new();
-            while let Ok(tuple) = rcv_checks.recv() {
-                acc.merge(&tuple);
+    // the relations requiring no pairing are checked upfront when failing
+    // fast, before spawning any pairing computation
+    let tipp_mipp = match options.fail_fast {
+        true => {
+            let tipp_mipp = tipp_mipp_final(proof, &ext.mipps, &r, transcript);
+            if let Some((relation, _)) = tipp_mipp.z_checks.iter().find(|(_, valid)| !valid) {
+                return Err(Error::InvalidProof(format!("{:?} check failed", relation)));
             }
-            valid_send.send(acc.verify()).unwrap();
-        });
+            Some(tipp_mipp)
+        }
+        false => None,
+    };
 
-        // 1.Check TIPA proof ab
-        // 2.Check TIPA proof c
-        let checkclone = send_checks.clone();
-        s.spawn(move |_| {
-            let now = Instant::now();
-            verify_tipp_mipp::<E, R, T>(
-                ip_verifier_srs,
-                proof,
-                &r, // we give the extra r as it's not part of the proof itself - it is simply used on top for the groth16 aggregation
-                &mut transcript,
-                &mut_rng,
-                checkclone,
-            );
-            dbg!("TIPP took {} ms", now.elapsed().as_millis(),);
-        });
+    // pairing checks sent by all tasks so we aggregate them all at the end -
+    // 11 places where we send pairing checks, 3 more per extension, one per
+    // extra equation and one for the columns of public inputs
+    let all_checks = Mutex::new(Vec::with_capacity(
+        12 + 3 * ext.mipps.len() + ext.equations.len(),
+    ));
+    let send_checks = Checks(&all_checks);
 
+    // 1.Check TIPA proof ab
+    // 2.Check TIPA proof c
+    let checkclone = send_checks.clone();
+    let tipp_mipp_checks = move || {
+        let timer = start_timer!(|| "TIPP");
+        // we give the extra r as it's not part of the proof itself - it is
+        // simply used on top for the groth16 aggregation
+        let tipp_mipp =
+            tipp_mipp.unwrap_or_else(|| tipp_mipp_final(proof, &ext.mipps, &r, transcript));
+        verify_tipp_mipp::<E>(keys, proof, &ext.mipps, &r, tipp_mipp, coeffs, checkclone);
+        end_timer!(timer);
+    };
+
+    let groth16_checks = move || {
         // Check aggregate pairing product equation
         // SUM of a geometric progression
         // SUM a^i = (1 - a^n) / (1 - a) = -(1-a^n)/-(1-a)
         // = (a^n - 1) / (a - 1)
         dbg!("checking aggregate pairing");
-        let mut r_sum = r.pow(&[public_inputs.len() as u64]);
+        let mut r_sum = r.pow([nproofs as u64]);
         r_sum.sub_assign(&<E as Pairing>::ScalarField::one());
         let b = sub!(r, &<E as Pairing>::ScalarField::one())
             .inverse()
@@ -112,119 +829,286 @@ pub fn verify_aggregate_proof<E: Pairing + std::fmt::Debug, R: Rng + Send, T: Tr
         // only checking *one* pairing check (the Groth16 verification equation)
         // so we don't need to randomize as all other checks are being
         // randomized already. When merging all pairing checks together, this
-        // will be the only one non-randomized.
+        // will be the only one non-randomized, unless the check is to be
+        // merged with the ones of other aggregated proofs.
         //
-        let (r_vec_sender, r_vec_receiver) = bounded(1);
-        //        s.spawn(move |_| {
-        let now = Instant::now();
-        r_vec_sender
-            .send(structured_scalar_power(public_inputs.len(), &r))
-            .unwrap();
-        let elapsed = now.elapsed().as_millis();
-        dbg!("generation of r vector: {}ms", elapsed);
-        //        });
+        // 5. compute the middle part of the final pairing equation, the one
+        //    with the public inputs
+        let g_ic = {
+            // We want to compute MUL(i:0 -> l) S_i ^ (SUM(j:0 -> n) ai,j * r^j)
+            // this table keeps tracks of incremental computation of each i-th
+            // exponent to later multiply with S_i
+            // The index of the table is i, which is an index of the public
+            // input element
+            // NOTE: in this version it's not r^2j but simply r^j
+            let timer = start_timer!(|| "table generation");
+            // S_0 is multiplied by the sum of the powers of r
+            let summed = ark_std::iter::once(r_sum)
+                .chain(match &ext.inputs {
+                    // the sums are the evaluations of the columns at r
+                    Some(inputs) => inputs.opening.evaluations.clone(),
+                    None => public_inputs_sums(public_inputs, &r, &r_sum),
+                })
+                .collect::<Vec<_>>();
 
-        par! {
-            // 3. Compute left part of the final pairing equation
-            let left = {
-                let alpha_g1_r_suma = pvk.vk.alpha_g1;
-                let alpha_g1_r_sum = alpha_g1_r_suma.mul(r_sum);
+            // now we do the multi exponentiation
+            let mut g_ic = keys.ic(&summed);
+            if let Some(ic) = ic_r {
+                g_ic.add_assign(&ic);
+            }
+            end_timer!(timer);
 
-                E::miller_loop(E::G1Prepared::from(alpha_g1_r_sum.into()), E::G2Prepared::from(pvk.vk.beta_g2))
+            g_ic.into_affine()
+        };
+        // final value ip_ab is what we want to compare in the groth16
+        // aggregated equation A * B
+        let check = match keys.prepared {
+            // 3. the left part e(alpha^r_sum, beta) = e(alpha, beta)^r_sum is
+            //    derived from the prepared verifying key and moved to the
+            //    right side of the equation
+            Some(prepared) => {
+                let alpha_beta_r_sum = pvk.alpha_g1_beta_g2.cyclotomic_exp((-r_sum).into_bigint());
+                let right = mul!(proof.ip_ab, &alpha_beta_r_sum);
+                let pairs = [
+                    (&g_ic, &prepared.gamma_g2),
+                    (&proof.agg_c, &prepared.delta_g2),
+                ];
+                if options.randomize_groth16 {
+                    PairingCheck::rand_prepared_with_coeff(
+                        coeffs(Relation::Groth16),
+                        &pairs,
+                        &right,
+                    )
+                } else {
+                    let lefts = cfg_iter!(pairs)
+                        .map(|(a, b)| E::miller_loop(E::G1Prepared::from(**a), (*b).clone()).0)
+                        .collect();
+                    PairingCheck::from_products(lefts, right)
+                }
+            }
+            None => {
+                // 3. Compute left part of the final pairing equation
+                let alpha_g1_r_sum = pvk.vk.alpha_g1.mul(r_sum).into_affine();
+                if options.randomize_groth16 {
+                    PairingCheck::rand_with_coeff(
+                        coeffs(Relation::Groth16),
+                        &[
+                            (&alpha_g1_r_sum, &pvk.vk.beta_g2),
+                            (&g_ic, &pvk.vk.gamma_g2),
+                            (&proof.agg_c, &pvk.vk.delta_g2),
+                        ],
+                        &proof.ip_ab,
+                    )
+                } else {
+                    par! {
+                        let left = E::miller_loop(
+                            E::G1Prepared::from(alpha_g1_r_sum),
+                            E::G2Prepared::from(pvk.vk.beta_g2),
+                        ),
+                        let middle = E::miller_loop(
+                            E::G1Prepared::from(g_ic),
+                            E::G2Prepared::from(pvk.vk.gamma_g2),
+                        ),
+                        // 4. Compute right part of the final pairing equation
+                        let right = E::miller_loop(
+                            // e(c^r vector form, h^delta)
+                            E::G1Prepared::from(proof.agg_c),
+                            E::G2Prepared::from(pvk.vk.delta_g2),
+                        )
+                    };
+                    PairingCheck::from_products(vec![left.0, middle.0, right.0], proof.ip_ab)
+                }
+            }
+        };
+        send_checks.send((Relation::Groth16, check));
 
-            },
-            // 4. Compute right part of the final pairing equation
-            let right = E::miller_loop(
-                    // e(c^r vector form, h^delta)
-                    E::G1Prepared::from(proof.agg_c),
-                    E::G2Prepared::from(pvk.vk.delta_g2),
-                ),
-            // 5. compute the middle part of the final pairing equation, the one
-            //    with the public inputs
-            let middle = {
-                    // We want to compute MUL(i:0 -> l) S_i ^ (SUM(j:0 -> n) ai,j * r^j)
-                    // this table keeps tracks of incremental computation of each i-th
-                    // exponent to later multiply with S_i
-                    // The index of the table is i, which is an index of the public
-                    // input element
-                    // We incrementally build the r vector and the table
-                    // NOTE: in this version it's not r^2j but simply r^j
-
-                    let l = public_inputs[0].len();
-                    let mut g_ic:<E as Pairing>::G1 = pvk.vk.gamma_abc_g1[0].into();
-                    g_ic.mul_assign(r_sum);
-
-                    let powers = r_vec_receiver.recv().unwrap();
-
-                    let now = Instant::now();
-                    // now we do the multi exponentiation
-                    let summed = (0..l).into_par_iter().map(|i| {
-                        // i denotes the column of the public input, and j denotes which public input
-                        let mut c = public_inputs[0][i];
-                        for j in 1..public_inputs.len() {
-                            let mut ai = public_inputs[j][i];
-                            ai.mul_assign(&powers[j]);
-                            c.add_assign(&ai);
-                        }
-                        c
-                    }).collect::<Vec<_>>();
+        // opening of the columns of public inputs, randomized
+        if let (Some(inputs), Some(gamma)) = (&ext.inputs, inputs_gamma) {
+            let check =
+                inputs_opening_check(keys, inputs, &r, &gamma, coeffs(Relation::InputsOpening));
+            send_checks.send((Relation::InputsOpening, check));
+        }
+
+        // extra equations of the extensions, randomized
+        for (i, equation) in ext.equations.iter().enumerate() {
+            let points = equation
+                .iter()
+                .map(|(a, _)| match a {
+                    EquationPoint::Point(a) => *a,
+                    EquationPoint::Ic => ic_r.unwrap_or_default(),
+                })
+                .collect::<Vec<_>>();
+            let pairs = points
+                .iter()
+                .zip(equation)
+                .map(|(a, (_, b))| (a, b))
+                .collect::<Vec<_>>();
+            let check = PairingCheck::rand_with_coeff(
+                coeffs(Relation::ExtensionEquation(i)),
+                &pairs,
+                &<E as Pairing>::TargetField::one(),
+            );
+            send_checks.send((Relation::ExtensionEquation(i), check));
+        }
+    };
+    join(tipp_mipp_checks, groth16_checks);
+    Ok(all_checks.into_inner().unwrap())
+}
 
-                    let totsi = <<E as Pairing>::G1 as VariableBaseMSM>::msm(&pvk.vk.gamma_abc_g1[1..],&summed).unwrap();
+/// Returns $\sum_j x_{j,i} r^j$ for each public input $i$.
+fn public_inputs_sums<F: PrimeField, I: PublicInputs<F> + ?Sized>(
+    public_inputs: &I,
+    r: &F,
+    r_sum: &F,
+) -> Vec<F> {
+    let l = public_inputs.num_inputs().unwrap_or(0);
+    // the powers of r are only needed for the inputs that are not the same
+    // for all proofs
+    let timer = start_timer!(|| "generation of r vector");
+    let powers = match (0..l).all(|i| public_inputs.constant(i).is_some()) {
+        true => Vec::new(),
+        false => structured_scalar_power(public_inputs.num_proofs(), r),
+    };
+    end_timer!(timer);
 
-                    g_ic.add_assign(&totsi);
+    cfg_into_iter!(0..l)
+        .map(|i| match public_inputs.constant(i) {
+            // SUM(j:0 -> n) a * r^j = a * r_sum
+            Some(c) => mul!(c, r_sum),
+            // i denotes the column of the public input
+            None => public_inputs.combine_column(i, &powers),
+        })
+        .collect()
+}
 
-                    let ml = E::miller_loop(E::G1Prepared::from(g_ic.into_affine()), E::G2Prepared::from(pvk.vk.gamma_g2.clone()));
-                    let elapsed = now.elapsed().as_millis();
-                    dbg!("table generation: {}ms", elapsed);
+/// Returns the pairing check of the KZG opening at $r$ of the columns of
+/// public inputs, batched with the powers of $\gamma$:
+/// $e(C - y g + r \pi, -h) e(\pi, h^\alpha) = 1$ where $C$ and $y$ are the
+/// combinations of the commitments and of the evaluations.
+fn inputs_opening_check<E: Pairing>(
+    keys: &VerifierKeys<E>,
+    inputs: &SuccinctInputs<E>,
+    r: &E::ScalarField,
+    gamma: &E::ScalarField,
+    coeff: E::ScalarField,
+) -> PairingCheck<E> {
+    let gammas = structured_scalar_power(inputs.commitments.len(), gamma);
+    let c = <E::G1 as VariableBaseMSM>::msm(inputs.commitments, &gammas).unwrap();
+    let y = inputs
+        .opening
+        .evaluations
+        .iter()
+        .zip(&gammas)
+        .map(|(e, g)| *e * g)
+        .sum::<E::ScalarField>();
+    let pi = inputs.opening.opening;
+    let left = (c - keys.mul_g(&y) + pi.mul(*r)).into_affine();
+    let h_alpha = E::G2Prepared::from(keys.srs.h_alpha.into_affine());
+    PairingCheck::rand_prepared_with_coeff(
+        coeff,
+        &[(&left, &keys.neg_h()), (&pi, &h_alpha)],
+        &<E as Pairing>::TargetField::one(),
+    )
+}
 
-                    ml
-            }
-        };
-        // final value ip_ab is what we want to compare in the groth16
-        // aggregated equation A * B
-        let check =
-            PairingCheck::from_products(vec![left.0, middle.0, right.0], proof.ip_ab.clone());
-        send_checks.send(check).unwrap();
-    });
-    let res = valid_rcv.recv().unwrap();
-    dbg!(format!("aggregate verify done: valid ? {}", res));
-    match res {
-        true => Ok(()),
-        false => Err(Error::InvalidProof("Proof Verification Failed".to_string())),
-    }
+/// Values of TIPP and MIPP the verifier derives from the proof and the
+/// transcript before computing any pairing.
+struct TippMippFinal<E: Pairing> {
+    /// (T,U), Z for TIPP and MIPP
+    res: GipaTUZ<E>,
+    challenges: Vec<E::ScalarField>,
+    challenges_inv: Vec<E::ScalarField>,
+    /// (T,U), Z for the MIPP of the extra vectors
+    ext_res: Vec<MippTUZ<E>>,
+    /// KZG challenge point
+    kzg_challenge: E::ScalarField,
+    /// validity of the relations $Z = C^r$ of MIPP, for C and the extra
+    /// vectors, which don't require any pairing
+    z_checks: Vec<(Relation, bool)>,
 }
 
-/// verify_tipp_mipp returns a pairing equation to check the tipp proof.  $r$ is
-/// the randomness used to produce a random linear combination of A and B and
-/// used in the MIPP part with C
-fn verify_tipp_mipp<E: Pairing, R: Rng + Send, T: Transcript + Send>(
-    v_srs: &VerifierSRS<E>,
+/// tipp_mipp_final runs the GIPA verifier, derives the KZG challenge and checks
+/// the final $Z$ values of MIPP, which only requires a scalar multiplication
+/// per vector. $r$ is the randomness used to produce a random linear
+/// combination of A and B and used in the MIPP part with C
+fn tipp_mipp_final<E: Pairing, T: Transcript + Send>(
     proof: &AggregateProof<E>,
+    extensions: &[Extension<E>],
     r_shift: &E::ScalarField,
     transcript: &mut T,
-    rng: &Mutex<R>,
-    checks: Sender<PairingCheck<E>>,
-) {
+) -> TippMippFinal<E> {
     dbg!("verify with srs shift");
-    let now = Instant::now();
+    let timer = start_timer!(|| "TIPP verify: gipa verify tipp");
     // (T,U), Z for TIPP and MIPP  and all challenges
-    let (final_res, final_r, challenges, challenges_inv) =
-        gipa_verify_tipp_mipp(&proof, r_shift, transcript);
-    dbg!(
-        "TIPP verify: gipa verify tipp {}ms",
-        now.elapsed().as_millis()
+    let (res, final_r, challenges, challenges_inv, ext_res) =
+        gipa_verify_tipp_mipp(proof, extensions, r_shift, transcript);
+    end_timer!(timer);
+
+    // KZG challenge point
+    let kzg_challenge = kzg_challenge(
+        transcript,
+        &proof.tmipp.gipa,
+        extensions.iter().map(|x| x.mipp),
+        &challenges[0],
     );
 
+    // MIPP
+    // Verify base inner product commitment
+    // Z ==  c ^ r
+    let final_z = proof.tmipp.gipa.final_c.mul(final_r);
+    if final_z != res.zc {
+        dbg!(format!(
+            "tipp verify: INVALID final_z check {} vs {}",
+            final_z, res.zc
+        ));
+    }
+    let mut z_checks = vec![(Relation::MippZ, final_z == res.zc)];
+    // MIPP for the extra vectors, checked as for C
+    z_checks.extend(
+        extensions
+            .iter()
+            .zip(ext_res.iter())
+            .enumerate()
+            .map(|(i, (x, res))| {
+                let valid = x.mipp.final_x.mul(final_r) == res.z;
+                if !valid {
+                    dbg!("tipp verify: INVALID final_z check for extension");
+                }
+                (Relation::ExtensionMippZ(i), valid)
+            }),
+    );
+    TippMippFinal {
+        res,
+        challenges,
+        challenges_inv,
+        ext_res,
+        kzg_challenge,
+        z_checks,
+    }
+}
+
+/// verify_tipp_mipp sends the pairing checks of the tipp and mipp proofs, given
+/// the final values derived by `tipp_mipp_final`.
+fn verify_tipp_mipp<E: Pairing>(
+    keys: &VerifierKeys<E>,
+    proof: &AggregateProof<E>,
+    extensions: &[Extension<E>],
+    r_shift: &E::ScalarField,
+    tipp_mipp: TippMippFinal<E>,
+    coeffs: Coefficients<E>,
+    checks: Checks<E>,
+) {
+    let TippMippFinal {
+        res: final_res,
+        challenges,
+        challenges_inv,
+        ext_res,
+        kzg_challenge: c,
+        z_checks,
+    } = tipp_mipp;
     // Verify commitment keys wellformed
     let fvkey = proof.tmipp.gipa.final_vkey;
     let fwkey = proof.tmipp.gipa.final_wkey;
-    // KZG challenge point
-    transcript.append(b"kzg-challenge", &challenges[0]);
-    transcript.append(b"vkey0", &proof.tmipp.gipa.final_vkey.0);
-    transcript.append(b"vkey1", &proof.tmipp.gipa.final_vkey.1);
-    transcript.append(b"wkey0", &proof.tmipp.gipa.final_wkey.0);
-    transcript.append(b"wkey1", &proof.tmipp.gipa.final_wkey.1);
-    let c = transcript.challenge_scalar::<E::ScalarField>(b"z-challenge");
     // we take reference so they are able to be copied in the par! macro
     let final_a = &proof.tmipp.gipa.final_a;
     let final_b = &proof.tmipp.gipa.final_b;
@@ -235,7 +1119,7 @@ fn verify_tipp_mipp<E: Pairing, R: Rng + Send, T: Transcript + Send>(
     let final_tc = &final_res.tc;
     let final_uc = &final_res.uc;
 
-    let now = Instant::now();
+    let timer = start_timer!(|| "TIPP verify: parallel checks before merge");
     let vclone = checks.clone();
     let wclone = checks.clone();
     let zclone = checks.clone();
@@ -246,23 +1130,23 @@ fn verify_tipp_mipp<E: Pairing, R: Rng + Send, T: Transcript + Send>(
     par! {
         // check the opening proof for v
         let _vtuple = verify_kzg_v(
-            v_srs,
+            keys,
             &fvkey,
             &proof.tmipp.vkey_opening,
             &challenges_inv,
             &c,
-            rng,
+            coeffs,
             vclone,
         ),
         // check the opening proof for w - note that w has been rescaled by $r^{-1}$
         let _wtuple = verify_kzg_w(
-            v_srs,
+            keys,
             &fwkey,
             &proof.tmipp.wkey_opening,
             &challenges,
             &r_shift.inverse().unwrap(),
             &c,
-            rng,
+            coeffs,
             wclone,
         ),
         //
@@ -272,55 +1156,65 @@ fn verify_tipp_mipp<E: Pairing, R: Rng + Send, T: Transcript + Send>(
         // TIPP
         // z = e(A,B)
         //let _check_z = zclone.send(PairingCheck::rand(&rng,&[(final_a, final_b)], final_zab)).unwrap(),
-        let pcheckz = PairingCheck::rand(&rng,&[(final_a, final_b)], final_zab),
+        let pcheckz = PairingCheck::rand_with_coeff(coeffs(Relation::TippZ),&[(final_a, final_b)], final_zab),
         //  final_aB.0 = T = e(A,v1)e(w1,B)
         //let check_ab0 = ab0clone.send(PairingCheck::rand(&rng,&[(final_a, &fvkey.0),(&fwkey.0, final_b)], final_tab)).unwrap(),
-        let pcheck_ab = PairingCheck::rand(&rng,&[(final_a, &fvkey.0),(&fwkey.0, final_b)], final_tab),
+        let pcheck_ab = PairingCheck::rand_with_coeff(coeffs(Relation::TippT),&[(final_a, &fvkey.0),(&fwkey.0, final_b)], final_tab),
 
         //  final_aB.1 = U = e(A,v2)e(w2,B)
         //let _check_ab1 = ab1clone.send(PairingCheck::rand(&rng,&[(final_a, &fvkey.1),(&fwkey.1, final_b)], final_uab)).unwrap(),
-        let pcheckab2 = PairingCheck::rand(&rng,&[(final_a, &fvkey.1),(&fwkey.1, final_b)], final_uab),
+        let pcheckab2 = PairingCheck::rand_with_coeff(coeffs(Relation::TippU),&[(final_a, &fvkey.1),(&fwkey.1, final_b)], final_uab),
 
         // MIPP
-        // Verify base inner product commitment
-        // Z ==  c ^ r
-        let final_z =
-            ip::multiexponentiation::<E::G1Affine>(&[final_c.clone()], &[final_r]),
         // Check commiment correctness
         // T = e(C,v1)
         //let _check_t = tclone.send(PairingCheck::rand(&rng,&[(final_c,&fvkey.0)],final_tc)).unwrap(),
-        let pcheckt = PairingCheck::rand(&rng,&[(final_c,&fvkey.0)],final_tc),
+        let pcheckt = PairingCheck::rand_with_coeff(coeffs(Relation::MippT),&[(final_c,&fvkey.0)],final_tc),
         // U = e(A,v2)
         //let _check_u = uclone.send(PairingCheck::rand(&rng,&[(final_c,&fvkey.1)],final_uc)).unwrap()
-        let pchecku = PairingCheck::rand(&rng,&[(final_c,&fvkey.1)],final_uc)
+        let pchecku = PairingCheck::rand_with_coeff(coeffs(Relation::MippU),&[(final_c,&fvkey.1)],final_uc)
     };
 
-    tclone.send(pcheckt).unwrap();
-    uclone.send(pchecku).unwrap();
-    ab0clone.send(pcheck_ab).unwrap();
-    ab1clone.send(pcheckab2).unwrap();
-    zclone.send(pcheckz).unwrap();
-    match final_z {
-        Err(e) => {
-            dbg!("TIPP verify: INVALID with multi exp: {}", e);
-            checks.send(PairingCheck::new_invalid()).unwrap();
-        }
-        Ok(z) => {
-            dbg!(format!(
-                "TIPP verify: parallel checks before merge: {}ms",
-                now.elapsed().as_millis()
+    // MIPP for the extra vectors, checked as for C
+    cfg_iter!(extensions)
+        .zip(cfg_iter!(ext_res))
+        .enumerate()
+        .for_each(|(i, (x, res))| {
+            let final_x = &x.mipp.final_x;
+            checks.send((
+                Relation::ExtensionMippT(i),
+                PairingCheck::rand_with_coeff(
+                    coeffs(Relation::ExtensionMippT(i)),
+                    &[(final_x, &fvkey.0)],
+                    &res.t,
+                ),
             ));
-            // only check that doesn't require pairing so we can give a tuple
-            // that will render the equation wrong in case it's false
-            if z != final_res.zc {
-                dbg!(format!(
-                    "tipp verify: INVALID final_z check {} vs {}",
-                    z, final_res.zc
-                ));
-                checks.send(PairingCheck::new_invalid()).unwrap()
-            }
-        }
-    };
+            checks.send((
+                Relation::ExtensionMippU(i),
+                PairingCheck::rand_with_coeff(
+                    coeffs(Relation::ExtensionMippU(i)),
+                    &[(final_x, &fvkey.1)],
+                    &res.u,
+                ),
+            ));
+        });
+
+    tclone.send((Relation::MippT, pcheckt));
+    uclone.send((Relation::MippU, pchecku));
+    ab0clone.send((Relation::TippT, pcheck_ab));
+    ab1clone.send((Relation::TippU, pcheckab2));
+    zclone.send((Relation::TippZ, pcheckz));
+    end_timer!(timer);
+    // only checks that don't require pairing so we give a tuple that will
+    // render the equation wrong in case it's false
+    for (relation, valid) in z_checks {
+        let check = match valid {
+            // nothing to check, it is only reported
+            true => PairingCheck::new(),
+            false => PairingCheck::new_invalid(),
+        };
+        checks.send((relation, check));
+    }
 }
 
 /// gipa_verify_tipp_mipp recurse on the proof and statement and produces the final
@@ -334,6 +1228,7 @@ fn verify_tipp_mipp<E: Pairing, R: Rng + Send, T: Transcript + Send>(
 /// between them, such as the KZG proof for commitment keys.
 fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
     proof: &AggregateProof<E>,
+    extensions: &[Extension<E>],
     r_shift: &E::ScalarField,
     transcript: &mut T,
 ) -> (
@@ -341,6 +1236,7 @@ fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
     E::ScalarField,
     Vec<E::ScalarField>,
     Vec<E::ScalarField>,
+    Vec<MippTUZ<E>>,
 ) {
     dbg!("gipa verify TIPP");
     let gipa = &proof.tmipp.gipa;
@@ -352,13 +1248,16 @@ fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
     let zs_ab = &gipa.z_ab;
     let zs_c = &gipa.z_c;
 
-    let now = Instant::now();
+    let timer = start_timer!(|| "TIPP verify: gipa challenge gen");
 
     let mut challenges = Vec::new();
     let mut challenges_inv = Vec::new();
 
     transcript.append(b"inner-product-ab", &proof.ip_ab);
-    transcript.append(b"comm-c", &proof.agg_c);
+    transcript.append_point(b"comm-c", &proof.agg_c);
+    for x in extensions {
+        transcript.append_point(b"comm-extension", x.agg);
+    }
     let mut c_inv: E::ScalarField =
         transcript.challenge_scalar::<E::ScalarField>(b"first-challenge");
     let mut c = c_inv.inverse().unwrap();
@@ -378,18 +1277,26 @@ fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
         let (zc_l, zc_r) = z_c;
 
         // Fiat-Shamir challenge
-        if i == 0 {
+        if i == 0 && !transcript.bind_gipa_rounds() {
             // already generated c_inv and c outside of the loop
         } else {
             transcript.append(b"c_inv", &c_inv);
             transcript.append(b"zab_l", zab_l);
             transcript.append(b"zab_r", zab_r);
-            transcript.append(b"zc_l", zc_l);
-            transcript.append(b"zc_r", zc_r);
+            transcript.append_point(b"zc_l", zc_l);
+            transcript.append_point(b"zc_r", zc_r);
             transcript.append(b"tab_l", tab_l);
             transcript.append(b"tab_r", tab_r);
             transcript.append(b"tuc_l", tuc_l);
             transcript.append(b"tuc_r", tuc_r);
+            for x in extensions {
+                let (tux_l, tux_r) = &x.mipp.comms[i];
+                let (zx_l, zx_r) = &x.mipp.z[i];
+                transcript.append_point(b"zx_l", zx_l);
+                transcript.append_point(b"zx_r", zx_r);
+                transcript.append(b"tux_l", tux_l);
+                transcript.append(b"tux_r", tux_r);
+            }
             c_inv = transcript.challenge_scalar::<E::ScalarField>(b"challenge_i");
             c = c_inv.inverse().unwrap();
         }
@@ -397,12 +1304,9 @@ fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
         challenges_inv.push(c_inv);
     }
 
-    dbg!(
-        "TIPP verify: gipa challenge gen took {}ms",
-        now.elapsed().as_millis()
-    );
+    end_timer!(timer);
 
-    let now = Instant::now();
+    let timer = start_timer!(|| "TIPP verify: gipa prep and accumulate");
     // output of the pair commitment T and U in TIPP -> COM((v,w),A,B)
     //let comab2 = proof.com_ab.clone();
     //let Output(t_ab, u_ab) = (comab2.0, comab2.1);
@@ -453,71 +1357,111 @@ fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
         ZC(&'a E::G1Affine, <E::ScalarField as PrimeField>::BigInt),
     }
 
-    let res = comms_ab
-        .par_iter()
-        .zip(zs_ab.par_iter())
-        .zip(comms_c.par_iter().zip(zs_c.par_iter()))
-        .zip(challenges.par_iter().zip(challenges_inv.par_iter()))
-        .flat_map(|(((comm_ab, z_ab), (comm_c, z_c)), (c, c_inv))| {
-            // T and U values for right and left for AB part
-            let (Output { 0: tab_l, 1: uab_l }, Output { 0: tab_r, 1: uab_r }) = comm_ab;
-            let (zab_l, zab_r) = z_ab;
-            // T and U values for right and left for C part
-            let (Output { 0: tc_l, 1: uc_l }, Output { 0: tc_r, 1: uc_r }) = comm_c;
-            let (zc_l, zc_r) = z_c;
-
-            let c_repr: <<E as Pairing>::ScalarField as PrimeField>::BigInt = (*c).into();
-            let c_inv_repr: <<E as Pairing>::ScalarField as PrimeField>::BigInt = (*c_inv).into();
-
-            // we multiple left side by x and right side by x^-1
-            vec![
-                Op::TAB::<E>(tab_l, c_repr),
-                Op::TAB(tab_r, c_inv_repr),
-                Op::UAB(uab_l, c_repr),
-                Op::UAB(uab_r, c_inv_repr),
-                Op::ZAB(zab_l, c_repr),
-                Op::ZAB(zab_r, c_inv_repr),
-                Op::TC::<E>(tc_l, c_repr),
-                Op::TC(tc_r, c_inv_repr),
-                Op::UC(uc_l, c_repr),
-                Op::UC(uc_r, c_inv_repr),
-                Op::ZC(zc_l, c_repr),
-                Op::ZC(zc_r, c_inv_repr),
-            ]
-        })
-        .fold(GipaTUZ::<E>::default, |mut res, op: Op<E>| {
-            match op {
-                Op::TAB(tx, c) => {
-                    let tx: <E as Pairing>::TargetField = tx.pow(c);
-                    res.tab.mul_assign(&tx);
-                }
-                Op::UAB(ux, c) => {
-                    let ux: <E as Pairing>::TargetField = ux.pow(c);
-                    res.uab.mul_assign(&ux);
-                }
-                Op::ZAB(zx, c) => {
-                    let zx: <E as Pairing>::TargetField = zx.pow(c);
-                    res.zab.mul_assign(&zx);
-                }
-                Op::TC(tx, c) => {
-                    let tx: <E as Pairing>::TargetField = tx.pow(c);
-                    res.tc.mul_assign(&tx);
-                }
-                Op::UC(ux, c) => {
-                    let ux: <E as Pairing>::TargetField = ux.pow(c);
-                    res.uc.mul_assign(&ux);
-                }
-                Op::ZC(zx, c) => {
-                    let zxp: E::G1 = zx.mul_bigint(c);
-                    res.zc.add_assign(&zxp);
+    let res = cfg_reduce!(
+        cfg_fold!(
+            cfg_iter!(comms_ab)
+                .zip(cfg_iter!(zs_ab))
+                .zip(cfg_iter!(comms_c).zip(cfg_iter!(zs_c)))
+                .zip(cfg_iter!(challenges).zip(cfg_iter!(challenges_inv)))
+                .flat_map(|(((comm_ab, z_ab), (comm_c, z_c)), (c, c_inv))| {
+                    // T and U values for right and left for AB part
+                    let (Output { 0: tab_l, 1: uab_l }, Output { 0: tab_r, 1: uab_r }) = comm_ab;
+                    let (zab_l, zab_r) = z_ab;
+                    // T and U values for right and left for C part
+                    let (Output { 0: tc_l, 1: uc_l }, Output { 0: tc_r, 1: uc_r }) = comm_c;
+                    let (zc_l, zc_r) = z_c;
+
+                    let c_repr: <<E as Pairing>::ScalarField as PrimeField>::BigInt = (*c).into();
+                    let c_inv_repr: <<E as Pairing>::ScalarField as PrimeField>::BigInt =
+                        (*c_inv).into();
+
+                    // we multiple left side by x and right side by x^-1
+                    vec![
+                        Op::TAB::<E>(tab_l, c_repr),
+                        Op::TAB(tab_r, c_inv_repr),
+                        Op::UAB(uab_l, c_repr),
+                        Op::UAB(uab_r, c_inv_repr),
+                        Op::ZAB(zab_l, c_repr),
+                        Op::ZAB(zab_r, c_inv_repr),
+                        Op::TC::<E>(tc_l, c_repr),
+                        Op::TC(tc_r, c_inv_repr),
+                        Op::UC(uc_l, c_repr),
+                        Op::UC(uc_r, c_inv_repr),
+                        Op::ZC(zc_l, c_repr),
+                        Op::ZC(zc_r, c_inv_repr),
+                    ]
+                }),
+            GipaTUZ::<E>::default,
+            |mut res, op: Op<E>| {
+                match op {
+                    Op::TAB(tx, c) => {
+                        let tx: <E as Pairing>::TargetField = tx.pow(c);
+                        res.tab.mul_assign(&tx);
+                    }
+                    Op::UAB(ux, c) => {
+                        let ux: <E as Pairing>::TargetField = ux.pow(c);
+                        res.uab.mul_assign(&ux);
+                    }
+                    Op::ZAB(zx, c) => {
+                        let zx: <E as Pairing>::TargetField = zx.pow(c);
+                        res.zab.mul_assign(&zx);
+                    }
+                    Op::TC(tx, c) => {
+                        let tx: <E as Pairing>::TargetField = tx.pow(c);
+                        res.tc.mul_assign(&tx);
+                    }
+                    Op::UC(ux, c) => {
+                        let ux: <E as Pairing>::TargetField = ux.pow(c);
+                        res.uc.mul_assign(&ux);
+                    }
+                    Op::ZC(zx, c) => {
+                        let zxp: E::G1 = zx.mul_bigint(c);
+                        res.zc.add_assign(&zxp);
+                    }
                 }
+                res
             }
-            res
-        })
-        .reduce(GipaTUZ::default, |mut acc_res, res| {
+        ),
+        GipaTUZ::default,
+        |mut acc_res, res| {
             acc_res.merge(&res);
             acc_res
-        });
+        }
+    );
+    // the extra vectors of MIPP are scaled the same way as C
+    let ext_res = cfg_iter!(extensions)
+        .map(|x| {
+            let mut res = MippTUZ::<E> {
+                t: x.com.0,
+                u: x.com.1,
+                z: x.agg.into_group(),
+            };
+            let scaled = cfg_reduce!(
+                cfg_iter!(x.mipp.comms)
+                    .zip(cfg_iter!(x.mipp.z))
+                    .zip(cfg_iter!(challenges).zip(cfg_iter!(challenges_inv)))
+                    .map(|((comm_x, z_x), (c, c_inv))| {
+                        let (Output { 0: tx_l, 1: ux_l }, Output { 0: tx_r, 1: ux_r }) = comm_x;
+                        let (zx_l, zx_r) = z_x;
+                        let c_repr: <E::ScalarField as PrimeField>::BigInt = (*c).into();
+                        let c_inv_repr: <E::ScalarField as PrimeField>::BigInt = (*c_inv).into();
+                        MippTUZ::<E> {
+                            t: tx_l.pow(c_repr) * tx_r.pow(c_inv_repr),
+                            u: ux_l.pow(c_repr) * ux_r.pow(c_inv_repr),
+                            z: zx_l.mul_bigint(c_repr) + zx_r.mul_bigint(c_inv_repr),
+                        }
+                    }),
+                MippTUZ::default,
+                |mut acc, res| {
+                    acc.merge(&res);
+                    acc
+                }
+            );
+            res.merge(&scaled);
+            res
+        })
+        .collect::<Vec<_>>();
+
     // we reverse the order because the polynomial evaluation routine expects
     // the challenges in reverse order.Doing it here allows us to compute the final_r
     // in log time. Challenges are used as well in the KZG verification checks.
@@ -534,24 +1478,21 @@ fn gipa_verify_tipp_mipp<E: Pairing, T: Transcript + Send>(
         &E::ScalarField::one(),
     );
 
-    dbg!(
-        "TIPP verify: gipa prep and accumulate took {}ms",
-        now.elapsed().as_millis()
-    );
-    (final_res, final_r, challenges, challenges_inv)
+    end_timer!(timer);
+    (final_res, final_r, challenges, challenges_inv, ext_res)
 }
 
 /// verify_kzg_opening_g2 takes a KZG opening, the final commitment key, SRS and
 /// any shift (in TIPP we shift the v commitment by r^-1) and returns a pairing
 /// tuple to check if the opening is correct or not.
-pub fn verify_kzg_v<E: Pairing, R: Rng + Send>(
-    v_srs: &VerifierSRS<E>,
+pub(crate) fn verify_kzg_v<E: Pairing>(
+    keys: &VerifierKeys<E>,
     final_vkey: &(E::G2Affine, E::G2Affine),
     vkey_opening: &KZGOpening<E::G2Affine>,
     challenges: &[E::ScalarField],
     kzg_challenge: &E::ScalarField,
-    rng: &Mutex<R>,
-    checks: Sender<PairingCheck<E>>,
+    coeffs: Coefficients<E>,
+    checks: Checks<E>,
 ) {
     // f_v(z)
     let vpoly_eval_z = polynomial_evaluation_product_form_from_transcript(
@@ -562,152 +1503,164 @@ pub fn verify_kzg_v<E: Pairing, R: Rng + Send>(
     // -g such that when we test a pairing equation we only need to check if
     // it's equal 1 at the end:
     // e(a,b) = e(c,d) <=> e(a,b)e(-c,d) = 1
+    let v_srs = keys.srs;
     let mut ng = v_srs.g.clone();
     // e(A,B) = e(C,D) <=> e(A,B)e(-C,D) == 1 <=> e(A,B)e(C,D)^-1 == 1
     ng = ng.neg();
     let ng = ng.into_affine();
 
-    let v1clone = checks.clone();
-    let v2clone = checks.clone();
+    let v1 = RelationCheck {
+        relation: Relation::KzgV1,
+        coeffs,
+        checks: checks.clone(),
+    };
+    let v2 = RelationCheck {
+        relation: Relation::KzgV2,
+        coeffs,
+        checks,
+    };
 
     par! {
         // e(g, C_f * h^{-y}) == e(v1 * g^{-x}, \pi) = 1
-        let _check1 = kzg_check_v::<E, R>(
-            v_srs,
+        let _check1 = kzg_check_v::<E>(
+            keys,
             ng,
-            *kzg_challenge,
-            vpoly_eval_z,
+            (*kzg_challenge, vpoly_eval_z),
             final_vkey.0.into_group(),
             v_srs.g_alpha,
             vkey_opening.0,
-            &rng,
-            v1clone,
+            v1,
         ),
 
         // e(g, C_f * h^{-y}) == e(v2 * g^{-x}, \pi) = 1
-        let _check2 = kzg_check_v::<E, R>(
-            v_srs,
+        let _check2 = kzg_check_v::<E>(
+            keys,
             ng,
-            *kzg_challenge,
-            vpoly_eval_z,
+            (*kzg_challenge, vpoly_eval_z),
             final_vkey.1.into_group(),
             v_srs.g_beta,
             vkey_opening.1,
-            &rng,
-            v2clone,
+            v2,
         )
     };
 }
 
-fn kzg_check_v<E: Pairing, R: Rng + Send>(
-    v_srs: &VerifierSRS<E>,
+/// A relation to check, with the coefficients of the relations and the
+/// checks its pairing check is sent to.
+struct RelationCheck<'a, E: Pairing> {
+    relation: Relation,
+    coeffs: Coefficients<'a, E>,
+    checks: Checks<'a, E>,
+}
+
+fn kzg_check_v<E: Pairing>(
+    keys: &VerifierKeys<E>,
     ng: E::G1Affine,
-    x: E::ScalarField,
-    y: E::ScalarField,
+    (x, y): (E::ScalarField, E::ScalarField),
     cf: E::G2,
     vk: E::G1,
     pi: E::G2Affine,
-    rng: &Mutex<R>,
-    checks: Sender<PairingCheck<E>>,
+    check: RelationCheck<E>,
 ) {
     // KZG Check: e(g, C_f * h^{-y}) = e(vk * g^{-x}, \pi)
     // Transformed, such that
     // e(-g, C_f * h^{-y}) * e(vk * g^{-x}, \pi) = 1
 
     // C_f - (y * h)
-    let b = sub!(cf, &mul!(v_srs.h, y)).into_affine();
+    let b = sub!(cf, &keys.mul_h(&y)).into_affine();
 
     // vk - (g * x)
-    let c = sub!(vk, &mul!(v_srs.g, x)).into_affine();
-    let p = PairingCheck::rand(
-        &rng,
+    let c = sub!(vk, &keys.mul_g(&x)).into_affine();
+    let p = PairingCheck::rand_with_coeff(
+        (check.coeffs)(check.relation),
         &[(&ng, &b), (&c, &pi)],
         &<E as Pairing>::TargetField::one(),
     );
-    checks.send(p).unwrap();
+    check.checks.send((check.relation, p));
 }
 
 /// Similar to verify_kzg_opening_g2 but for g1.
-pub fn verify_kzg_w<E: Pairing, R: Rng + Send>(
-    v_srs: &VerifierSRS<E>,
+pub(crate) fn verify_kzg_w<E: Pairing>(
+    keys: &VerifierKeys<E>,
     final_wkey: &(E::G1Affine, E::G1Affine),
     wkey_opening: &KZGOpening<E::G1Affine>,
     challenges: &[E::ScalarField],
     r_shift: &E::ScalarField,
     kzg_challenge: &E::ScalarField,
-    rng: &Mutex<R>,
-    checks: Sender<PairingCheck<E>>,
+    coeffs: Coefficients<E>,
+    checks: Checks<E>,
 ) {
+    let v_srs = keys.srs;
     // compute in parallel f(z) and z^n and then combines into f_w(z) = z^n * f(z)
     par! {
         let fz = polynomial_evaluation_product_form_from_transcript(challenges, kzg_challenge, r_shift),
-        let zn = kzg_challenge.pow(&[v_srs.n as u64])
+        let zn = kzg_challenge.pow([v_srs.n as u64])
     };
 
     let mut fwz = fz;
     fwz.mul_assign(&zn);
 
-    let mut nh = v_srs.h;
-    nh = nh.neg();
-    let nh = nh.into_affine();
+    let nh = &keys.neg_h();
 
-    let w1clone = checks.clone();
-    let w2clone = checks.clone();
+    let w1 = RelationCheck {
+        relation: Relation::KzgW1,
+        coeffs,
+        checks: checks.clone(),
+    };
+    let w2 = RelationCheck {
+        relation: Relation::KzgW2,
+        coeffs,
+        checks,
+    };
     par! {
         // e(C_f * g^{-y}, h) = e(\pi, w1 * h^{-x})
-        let _check1 = kzg_check_w::<E, R>(
-            v_srs,
+        let _check1 = kzg_check_w::<E>(
+            keys,
             nh,
-            *kzg_challenge,
-            fwz,
+            (*kzg_challenge, fwz),
             final_wkey.0.into_group(),
             v_srs.h_alpha,
             wkey_opening.0,
-            &rng,
-            w1clone,
+            w1,
         ),
 
         // e(C_f * g^{-y}, h) = e(\pi, w2 * h^{-x})
-        let _check2 = kzg_check_w::<E, R>(
-            v_srs,
+        let _check2 = kzg_check_w::<E>(
+            keys,
             nh,
-            *kzg_challenge,
-            fwz,
+            (*kzg_challenge, fwz),
             final_wkey.1.into_group(),
             v_srs.h_beta,
             wkey_opening.1,
-            &rng,
-            w2clone,
+            w2,
         )
     };
 }
 
-fn kzg_check_w<E: Pairing, R: Rng + Send>(
-    v_srs: &VerifierSRS<E>,
-    nh: E::G2Affine,
-    x: E::ScalarField,
-    y: E::ScalarField,
+fn kzg_check_w<E: Pairing>(
+    keys: &VerifierKeys<E>,
+    nh: &E::G2Prepared,
+    (x, y): (E::ScalarField, E::ScalarField),
     cf: E::G1,
     wk: E::G2,
     pi: E::G1Affine,
-    rng: &Mutex<R>,
-    checks: Sender<PairingCheck<E>>,
+    check: RelationCheck<E>,
 ) {
     // KZG Check: e(C_f * g^{-y}, h) = e(\pi, wk * h^{-x})
     // Transformed, such that
     // e(C_f * g^{-y}, -h) * e(\pi, wk * h^{-x}) = 1
 
     // C_f - (y * g)
-    let a = sub!(cf, &mul!(v_srs.g, y)).into_affine();
+    let a = sub!(cf, &keys.mul_g(&y)).into_affine();
 
     // wk - (x * h)
-    let d = sub!(wk, &mul!(v_srs.h, x)).into_affine();
-    let p = PairingCheck::rand(
-        &rng,
-        &[(&a, &nh), (&pi, &d)],
+    let d = E::G2Prepared::from(sub!(wk, &keys.mul_h(&x)).into_affine());
+    let p = PairingCheck::rand_prepared_with_coeff(
+        (check.coeffs)(check.relation),
+        &[(&a, nh), (&pi, &d)],
         &<E as Pairing>::TargetField::one(),
     );
-    checks.send(p).unwrap();
+    check.checks.send((check.relation, p));
 }
 
 /// Keeps track of the variables that have been sent by the prover and must
@@ -751,3 +1704,84 @@ where
         self.zc.add_assign(&other.zc);
     }
 }
+
+/// Keeps track of the variables that have been sent by the prover and must be
+/// multiplied together by the verifier for an extra vector of MIPP.
+struct MippTUZ<E: Pairing> {
+    pub t: <E as Pairing>::TargetField,
+    pub u: <E as Pairing>::TargetField,
+    pub z: E::G1,
+}
+
+impl<E> Default for MippTUZ<E>
+where
+    E: Pairing,
+{
+    fn default() -> Self {
+        Self {
+            t: <E as Pairing>::TargetField::one(),
+            u: <E as Pairing>::TargetField::one(),
+            z: E::G1::zero(),
+        }
+    }
+}
+
+impl<E> MippTUZ<E>
+where
+    E: Pairing,
+{
+    fn merge(&mut self, other: &Self) {
+        self.t.mul_assign(&other.t);
+        self.u.mul_assign(&other.u);
+        self.z.add_assign(&other.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aggregate_proofs_unchecked, srs, transcript::new_merlin_transcript};
+    use ark_bn254::{Bn254, Fr, G1Projective, G2Projective};
+    use ark_groth16::{prepare_verifying_key, Proof, VerifyingKey};
+    use ark_std::UniformRand;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_deterministic_check_is_reproducible() {
+        let n = 8;
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0u64);
+        let generic = srs::setup_fake_srs::<Bn254, _>(&mut rng, n);
+        let (prover_srs, ver_srs) = generic.specialize(n);
+        // the merged check doesn't need valid proofs to be reproduced
+        let vk = VerifyingKey::<Bn254> {
+            alpha_g1: G1Projective::rand(&mut rng).into_affine(),
+            beta_g2: G2Projective::rand(&mut rng).into_affine(),
+            gamma_g2: G2Projective::rand(&mut rng).into_affine(),
+            delta_g2: G2Projective::rand(&mut rng).into_affine(),
+            gamma_abc_g1: vec![G1Projective::rand(&mut rng).into_affine()],
+        };
+        let pvk = prepare_verifying_key(&vk);
+        let proofs = (0..n)
+            .map(|_| Proof::<Bn254> {
+                a: G1Projective::rand(&mut rng).into_affine(),
+                b: G2Projective::rand(&mut rng).into_affine(),
+                c: G1Projective::rand(&mut rng).into_affine(),
+            })
+            .collect::<Vec<_>>();
+        let inputs = vec![Vec::<Fr>::new(); n];
+        let mut transcript = new_merlin_transcript(b"test");
+        bind_statement(&mut transcript, &vk, &ver_srs, &inputs).unwrap();
+        let proof = aggregate_proofs_unchecked(&prover_srs, &mut transcript, &proofs).unwrap();
+
+        let run = || {
+            let mut transcript = new_merlin_transcript(b"test");
+            deterministic_check(&ver_srs, &pvk, &inputs, &proof, &mut transcript).unwrap()
+        };
+        let (expected, coeffs) = run();
+        for _ in 0..4 {
+            let (check, other) = run();
+            assert_eq!(check, expected);
+            assert_eq!(other, coeffs);
+        }
+    }
+}
@@ -1,13 +1,65 @@
 use ark_bn254::{Bn254, Fr};
-use ark_ff::One;
-use ark_groth16::{prepare_verifying_key, Groth16};
+use ark_ff::{One, Zero};
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey};
+use rand_chacha::ChaChaRng;
 use snarkpack;
+use snarkpack::srs::{GenericSRS, ProverSRS, VerifierSRS};
 use snarkpack::transcript::Transcript;
 
 mod constraints;
 use crate::constraints::Benchmark;
 use rand_core::SeedableRng;
 
+const NUM_CONSTRAINTS: usize = 100;
+
+/// Setup is what most tests start from: the Groth16 parameters of the
+/// benchmark circuit, an SRS specialized to the number of proofs and valid
+/// proofs with their public inputs. The rng is the one they were created with.
+struct Setup {
+    params: ProvingKey<Bn254>,
+    pvk: PreparedVerifyingKey<Bn254>,
+    srs: GenericSRS<Bn254>,
+    prover_srs: ProverSRS<Bn254>,
+    ver_srs: VerifierSRS<Bn254>,
+    proofs: Vec<Proof<Bn254>>,
+    all_inputs: Vec<Vec<Fr>>,
+    rng: ChaChaRng,
+}
+
+fn setup(nproofs: usize, seed: u64) -> Setup {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    let params = parameters(&mut rng);
+    let pvk = prepare_verifying_key(&params.vk);
+    let srs = snarkpack::srs::setup_fake_srs::<Bn254, _>(&mut rng, nproofs);
+    let (prover_srs, ver_srs) = srs.specialize(nproofs);
+    let proofs = (0..nproofs)
+        .map(|_| prove(&params, &mut rng))
+        .collect::<Vec<_>>();
+    Setup {
+        params,
+        pvk,
+        srs,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs: vec![[Fr::one(); 2].to_vec(); nproofs],
+        rng,
+    }
+}
+
+/// Returns new Groth16 parameters of the benchmark circuit.
+fn parameters(rng: &mut ChaChaRng) -> ProvingKey<Bn254> {
+    let c = Benchmark::<Fr>::new(NUM_CONSTRAINTS);
+    Groth16::<Bn254>::generate_random_parameters_with_reduction(c, rng).unwrap()
+}
+
+/// Returns a proof of the benchmark circuit, whose public inputs are [1, 1].
+fn prove(params: &ProvingKey<Bn254>, rng: &mut ChaChaRng) -> Proof<Bn254> {
+    let c = Benchmark::new(NUM_CONSTRAINTS);
+    Groth16::<Bn254>::create_random_proof_with_reduction(c, params, rng)
+        .expect("proof creation failed")
+}
+
 #[test]
 fn groth16_aggregation() {
     let num_constraints = 1000;
@@ -39,11 +91,47 @@ fn groth16_aggregation() {
 
     let mut prover_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
     prover_transcript.append(b"public-inputs", &all_inputs);
-    let aggregate_proof = snarkpack::aggregate_proofs(&prover_srs, &mut prover_transcript, &proofs)
-        .expect("error in aggregation");
+    let aggregate_proof =
+        snarkpack::aggregate_proofs_unchecked(&prover_srs, &mut prover_transcript, &proofs)
+            .expect("error in aggregation");
 
     let mut ver_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
     ver_transcript.append(b"public-inputs", &all_inputs);
+    snarkpack::verify_aggregate_proof_unchecked(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut ver_transcript,
+    )
+    .expect("error in verification");
+}
+
+#[test]
+fn groth16_aggregation_checked() {
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(8, 2);
+
+    let mut prover_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut prover_transcript,
+    )
+    .expect("error in aggregation");
+
+    let mut ver_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
     snarkpack::verify_aggregate_proof(
         &ver_srs,
         &pvk,
@@ -53,4 +141,1131 @@ fn groth16_aggregation() {
         &mut ver_transcript,
     )
     .expect("error in verification");
+
+    // workers computing the heavy parts produce the same proof
+    let mut workers = (0..2)
+        .map(|_| snarkpack::InProcessWorker::<Bn254>::new())
+        .collect::<Vec<_>>();
+    let mut prover_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
+    let distributed_proof = snarkpack::aggregate_proofs_distributed(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut workers,
+        &mut prover_transcript,
+    )
+    .expect("error in distributed aggregation");
+    assert_eq!(aggregate_proof, distributed_proof);
+
+    // the public inputs are bound in the transcript so a different statement
+    // must be rejected
+    let mut wrong_inputs = all_inputs.clone();
+    wrong_inputs[3][0] = Fr::from(2u64);
+    let mut ver_transcript = snarkpack::transcript::new_merlin_transcript(b"test aggregation");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &wrong_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut ver_transcript,
+    )
+    .expect_err("verification should have failed");
+}
+
+#[test]
+fn groth16_aggregator() {
+    let nproofs = 4;
+    let Setup {
+        params,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        ..
+    } = setup(nproofs, 3);
+
+    let aggregator = snarkpack::Aggregator::builder(prover_srs, params.vk.clone())
+        .transcript_label(b"test aggregator")
+        .context(b"block 42")
+        .build();
+    let aggregate_proof = aggregator
+        .aggregate(&proofs, &all_inputs)
+        .expect("error in aggregation");
+    aggregator
+        .verifier()
+        .verify(&aggregate_proof, &all_inputs)
+        .expect("error in verification");
+
+    let verifier = snarkpack::AggregateVerifier::builder(ver_srs.clone(), &params.vk)
+        .transcript_label(b"test aggregator")
+        .context(b"block 42")
+        .build();
+    verifier
+        .verify(&aggregate_proof, &all_inputs)
+        .expect("error in verification");
+
+    // a verifier with another context derives other challenges
+    let verifier = snarkpack::AggregateVerifier::builder(ver_srs, &params.vk)
+        .transcript_label(b"test aggregator")
+        .context(b"block 43")
+        .build();
+    verifier
+        .verify(&aggregate_proof, &all_inputs)
+        .expect_err("verification should have failed");
+}
+
+#[test]
+fn groth16_aggregation_batch() {
+    let mut rng = ChaChaRng::seed_from_u64(4u64);
+    // two circuits with different verifying keys and numbers of proofs
+    let mut statements = Vec::new();
+    for (nproofs, seed) in [(4, 41), (8, 42)] {
+        let Setup {
+            params,
+            pvk,
+            prover_srs,
+            ver_srs,
+            proofs,
+            all_inputs,
+            ..
+        } = setup(nproofs, seed);
+        let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test batch");
+        let aggregate_proof = snarkpack::aggregate_proofs(
+            &prover_srs,
+            &params.vk,
+            &all_inputs,
+            &proofs,
+            &mut transcript,
+        )
+        .expect("error in aggregation");
+        statements.push((ver_srs, pvk, all_inputs, aggregate_proof));
+    }
+
+    snarkpack::verify_aggregate_proofs_batch(batch_items(&statements), &mut rng)
+        .expect("error in batch verification");
+
+    // a single invalid statement invalidates the whole batch
+    statements[1].2[5][1] = Fr::from(2u64);
+    snarkpack::verify_aggregate_proofs_batch(batch_items(&statements), &mut rng)
+        .expect_err("batch verification should have failed");
+}
+
+type Statement = (
+    snarkpack::srs::VerifierSRS<Bn254>,
+    ark_groth16::PreparedVerifyingKey<Bn254>,
+    Vec<Vec<Fr>>,
+    snarkpack::AggregateProof<Bn254>,
+);
+
+fn batch_items(statements: &[Statement]) -> Vec<snarkpack::BatchItem<'_, Bn254, impl Transcript>> {
+    statements
+        .iter()
+        .map(|(srs, pvk, inputs, proof)| snarkpack::BatchItem {
+            srs,
+            pvk,
+            public_inputs: inputs,
+            proof,
+            transcript: snarkpack::transcript::new_merlin_transcript(b"test batch"),
+        })
+        .collect()
+}
+
+#[test]
+fn groth16_aggregation_report() {
+    use ark_ec::{AffineRepr, CurveGroup};
+    use snarkpack::Relation;
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 5);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test report");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let report = |proof: &snarkpack::AggregateProof<Bn254>, rng: &mut ChaChaRng| {
+        let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test report");
+        snarkpack::verify_aggregate_proof_with_report(
+            &ver_srs,
+            &pvk,
+            &all_inputs,
+            proof,
+            rng,
+            &mut transcript,
+        )
+        .expect("error in verification")
+    };
+    let valid = report(&aggregate_proof, &mut rng);
+    assert!(valid.is_valid());
+    assert_eq!(valid.relations.len(), 11);
+
+    // the final values are not part of the transcript so only the relations
+    // using them fail
+    let mut invalid = aggregate_proof.clone();
+    let gipa = &mut invalid.tmipp.gipa;
+    gipa.final_c = (gipa.final_c + ark_bn254::G1Affine::generator()).into_affine();
+    assert_eq!(
+        report(&invalid, &mut rng).failures(),
+        vec![Relation::MippT, Relation::MippU, Relation::MippZ]
+    );
+
+    let mut invalid = aggregate_proof.clone();
+    let opening = &mut invalid.tmipp.vkey_opening;
+    opening.0 = (opening.0 + ark_bn254::G2Affine::generator()).into_affine();
+    assert_eq!(report(&invalid, &mut rng).failures(), vec![Relation::KzgV1]);
+
+    let mut invalid = aggregate_proof;
+    let gipa = &mut invalid.tmipp.gipa;
+    gipa.final_a = (gipa.final_a + ark_bn254::G1Affine::generator()).into_affine();
+    assert_eq!(
+        report(&invalid, &mut rng).failures(),
+        vec![Relation::TippZ, Relation::TippT, Relation::TippU]
+    );
+}
+
+#[test]
+fn groth16_aggregation_deterministic() {
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        ..
+    } = setup(nproofs, 6);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test deterministic");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let verify = |proof: &snarkpack::AggregateProof<Bn254>| {
+        let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test deterministic");
+        snarkpack::verify_aggregate_proof_deterministic(
+            &ver_srs,
+            &pvk,
+            &all_inputs,
+            proof,
+            &mut transcript,
+        )
+    };
+    let coeffs = verify(&aggregate_proof).expect("error in verification");
+    assert_eq!(coeffs, verify(&aggregate_proof).unwrap());
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test deterministic");
+    let expected = snarkpack::deterministic_coefficients(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut transcript,
+    )
+    .unwrap();
+    assert_eq!(coeffs, expected);
+
+    let mut invalid = aggregate_proof;
+    let gipa = &mut invalid.tmipp.gipa;
+    gipa.final_a = (gipa.final_a + ark_bn254::G1Affine::generator()).into_affine();
+    verify(&invalid).expect_err("verification should have failed");
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test deterministic");
+    let other = snarkpack::deterministic_coefficients(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &invalid,
+        &mut transcript,
+    )
+    .unwrap();
+    assert!(other.iter().zip(&coeffs).all(|(a, b)| a.1 != b.1));
+}
+
+#[test]
+fn groth16_aggregation_prepared() {
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    let Setup {
+        params,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(4, 7);
+    let verifier = snarkpack::PreparedAggregateVerifier::new(ver_srs, &params.vk);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test prepared");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    // the prepared verifier is reused across verifications
+    for _ in 0..2 {
+        let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test prepared");
+        verifier
+            .verify(&all_inputs, &aggregate_proof, &mut rng, &mut transcript)
+            .expect("error in verification");
+    }
+
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test prepared");
+    let wrong_inputs = vec![
+        [Fr::one(); 2].to_vec(),
+        vec![Fr::one(), Fr::from(2u64)],
+        [Fr::one(); 2].to_vec(),
+        [Fr::one(); 2].to_vec(),
+    ];
+    verifier
+        .verify(&wrong_inputs, &aggregate_proof, &mut rng, &mut transcript)
+        .expect_err("verification should have failed with wrong inputs");
+
+    let mut invalid = aggregate_proof;
+    invalid.agg_c = (invalid.agg_c + ark_bn254::G1Affine::generator()).into_affine();
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test prepared");
+    verifier
+        .verify(&all_inputs, &invalid, &mut rng, &mut transcript)
+        .expect_err("verification should have failed with invalid proof");
+}
+
+#[test]
+fn groth16_aggregation_columnar_inputs() {
+    use snarkpack::{Column, ColumnMajorInputs, LazyInputs};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 8);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test columns");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let first = vec![Fr::one(); nproofs];
+    let columns = ColumnMajorInputs::new(
+        nproofs,
+        vec![Column::Values(&first), Column::Constant(Fr::one())],
+    )
+    .unwrap();
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test columns");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &columns,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification with columns");
+
+    // all inputs constant, so no power of r is needed at all
+    let lazy = LazyInputs::new(nproofs, 2, |_, _| Fr::zero())
+        .with_constant(0, Fr::one())
+        .with_constant(1, Fr::one());
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test columns");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &lazy,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification with lazy inputs");
+
+    let wrong = LazyInputs::new(nproofs, 2, |j, _| Fr::from(j as u64 / 3 + 1));
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test columns");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &wrong,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect_err("verification should have failed with wrong inputs");
+}
+
+#[test]
+fn groth16_aggregation_succinct() {
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 9);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test succinct");
+    let aggregate_proof = snarkpack::aggregate_proofs_succinct(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+    let commitments =
+        snarkpack::commit_public_inputs(&prover_srs, &params.vk, &all_inputs).unwrap();
+
+    // a verifying key without any IC point is rejected
+    let mut empty_vk = params.vk.clone();
+    empty_vk.gamma_abc_g1.clear();
+    assert!(matches!(
+        snarkpack::commit_public_inputs(&prover_srs, &empty_vk, &Vec::<Vec<Fr>>::new()),
+        Err(snarkpack::Error::MalformedVerifyingKey)
+    ));
+
+    let mut buffer = Vec::new();
+    aggregate_proof.write(&mut buffer).unwrap();
+    let read = snarkpack::SuccinctAggregateProof::<Bn254>::read(&buffer[..]).unwrap();
+    assert_eq!(aggregate_proof, read);
+
+    let verify = |commitments: &[ark_bn254::G1Affine],
+                  proof: &snarkpack::SuccinctAggregateProof<Bn254>,
+                  rng: &mut ChaChaRng| {
+        let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test succinct");
+        snarkpack::verify_succinct_aggregate_proof(
+            &ver_srs,
+            &pvk,
+            commitments,
+            proof,
+            rng,
+            &mut transcript,
+        )
+    };
+    verify(&commitments, &aggregate_proof, &mut rng).expect("error in verification");
+
+    // commitments to other inputs
+    let mut wrong_inputs = all_inputs.clone();
+    wrong_inputs[3][0] = Fr::from(2u64);
+    let wrong = snarkpack::commit_public_inputs(&prover_srs, &params.vk, &wrong_inputs).unwrap();
+    verify(&wrong, &aggregate_proof, &mut rng).expect_err("wrong commitments");
+
+    // evaluations not matching the commitments
+    let mut invalid = aggregate_proof.clone();
+    invalid.inputs.evaluations[1] += Fr::one();
+    verify(&commitments, &invalid, &mut rng).expect_err("wrong evaluations");
+    let mut invalid = aggregate_proof;
+    invalid.inputs.opening =
+        (invalid.inputs.opening + ark_bn254::G1Affine::generator()).into_affine();
+    verify(&commitments, &invalid, &mut rng).expect_err("wrong opening");
+}
+
+#[test]
+fn groth16_aggregation_with_policy() {
+    use ark_ec::{AffineRepr, CurveGroup};
+    use snarkpack::{Error, VerificationPolicy};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 10);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test policy");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let mut verify = |policy: &VerificationPolicy, proof: &snarkpack::AggregateProof<Bn254>| {
+        let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test policy");
+        snarkpack::verify_aggregate_proof_with_policy(
+            policy,
+            &ver_srs,
+            &pvk,
+            &all_inputs,
+            proof,
+            &mut rng,
+            &mut transcript,
+        )
+    };
+    let policy = VerificationPolicy::default()
+        .max_nproofs(nproofs)
+        .allow_srs(&ver_srs)
+        .max_public_inputs(2);
+    verify(&policy, &aggregate_proof).expect("error in verification");
+    verify(&VerificationPolicy::default(), &aggregate_proof).expect("error in verification");
+
+    let rejected = [
+        VerificationPolicy::default().max_nproofs(nproofs / 2),
+        VerificationPolicy::default().allow_srs_hash(vec![0u8; 32]),
+        VerificationPolicy::default().max_public_inputs(1),
+    ];
+    for policy in &rejected {
+        match verify(policy, &aggregate_proof) {
+            Err(Error::PolicyViolation(_)) => {}
+            res => panic!("policy should have been violated: {:?}", res),
+        }
+    }
+
+    // target group elements out of the prime order subgroup are rejected
+    let mut invalid = aggregate_proof.clone();
+    invalid.ip_ab += <Bn254 as ark_ec::pairing::Pairing>::TargetField::one();
+    match verify(&policy, &invalid) {
+        Err(Error::InvalidProof(msg)) => assert!(msg.contains("subgroup"), "{}", msg),
+        res => panic!("verification should have failed early: {:?}", res),
+    }
+
+    // the relation Z = C^r of MIPP is checked before any pairing
+    let mut invalid = aggregate_proof;
+    invalid.tmipp.gipa.final_c =
+        (invalid.tmipp.gipa.final_c + ark_bn254::G1Affine::generator()).into_affine();
+    match verify(&policy, &invalid) {
+        Err(Error::InvalidProof(msg)) => assert!(msg.contains("MippZ"), "{}", msg),
+        res => panic!("verification should have failed early: {:?}", res),
+    }
+}
+
+#[test]
+fn groth16_aggregation_pairing_check() {
+    use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+    use ark_std::UniformRand;
+    use snarkpack::PairingCheck;
+
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(4, 11);
+    let extra = prove(&params, &mut rng);
+    let aggregator = snarkpack::Aggregator::builder(prover_srs, params.vk.clone()).build();
+    let aggregate_proof = aggregator
+        .aggregate(&proofs, &all_inputs)
+        .expect("error in aggregation");
+
+    // pending checks of the aggregated proof
+    let pending = aggregator
+        .verifier()
+        .pairing_check(&aggregate_proof, &all_inputs)
+        .expect("error in verification");
+    assert!(pending.verify());
+    let mut transcript =
+        snarkpack::transcript::new_merlin_transcript(snarkpack::DEFAULT_TRANSCRIPT_LABEL);
+    let pending = snarkpack::aggregate_proof_pairing_check(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+    assert!(pending.verify());
+
+    // the Groth16 equation of another proof
+    let ic = params
+        .vk
+        .gamma_abc_g1
+        .iter()
+        .skip(1)
+        .fold(params.vk.gamma_abc_g1[0].into_group(), |acc, g| acc + g);
+    let neg_ic = (-ic).into_affine();
+    let neg_c = (-extra.c.into_group()).into_affine();
+    let alpha_beta = Bn254::pairing(params.vk.alpha_g1, params.vk.beta_g2).0;
+    let groth16 = PairingCheck::<Bn254>::rand(
+        &mut rng,
+        &[
+            (&extra.a, &extra.b),
+            (&neg_ic, &params.vk.gamma_g2),
+            (&neg_c, &params.vk.delta_g2),
+        ],
+        &alpha_beta,
+    );
+
+    // a BLS signature e(sig, h) = e(m, pk)
+    let sk = Fr::rand(&mut rng);
+    let h = ark_bn254::G2Affine::generator();
+    let pk = (h * sk).into_affine();
+    let m = ark_bn254::G1Projective::rand(&mut rng);
+    let neg_m = (-m).into_affine();
+    let bls = |sig: &ark_bn254::G1Affine, rng: &mut ChaChaRng| {
+        PairingCheck::<Bn254>::rand(rng, &[(sig, &h), (&neg_m, &pk)], &ark_bn254::Fq12::one())
+    };
+    let sig = (m * sk).into_affine();
+
+    let mut all = pending;
+    all.merge(&groth16);
+    all.merge(&bls(&sig, &mut rng));
+    assert!(all.verify());
+
+    let invalid_sig = (sig + ark_bn254::G1Affine::generator()).into_affine();
+    let mut all = pending;
+    all.merge(&groth16);
+    all.merge(&bls(&invalid_sig, &mut rng));
+    assert!(!all.verify());
+}
+
+#[test]
+fn groth16_aggregation_cost_estimate() {
+    use ark_serialize::{CanonicalSerialize, Compress};
+    use snarkpack::{Calibration, CostEstimator};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        srs,
+        prover_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 12);
+    let estimator = CostEstimator::<Bn254>::new(nproofs, 2).unwrap();
+    assert_eq!(srs.g_alpha_powers.len(), estimator.generic_srs_len());
+    let aggregate_proof = snarkpack::Aggregator::builder(prover_srs, params.vk.clone())
+        .build()
+        .aggregate(&proofs, &all_inputs)
+        .expect("error in aggregation");
+
+    for compress in [Compress::Yes, Compress::No] {
+        let mut buffer = Vec::new();
+        aggregate_proof
+            .serialize_with_mode(&mut buffer, compress)
+            .unwrap();
+        assert_eq!(estimator.proof_size(compress), buffer.len());
+        assert_eq!(aggregate_proof.serialized_size(compress), buffer.len());
+    }
+
+    let calibration = Calibration::measure::<Bn254, _>(&mut rng, 2);
+    assert!(calibration.miller_loop > Default::default());
+    assert!(calibration.g1_msm > Default::default());
+    let verify_time = estimator.verifier(true).time(&calibration);
+    assert!(verify_time > calibration.final_exponentiation);
+    assert!(estimator.prover().time(&calibration) > verify_time);
+}
+
+#[test]
+fn groth16_aggregation_keccak256_transcript() {
+    use snarkpack::transcript::Keccak256Transcript;
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 13);
+    let mut transcript = Keccak256Transcript::new(b"test keccak");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let mut transcript = Keccak256Transcript::new(b"test keccak");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+
+    // a merlin transcript derives other challenges
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test keccak");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect_err("verification should have failed");
+}
+
+#[test]
+fn groth16_aggregation_poseidon_transcript() {
+    use snarkpack::transcript::{poseidon_config, PoseidonTranscript};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 14);
+    let config = poseidon_config::<Fr>();
+    let mut transcript = PoseidonTranscript::with_config(b"test poseidon", &config);
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let mut transcript = PoseidonTranscript::<Fr>::new(b"test poseidon");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+
+    // a merlin transcript derives other challenges
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test poseidon");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect_err("verification should have failed");
+}
+
+#[test]
+fn groth16_aggregation_sha256_transcript() {
+    use snarkpack::transcript::Sha256Transcript;
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 15);
+    let mut transcript = Sha256Transcript::new(b"test sha256");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let mut transcript = Sha256Transcript::new(b"test sha256");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+
+    // a merlin transcript derives other challenges
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test sha256");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect_err("verification should have failed");
+}
+
+#[test]
+fn groth16_aggregation_merlin_challenge_modes() {
+    use snarkpack::transcript::MerlinTranscript;
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 18);
+    let modes = [
+        MerlinTranscript::new(b"test merlin"),
+        MerlinTranscript::new(b"test merlin").with_challenge_bits(128),
+        MerlinTranscript::new(b"test merlin").legacy(),
+    ];
+    for (i, mode) in modes.iter().enumerate() {
+        let aggregate_proof = snarkpack::aggregate_proofs(
+            &prover_srs,
+            &params.vk,
+            &all_inputs,
+            &proofs,
+            &mut mode.clone(),
+        )
+        .expect("error in aggregation");
+
+        // the proof only verifies with the challenges of the prover
+        for (j, other) in modes.iter().enumerate() {
+            let res = snarkpack::verify_aggregate_proof(
+                &ver_srs,
+                &pvk,
+                &all_inputs,
+                &aggregate_proof,
+                &mut rng,
+                &mut other.clone(),
+            );
+            assert_eq!(res.is_ok(), i == j);
+        }
+    }
+}
+
+#[test]
+fn groth16_aggregation_recording_transcript() {
+    use snarkpack::transcript::{
+        diff_recordings, new_merlin_transcript, RecordingTranscript, TranscriptEvent,
+    };
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 16);
+    let mut transcript = RecordingTranscript::new(new_merlin_transcript(b"test recording"));
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let (_, prover) = transcript.into_parts();
+
+    let mut transcript = RecordingTranscript::new(new_merlin_transcript(b"test recording"));
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+    assert_eq!(diff_recordings(&prover, transcript.events()), None);
+
+    // the verifier feeds the transcript with data the prover did not
+    let mut transcript = RecordingTranscript::new(new_merlin_transcript(b"test recording"));
+    transcript.append(b"block", &42u64);
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &aggregate_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect_err("verification should have failed");
+    let diff = diff_recordings(&prover, transcript.events()).expect("recordings should differ");
+    assert_eq!(diff.index, 0);
+    assert_eq!(diff.prover.as_ref(), prover.first());
+    assert!(matches!(
+        diff.verifier,
+        Some(TranscriptEvent::Append {
+            label: b"block",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn groth16_aggregation_envelope() {
+    use snarkpack::{read_envelope, write_envelope, EnvelopeHeader, ProofVariant};
+
+    let nproofs = 4;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 19);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test envelope");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+    let mut envelope = Vec::new();
+    write_envelope(&aggregate_proof, &ver_srs, Some(&params.vk), &mut envelope).unwrap();
+
+    let header = EnvelopeHeader::read(&envelope[..]).unwrap();
+    assert_eq!(header.version, snarkpack::ENVELOPE_VERSION);
+    assert_eq!(header.variant, ProofVariant::Aggregate);
+    assert_eq!(header.nproofs, nproofs as u32);
+    let read_proof: snarkpack::AggregateProof<Bn254> =
+        read_envelope(&envelope[..], &ver_srs, Some(&params.vk)).expect("invalid envelope");
+    assert_eq!(read_proof, aggregate_proof);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test envelope");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &read_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+
+    // each mismatch is reported before reading the proof
+    let expect_err = |res: Result<snarkpack::AggregateProof<Bn254>, snarkpack::Error>,
+                      msg: &str| match res {
+        Err(snarkpack::Error::InvalidEnvelope(e)) => assert!(e.contains(msg), "{}", e),
+        _ => panic!("expected an envelope error: {}", msg),
+    };
+    let other_srs = snarkpack::srs::setup_fake_srs::<Bn254, _>(&mut rng, 2 * nproofs);
+    let (_, other_srs) = other_srs.specialize(2 * nproofs);
+    expect_err(
+        read_envelope(&envelope[..], &other_srs, None),
+        "for an SRS of 8 proofs",
+    );
+    let other_params = parameters(&mut rng);
+    expect_err(
+        read_envelope(&envelope[..], &ver_srs, Some(&other_params.vk)),
+        "verifying key mismatch",
+    );
+    let bls_srs = snarkpack::srs::setup_fake_srs::<ark_bls12_381::Bls12_381, _>(&mut rng, 4);
+    let (_, bls_srs) = bls_srs.specialize(nproofs);
+    assert!(matches!(
+        read_envelope::<_, snarkpack::AggregateProof<_>, _>(&envelope[..], &bls_srs, None),
+        Err(snarkpack::Error::InvalidEnvelope(e)) if e == "curve mismatch"
+    ));
+    assert!(matches!(
+        read_envelope::<_, snarkpack::SuccinctAggregateProof<Bn254>, _>(
+            &envelope[..],
+            &ver_srs,
+            None
+        ),
+        Err(snarkpack::Error::InvalidEnvelope(_))
+    ));
+    let mut future = envelope.clone();
+    future[4] = 2;
+    expect_err(
+        read_envelope(&future[..], &ver_srs, None),
+        "unsupported version 2",
+    );
+    expect_err(
+        read_envelope(&envelope[1..], &ver_srs, None),
+        "invalid magic bytes",
+    );
+}
+
+#[test]
+fn groth16_aggregation_torus() {
+    use ark_serialize::CanonicalSerialize;
+    use snarkpack::{AggregateProof, CostEstimator};
+
+    let nproofs = 8;
+    let Setup {
+        params,
+        pvk,
+        prover_srs,
+        ver_srs,
+        proofs,
+        all_inputs,
+        mut rng,
+        ..
+    } = setup(nproofs, 20);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test torus");
+    let aggregate_proof = snarkpack::aggregate_proofs(
+        &prover_srs,
+        &params.vk,
+        &all_inputs,
+        &proofs,
+        &mut transcript,
+    )
+    .expect("error in aggregation");
+
+    let mut buffer = Vec::new();
+    aggregate_proof.write_torus(&mut buffer).unwrap();
+    let estimator = CostEstimator::<Bn254>::new(nproofs, 2).unwrap();
+    assert_eq!(buffer.len(), estimator.torus_proof_size());
+    assert!(buffer.len() < aggregate_proof.compressed_size());
+    let read_proof = AggregateProof::<Bn254>::read_torus(&buffer[..]).expect("invalid proof");
+    assert_eq!(read_proof, aggregate_proof);
+    let mut transcript = snarkpack::transcript::new_merlin_transcript(b"test torus");
+    snarkpack::verify_aggregate_proof(
+        &ver_srs,
+        &pvk,
+        &all_inputs,
+        &read_proof,
+        &mut rng,
+        &mut transcript,
+    )
+    .expect("error in verification");
+
+    // a corrupted element of the target group is rejected when read
+    buffer[5] ^= 1;
+    assert!(AggregateProof::<Bn254>::read_torus(&buffer[..]).is_err());
+}
+
+#[test]
+fn groth16_aggregation_bellperson_fixtures() {
+    use ark_bls12_381::{Bls12_381, Config};
+    use ark_groth16::Proof;
+    use ark_serialize::CanonicalDeserialize;
+    use snarkpack::bellperson::{self, AggregateVersion};
+
+    let include: &[u8] = b"snarkpack bellperson fixture";
+    let nproofs = 8;
+    let mut rng = ChaChaRng::seed_from_u64(17u64);
+    let srs = bellperson::read_srs::<Config, _>(&include_bytes!("fixtures/bellperson/srs.bin")[..])
+        .expect("invalid srs");
+    let vk = bellperson::read_verifying_key::<Config, _>(
+        &include_bytes!("fixtures/bellperson/vk.bin")[..],
+    )
+    .expect("invalid verifying key");
+    let pvk = prepare_verifying_key(&vk);
+    let proofs = include_bytes!("fixtures/bellperson/proofs.bin")
+        .chunks(192)
+        .map(|bytes| Proof::<Bls12_381>::deserialize_compressed(bytes).expect("invalid proof"))
+        .collect::<Vec<_>>();
+    let all_inputs = include_bytes!("fixtures/bellperson/inputs.bin")
+        .chunks(64)
+        .map(|bytes| {
+            bytes
+                .chunks(32)
+                .map(|x| ark_bls12_381::Fr::deserialize_compressed(x).expect("invalid input"))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for (proof, inputs) in proofs.iter().zip(&all_inputs) {
+        assert!(Groth16::<Bls12_381>::verify_proof(&pvk, proof, inputs).unwrap());
+    }
+
+    // the SRS is written back as bellperson wrote it
+    let mut bytes = Vec::new();
+    bellperson::write_srs(&srs, &mut bytes).unwrap();
+    assert_eq!(bytes, include_bytes!("fixtures/bellperson/srs.bin"));
+
+    let (prover_srs, ver_srs) = srs.specialize(nproofs);
+    let fixtures = [
+        (
+            AggregateVersion::V1,
+            AggregateVersion::V2,
+            &include_bytes!("fixtures/bellperson/aggregate.bin")[..],
+        ),
+        (
+            AggregateVersion::V2,
+            AggregateVersion::V1,
+            &include_bytes!("fixtures/bellperson/aggregate_v2.bin")[..],
+        ),
+    ];
+    for (version, other, expected) in fixtures {
+        // the aggregation of bellperson is reproduced byte for byte
+        let aggregate_proof = bellperson::aggregate_proofs(&prover_srs, include, &proofs, version)
+            .expect("error in aggregation");
+        let mut bytes = Vec::new();
+        bellperson::write_aggregate_proof(&aggregate_proof, &mut bytes).unwrap();
+        assert_eq!(bytes, expected);
+
+        // the proof of bellperson is read and verified
+        let read_proof =
+            bellperson::read_aggregate_proof::<Config, _>(expected).expect("invalid proof");
+        assert_eq!(read_proof, aggregate_proof);
+        let verify = |include: &[u8], version, rng: &mut ChaChaRng| {
+            bellperson::verify_aggregate_proof(
+                &ver_srs,
+                &pvk,
+                &all_inputs,
+                &read_proof,
+                include,
+                version,
+                rng,
+            )
+        };
+        verify(include, version, &mut rng).expect("error in verification");
+        verify(b"another include", version, &mut rng).expect_err("verification should have failed");
+        verify(include, other, &mut rng).expect_err("verification should have failed");
+        assert!(
+            bellperson::read_aggregate_proof::<Config, _>(&expected[..expected.len() - 1]).is_err()
+        );
+    }
 }
@@ -0,0 +1,88 @@
+//! Generates the bellperson fixtures of the compatibility tests of snarkpack.
+use std::fs::File;
+
+use bellperson::groth16::{
+    aggregate::{aggregate_proofs, setup_fake_srs, verify_aggregate_proof, AggregateVersion},
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+};
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use blstrs::{Bls12, Scalar};
+use ff::Field;
+use rand_core::SeedableRng;
+
+/// Proves the knowledge of x such that y = x^2 and z = x^3 for the public
+/// inputs y and z.
+struct Cube {
+    x: Option<Scalar>,
+}
+
+impl Circuit<Scalar> for Cube {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x_val = self.x;
+        let y_val = x_val.map(|x| x.square());
+        let z_val = x_val.map(|x| x.square() * x);
+        let x = cs.alloc(|| "x", || x_val.ok_or(SynthesisError::AssignmentMissing))?;
+        let y = cs.alloc_input(|| "y", || y_val.ok_or(SynthesisError::AssignmentMissing))?;
+        let z = cs.alloc_input(|| "z", || z_val.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce(|| "y = x * x", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+        cs.enforce(|| "z = y * x", |lc| lc + y, |lc| lc + x, |lc| lc + z);
+        Ok(())
+    }
+}
+
+fn main() {
+    let dir = std::env::args().nth(1).expect("output directory");
+    let nproofs = 8;
+    let include = b"snarkpack bellperson fixture";
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(46u64);
+
+    let params = generate_random_parameters::<Bls12, _, _>(Cube { x: None }, &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+    let xs = (0..nproofs).map(|i| Scalar::from(i as u64 + 2)).collect::<Vec<_>>();
+    let inputs = xs
+        .iter()
+        .map(|x| vec![x.square(), x.square() * x])
+        .collect::<Vec<_>>();
+    let proofs = xs
+        .iter()
+        .map(|x| create_random_proof(Cube { x: Some(*x) }, &params, &mut rng).unwrap())
+        .collect::<Vec<_>>();
+    for (proof, inputs) in proofs.iter().zip(&inputs) {
+        assert!(verify_proof(&pvk, proof, inputs).unwrap());
+    }
+
+    let srs = setup_fake_srs::<Bls12, _>(&mut rng, nproofs);
+    let (pk, vk) = srs.specialize(nproofs);
+    let versions = [AggregateVersion::V1, AggregateVersion::V2];
+    let aggregates =
+        versions.map(|version| aggregate_proofs(&pk, include, &proofs, version).unwrap());
+    for (aggregate, version) in aggregates.iter().zip(versions) {
+        let other = match version {
+            AggregateVersion::V1 => AggregateVersion::V2,
+            AggregateVersion::V2 => AggregateVersion::V1,
+        };
+        let mut verify = |include: &[u8], version| {
+            verify_aggregate_proof(&vk, &pvk, &mut rng, &inputs, aggregate, include, version)
+                .unwrap()
+        };
+        assert!(verify(include, version));
+        assert!(!verify(b"other", version));
+        // each version only verifies the aggregate proofs of its own
+        assert!(!verify(include, other));
+    }
+
+    let create = |name: &str| File::create(format!("{}/{}", dir, name)).unwrap();
+    srs.write(&mut create("srs.bin")).unwrap();
+    params.vk.write(&mut create("vk.bin")).unwrap();
+    let mut out = create("proofs.bin");
+    for proof in &proofs {
+        proof.write(&mut out).unwrap();
+    }
+    let mut out = create("inputs.bin");
+    for x in inputs.iter().flatten() {
+        std::io::Write::write_all(&mut out, &x.to_bytes_le()).unwrap();
+    }
+    let [v1, v2] = aggregates;
+    v1.write(&mut create("aggregate.bin")).unwrap();
+    v2.write(&mut create("aggregate_v2.bin")).unwrap();
+}
@@ -0,0 +1,147 @@
+#!/usr/bin/env python3
+"""Generates keccak256_transcript.txt independently of arkworks.
+
+Keccak-256 is implemented below from the Keccak reference, the points are the
+BN254 generators of EIP-196 and EIP-197, and the transcript follows the format
+documented on `Keccak256Transcript`. The only data taken as is is the pairing
+of the generators, in the order of arkworks.
+
+Usage: python3 keccak256_transcript.py > keccak256_transcript.txt
+"""
+
+R = 21888242871839275222246405745257275088548364400416034343698204186575808495617
+
+ROUND_CONSTANTS = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+]
+
+ROTATIONS = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+]
+
+MASK = (1 << 64) - 1
+
+
+def rol(x, n):
+    return ((x << n) | (x >> (64 - n))) & MASK if n else x
+
+
+def keccak_f(a):
+    for rc in ROUND_CONSTANTS:
+        c = [a[x][0] ^ a[x][1] ^ a[x][2] ^ a[x][3] ^ a[x][4] for x in range(5)]
+        d = [c[(x - 1) % 5] ^ rol(c[(x + 1) % 5], 1) for x in range(5)]
+        a = [[a[x][y] ^ d[x] for y in range(5)] for x in range(5)]
+        b = [[0] * 5 for _ in range(5)]
+        for x in range(5):
+            for y in range(5):
+                b[y][(2 * x + 3 * y) % 5] = rol(a[x][y], ROTATIONS[x][y])
+        a = [[b[x][y] ^ (~b[(x + 1) % 5][y] & b[(x + 2) % 5][y]) for y in range(5)]
+             for x in range(5)]
+        a[0][0] ^= rc
+    return a
+
+
+def keccak256(data):
+    rate = 136
+    padded = bytearray(data) + b"\x01"
+    padded += b"\x00" * (-len(padded) % rate)
+    padded[-1] |= 0x80
+    a = [[0] * 5 for _ in range(5)]
+    for i in range(0, len(padded), rate):
+        block = padded[i:i + rate]
+        for j in range(rate // 8):
+            x, y = j % 5, j // 5
+            a[x][y] ^= int.from_bytes(block[8 * j:8 * j + 8], "little")
+        a = keccak_f(a)
+    out = b""
+    for j in range(4):
+        out += a[j % 5][j // 5].to_bytes(8, "little")
+    return out
+
+
+def u32(n):
+    return n.to_bytes(4, "big")
+
+
+def fe(n):
+    return n.to_bytes(32, "big")
+
+
+class Transcript:
+    def __init__(self, label):
+        self.state = keccak256(label)
+
+    def append(self, label, data):
+        self.state = keccak256(self.state + u32(len(label)) + label + u32(len(data)) + data)
+
+    def challenge(self, label):
+        while True:
+            self.state = keccak256(self.state + u32(len(label)) + label)
+            wide = keccak256(self.state + b"\x00") + keccak256(self.state + b"\x01")
+            c = int.from_bytes(wide, "big") % R
+            if c != 0:
+                return c
+
+
+G1 = fe(1) + fe(2)
+# EIP-197: x = x_c1 * i + x_c0, encoded as x_c1 || x_c0 || y_c1 || y_c0
+G2 = (
+    fe(11559732032986387107991004021392285783925812861821192530917403151452391805634)
+    + fe(10857046999023057135944570762232829481370756359578518086990519993285655852781)
+    + fe(4082367875863433681332203403145435568316851327593401208105741076214120093531)
+    + fe(8495653923123431417604973247489272438418190587263600148770280649306958101930)
+)
+# e(G1, G2), the 12 coefficients in the order of arkworks
+GT = bytes.fromhex(
+    "262b253feda94cfe0da01bde280a3ed6f87e5feb898578b55e1f63739d870e95"
+    "02e02d2cc795a2000a1b1f823879abbd397c4dea0918ed66b49d34b48efb8a4a"
+    "13a9f2d6e29b128da5b1ad44b31977935fd2957387ecb1fc4e135402fdbd1de0"
+    "040ba9fa500f1a5c4b31984a74e68659c4b420bd699ce630b130b08a6ea1162b"
+    "0afc2f3fd870678fbe359d7f9873f052478f590b211ce30bf5e3eeaef89eafdb"
+    "1c54a530398c9064bdc662d929e645cadda9a712cc5a8243f9cddbd2d98dd1f0"
+    "095c0fbf5d5a1ac023794a0d856f92591ba990ecfd4b7aef5c0d58c5dc2429fe"
+    "14d3d6ca72d8a950a31dc10f7b4053c9e9ad9ebb590cb4a60f8215d4b99f2b4a"
+    "1dc0e7bbc3d70e6689dc206b4b91c85759dc1a23043c585fdfaf545838ca7429"
+    "0b53320e5a6488cb98a855ffc837d2a75ab90d61ac16cc1b7ab2cd3ed5e22b97"
+    "13a8afd3085dae4c6c91476ef36cd1d318ce07bac42a9c0f9bd7fddaf5ebd723"
+    "00f97b5221474526b601f3730a3afa965ceee1b343940c383e5314859e762c97"
+)
+
+assert keccak256(b"").hex() == "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+
+HEADER = """\
+# Test vectors of Keccak256Transcript on BN254, see its documentation for the
+# format. Each line is one operation on the transcript:
+# - new <label>: creates the transcript with the label
+# - append <label> <data>: absorbs the big-endian encoding of an element, in hex
+# - challenge <label> <scalar>: derives a challenge, in big-endian hex
+#
+# The elements appended are, in order: the u32 8, the generators of G1 and G2
+# with `append_point`, the scalar 42, the pairing of the generators and the
+# vector of scalars [1, 2]. Generated by keccak256_transcript.py."""
+
+print(HEADER)
+label = b"snarkpack-test-vectors"
+print("new " + label.decode())
+t = Transcript(label)
+for name, data in [
+    (b"nproofs", u32(8)),
+    (b"g1", G1),
+    (b"g2", G2),
+    (b"scalar", fe(42)),
+    (b"gt", GT),
+    (b"inputs", (2).to_bytes(8, "big") + fe(1) + fe(2)),
+]:
+    print("append %s %s" % (name.decode(), data.hex()))
+    t.append(name, data)
+for name in [b"r", b"x"]:
+    print("challenge %s %s" % (name.decode(), fe(t.challenge(name)).hex()))
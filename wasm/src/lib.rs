@@ -0,0 +1,177 @@
+//! JavaScript bindings of the snarkpack verifier for aggregated Groth16
+//! proofs on BN254.
+//!
+//! All values cross the boundary as bytes in the compressed arkworks
+//! encoding: the SRS written by `VerifierSRS::write`, the Groth16 verifying
+//! key, the proof written by `AggregateProof::write` and the public inputs as
+//! 32 bytes scalars laid out proof by proof. Errors are returned as a
+//! `VerifyError` telling which of them was rejected.
+//!
+//! The verifier runs single-threaded, the `parallel` feature of snarkpack
+//! being disabled on this target.
+use ark_bn254::{Bn254, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use snarkpack::{srs::VerifierSRS, AggregateProof, AggregateVerifier};
+use wasm_bindgen::prelude::*;
+
+/// Size in bytes of a compressed BN254 scalar.
+const SCALAR_SIZE: usize = 32;
+
+/// ErrorKind tells which of the inputs of the verifier has been rejected.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidSrs,
+    InvalidVerifyingKey,
+    InvalidProof,
+    InvalidPublicInputs,
+    VerificationFailed,
+}
+
+/// VerifyError is the error thrown to javascript, with the kind of the error
+/// and a message describing it.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct VerifyError {
+    kind: ErrorKind,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl VerifyError {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+}
+
+impl VerifyError {
+    fn new(kind: ErrorKind, message: impl ToString) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Srs is the verifier SRS, specialized to a number of proofs.
+#[wasm_bindgen]
+pub struct Srs(VerifierSRS<Bn254>);
+
+#[wasm_bindgen]
+impl Srs {
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Srs, VerifyError> {
+        VerifierSRS::read(bytes)
+            .map(Srs)
+            .map_err(|e| VerifyError::new(ErrorKind::InvalidSrs, e))
+    }
+
+    #[wasm_bindgen(getter, js_name = numProofs)]
+    pub fn num_proofs(&self) -> usize {
+        self.0.n
+    }
+}
+
+/// VerifyingKey is the Groth16 verifying key of the circuit of the aggregated
+/// proofs.
+#[wasm_bindgen]
+pub struct VerifyingKey(ark_groth16::VerifyingKey<Bn254>);
+
+#[wasm_bindgen]
+impl VerifyingKey {
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<VerifyingKey, VerifyError> {
+        let vk = ark_groth16::VerifyingKey::deserialize_compressed(bytes)
+            .map_err(|e| VerifyError::new(ErrorKind::InvalidVerifyingKey, e))?;
+        if vk.gamma_abc_g1.is_empty() {
+            return Err(VerifyError::new(
+                ErrorKind::InvalidVerifyingKey,
+                "verifying key without any IC point",
+            ));
+        }
+        Ok(VerifyingKey(vk))
+    }
+
+    /// Returns the number of public inputs of each proof.
+    #[wasm_bindgen(getter, js_name = numInputs)]
+    pub fn num_inputs(&self) -> usize {
+        self.0.gamma_abc_g1.len() - 1
+    }
+}
+
+/// Verifier verifies the proofs aggregated by a snarkpack `Aggregator` built
+/// with the default transcript label and the same context.
+#[wasm_bindgen]
+pub struct Verifier {
+    inner: AggregateVerifier<Bn254>,
+    nproofs: usize,
+    ninputs: usize,
+}
+
+#[wasm_bindgen]
+impl Verifier {
+    #[wasm_bindgen(constructor)]
+    pub fn new(srs: &Srs, vk: &VerifyingKey, context: Option<Vec<u8>>) -> Verifier {
+        let mut builder = AggregateVerifier::builder(srs.0.clone(), &vk.0);
+        if let Some(context) = context {
+            builder = builder.context(&context);
+        }
+        Verifier {
+            inner: builder.build(),
+            nproofs: srs.0.n,
+            ninputs: vk.num_inputs(),
+        }
+    }
+
+    /// Verifies the aggregate proof against the public inputs, given as the
+    /// concatenation of the 32 bytes scalars of each proof.
+    pub fn verify(&self, proof: &[u8], public_inputs: &[u8]) -> Result<(), VerifyError> {
+        let proof = AggregateProof::<Bn254>::read(proof)
+            .map_err(|e| VerifyError::new(ErrorKind::InvalidProof, e))?;
+        let inputs = self.parse_inputs(public_inputs)?;
+        self.inner
+            .verify(&proof, &inputs)
+            .map_err(|e| VerifyError::new(ErrorKind::VerificationFailed, e))
+    }
+}
+
+impl Verifier {
+    fn parse_inputs(&self, bytes: &[u8]) -> Result<Vec<Vec<Fr>>, VerifyError> {
+        let expected = self.nproofs * self.ninputs * SCALAR_SIZE;
+        if bytes.len() != expected {
+            return Err(VerifyError::new(
+                ErrorKind::InvalidPublicInputs,
+                format!("expected {} bytes, got {}", expected, bytes.len()),
+            ));
+        }
+        if self.ninputs == 0 {
+            return Ok(vec![Vec::new(); self.nproofs]);
+        }
+        bytes
+            .chunks(self.ninputs * SCALAR_SIZE)
+            .map(|proof| {
+                proof
+                    .chunks(SCALAR_SIZE)
+                    .map(Fr::deserialize_compressed)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| VerifyError::new(ErrorKind::InvalidPublicInputs, e))
+    }
+}
+
+/// Serializes public inputs in the layout expected by `Verifier::verify`.
+pub fn encode_public_inputs(inputs: &[Vec<Fr>]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(inputs.len() * SCALAR_SIZE);
+    for x in inputs.iter().flatten() {
+        x.serialize_compressed(&mut out)
+            .expect("writing to a vector never fails");
+    }
+    out
+}
@@ -0,0 +1,114 @@
+//! Runs with `cargo test` and the wasm-bindgen-test-runner configured in
+//! `.cargo/config.toml`.
+use ark_bn254::{Bn254, Fr};
+use ark_ff::One;
+use ark_groth16::Groth16;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand_core::SeedableRng;
+use snarkpack_wasm::{encode_public_inputs, ErrorKind, Srs, Verifier, VerifyingKey};
+use wasm_bindgen_test::*;
+
+#[path = "../../tests/constraints.rs"]
+mod constraints;
+use constraints::Benchmark;
+
+struct Fixture {
+    srs: Vec<u8>,
+    vk: Vec<u8>,
+    proof: Vec<u8>,
+    inputs: Vec<u8>,
+}
+
+fn fixture(context: &[u8]) -> Fixture {
+    let num_constraints = 10;
+    let nproofs = 8;
+    let mut rng = rand_chacha::ChaChaRng::seed_from_u64(1u64);
+    let params = {
+        let c = Benchmark::<Fr>::new(num_constraints);
+        Groth16::<Bn254>::generate_random_parameters_with_reduction(c, &mut rng).unwrap()
+    };
+    let srs = snarkpack::srs::setup_fake_srs::<Bn254, _>(&mut rng, nproofs);
+    let (prover_srs, ver_srs) = srs.specialize(nproofs);
+    let proofs = (0..nproofs)
+        .map(|_| {
+            let c = Benchmark::new(num_constraints);
+            Groth16::<Bn254>::create_random_proof_with_reduction(c, &params, &mut rng)
+                .expect("proof creation failed")
+        })
+        .collect::<Vec<_>>();
+    let all_inputs = vec![[Fr::one(); 2].to_vec(); nproofs];
+    let aggregate_proof = snarkpack::Aggregator::builder(prover_srs, params.vk.clone())
+        .context(context)
+        .build()
+        .aggregate(&proofs, &all_inputs)
+        .expect("error in aggregation");
+
+    let mut fixture = Fixture {
+        srs: Vec::new(),
+        vk: Vec::new(),
+        proof: Vec::new(),
+        inputs: encode_public_inputs(&all_inputs),
+    };
+    ver_srs.write(&mut fixture.srs).unwrap();
+    params.vk.serialize_compressed(&mut fixture.vk).unwrap();
+    aggregate_proof.write(&mut fixture.proof).unwrap();
+    fixture
+}
+
+fn verifier(fixture: &Fixture, context: Option<&[u8]>) -> Verifier {
+    let srs = Srs::from_bytes(&fixture.srs).expect("invalid srs");
+    let vk = VerifyingKey::from_bytes(&fixture.vk).expect("invalid verifying key");
+    assert_eq!(srs.num_proofs(), 8);
+    assert_eq!(vk.num_inputs(), 2);
+    Verifier::new(&srs, &vk, context.map(|c| c.to_vec()))
+}
+
+#[wasm_bindgen_test]
+fn verify_aggregate_proof() {
+    let fixture = fixture(b"block 42");
+    let verifier = verifier(&fixture, Some(b"block 42"));
+    verifier
+        .verify(&fixture.proof, &fixture.inputs)
+        .expect("error in verification");
+
+    // another context derives other challenges
+    let err = self::verifier(&fixture, None)
+        .verify(&fixture.proof, &fixture.inputs)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::VerificationFailed);
+
+    // wrong public inputs
+    let mut inputs = fixture.inputs.clone();
+    inputs[0] ^= 1;
+    let err = verifier.verify(&fixture.proof, &inputs).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::VerificationFailed);
+
+    // inputs of the wrong length
+    let err = verifier
+        .verify(&fixture.proof, &fixture.inputs[1..])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidPublicInputs);
+
+    // truncated proof
+    let err = verifier
+        .verify(&fixture.proof[..fixture.proof.len() - 1], &fixture.inputs)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidProof);
+}
+
+#[wasm_bindgen_test]
+fn reject_malformed_keys() {
+    let fixture = fixture(b"");
+    let err = Srs::from_bytes(&fixture.srs[..10]).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidSrs);
+    let err = VerifyingKey::from_bytes(&fixture.vk[1..]).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidVerifyingKey);
+
+    let mut vk =
+        ark_groth16::VerifyingKey::<Bn254>::deserialize_compressed(&fixture.vk[..]).unwrap();
+    vk.gamma_abc_g1.clear();
+    let mut bytes = Vec::new();
+    vk.serialize_compressed(&mut bytes).unwrap();
+    let err = VerifyingKey::from_bytes(&bytes).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidVerifyingKey);
+}